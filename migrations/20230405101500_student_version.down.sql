@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE students DROP COLUMN version;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE students ADD version INTEGER NOT NULL DEFAULT 1;
\ No newline at end of file
@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE students DROP COLUMN deleted_at;
+
+ALTER TABLE users DROP COLUMN role;
+DROP TYPE IF EXISTS user_role;
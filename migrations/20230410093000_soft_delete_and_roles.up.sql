@@ -0,0 +1,5 @@
+-- Add up migration script here
+CREATE TYPE user_role AS ENUM ('admin', 'teacher');
+ALTER TABLE users ADD role user_role NOT NULL DEFAULT 'teacher';
+
+ALTER TABLE students ADD deleted_at TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE students DROP COLUMN updated_at;
+ALTER TABLE students DROP COLUMN updated_by;
+ALTER TABLE students DROP COLUMN created_by;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE students ADD created_by UUID REFERENCES users(id);
+ALTER TABLE students ADD updated_by UUID REFERENCES users(id);
+ALTER TABLE students ADD updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+UPDATE students SET updated_at = registration_date;
+CREATE INDEX IF NOT EXISTS students_created_by_idx ON students(created_by);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS course_teachers;
+DROP TABLE IF EXISTS teachers;
@@ -0,0 +1,17 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS teachers(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    full_name VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    subjects TEXT[] NOT NULL DEFAULT '{}',
+    user_id UUID UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS course_teachers(
+    course_name TEXT NOT NULL,
+    teacher_id UUID NOT NULL,
+    PRIMARY KEY (course_name, teacher_id),
+    FOREIGN KEY (teacher_id) REFERENCES teachers(id)
+);
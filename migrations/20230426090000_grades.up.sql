@@ -0,0 +1,19 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS grades(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    student_id UUID NOT NULL,
+    course_name TEXT NOT NULL,
+    assessment VARCHAR(255) NOT NULL,
+    score DOUBLE PRECISION NOT NULL CHECK (score >= 0),
+    max_score DOUBLE PRECISION NOT NULL CHECK (max_score > 0),
+    weight DOUBLE PRECISION NOT NULL DEFAULT 1 CHECK (weight > 0),
+    graded_on DATE NOT NULL DEFAULT CURRENT_DATE,
+    teacher_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (score <= max_score),
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE,
+    FOREIGN KEY (teacher_id) REFERENCES teachers(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS grades_student_course_idx ON grades (student_id, course_name);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS attendance;
+DROP TYPE IF EXISTS attendance_status;
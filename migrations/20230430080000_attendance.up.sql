@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TYPE attendance_status AS ENUM ('present', 'absent', 'late', 'excused');
+
+CREATE TABLE IF NOT EXISTS attendance(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    student_id UUID NOT NULL,
+    course_name TEXT NOT NULL,
+    session_date DATE NOT NULL,
+    status attendance_status NOT NULL,
+    note TEXT,
+    recorded_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (student_id, course_name, session_date),
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS attendance_course_date_idx ON attendance (course_name, session_date);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS group_members;
+DROP TABLE IF EXISTS class_groups;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP VIEW IF EXISTS current_courses;
+ALTER TABLE courses DROP COLUMN IF EXISTS term_id;
+DROP TABLE IF EXISTS terms;
+DROP TABLE IF EXISTS academic_years;
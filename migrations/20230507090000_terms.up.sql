@@ -0,0 +1,31 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS academic_years(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(9) NOT NULL UNIQUE,
+    starts_on DATE NOT NULL,
+    ends_on DATE NOT NULL,
+    CHECK (starts_on < ends_on)
+);
+
+CREATE TABLE IF NOT EXISTS terms(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    academic_year_id UUID NOT NULL,
+    code VARCHAR(32) NOT NULL UNIQUE,
+    name VARCHAR(64) NOT NULL,
+    starts_on DATE NOT NULL,
+    ends_on DATE NOT NULL,
+    -- mirrors `academic.current_term` of the settings
+    is_current BOOLEAN NOT NULL DEFAULT false,
+    CHECK (starts_on < ends_on),
+    FOREIGN KEY (academic_year_id) REFERENCES academic_years(id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS terms_current_idx ON terms (is_current) WHERE is_current;
+
+-- enrollments are scoped to a term, existing ones stay unscoped
+ALTER TABLE courses ADD term_id UUID REFERENCES terms(id);
+
+-- enrollments of the current term, unscoped ones when no term is current
+CREATE VIEW current_courses AS
+    SELECT * FROM courses
+    WHERE term_id IS NOT DISTINCT FROM (SELECT id FROM terms WHERE is_current);
@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS schedule_slots(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    course_name TEXT NOT NULL,
+    term_id UUID,
+    -- ISO day of week, 1 is Monday
+    day_of_week SMALLINT NOT NULL CHECK (day_of_week BETWEEN 1 AND 7),
+    starts_at TIME NOT NULL,
+    ends_at TIME NOT NULL,
+    room VARCHAR(32) NOT NULL,
+    teacher_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (starts_at < ends_at),
+    FOREIGN KEY (term_id) REFERENCES terms(id),
+    FOREIGN KEY (teacher_id) REFERENCES teachers(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS schedule_slots_day_idx ON schedule_slots (day_of_week, starts_at);
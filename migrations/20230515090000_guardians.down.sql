@@ -0,0 +1,13 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS student_guardians;
+DROP TABLE IF EXISTS guardians;
+DROP TYPE IF EXISTS guardian_relationship;
+
+-- enum values can't be dropped, so the type is recreated without 'parent'
+UPDATE users SET role = 'teacher' WHERE role = 'parent';
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
+ALTER TYPE user_role RENAME TO user_role_old;
+CREATE TYPE user_role AS ENUM ('admin', 'teacher');
+ALTER TABLE users ALTER COLUMN role TYPE user_role USING role::text::user_role;
+ALTER TABLE users ALTER COLUMN role SET DEFAULT 'teacher';
+DROP TYPE user_role_old;
@@ -0,0 +1,28 @@
+-- Add up migration script here
+ALTER TYPE user_role ADD VALUE IF NOT EXISTS 'parent';
+
+CREATE TYPE guardian_relationship AS ENUM ('mother', 'father', 'grandparent', 'sibling', 'guardian', 'other');
+
+CREATE TABLE IF NOT EXISTS guardians(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    full_name VARCHAR(255) NOT NULL,
+    relationship guardian_relationship NOT NULL,
+    phone VARCHAR(20),
+    email VARCHAR(255) NOT NULL,
+    user_id UUID UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS student_guardians(
+    student_id UUID NOT NULL,
+    guardian_id UUID NOT NULL,
+    is_primary BOOLEAN NOT NULL DEFAULT false,
+    PRIMARY KEY (student_id, guardian_id),
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE,
+    FOREIGN KEY (guardian_id) REFERENCES guardians(id) ON DELETE CASCADE
+);
+
+-- only one primary contact per student
+CREATE UNIQUE INDEX IF NOT EXISTS student_guardians_primary_idx
+    ON student_guardians (student_id) WHERE is_primary;
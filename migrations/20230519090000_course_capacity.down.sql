@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP VIEW IF EXISTS current_waitlist;
+DROP TABLE IF EXISTS course_waitlist;
+DROP TABLE IF EXISTS course_capacities;
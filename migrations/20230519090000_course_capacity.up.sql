@@ -0,0 +1,23 @@
+-- Add up migration script here
+-- courses without a row have no seat limit
+CREATE TABLE IF NOT EXISTS course_capacities(
+    course_name TEXT NOT NULL PRIMARY KEY,
+    max_seats INTEGER NOT NULL CHECK (max_seats > 0)
+);
+
+-- ordered by id, the first entry gets the next free seat
+CREATE TABLE IF NOT EXISTS course_waitlist(
+    id BIGSERIAL PRIMARY KEY,
+    course_name TEXT NOT NULL,
+    student_id UUID NOT NULL,
+    term_id UUID REFERENCES terms(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS course_waitlist_course_idx ON course_waitlist (course_name, id);
+
+-- waitlist of the current term, like current_courses
+CREATE VIEW current_waitlist AS
+    SELECT * FROM course_waitlist
+    WHERE term_id IS NOT DISTINCT FROM (SELECT id FROM terms WHERE is_current);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS course_prerequisites(
+    course_name TEXT NOT NULL,
+    prerequisite TEXT NOT NULL,
+    -- weighted average of the prerequisite course in percents
+    min_grade DOUBLE PRECISION NOT NULL DEFAULT 60 CHECK (min_grade BETWEEN 0 AND 100),
+    PRIMARY KEY (course_name, prerequisite),
+    CHECK (course_name <> prerequisite)
+);
@@ -0,0 +1,14 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS submission_files;
+DROP TABLE IF EXISTS submissions;
+DROP TABLE IF EXISTS assignments;
+DROP TABLE IF EXISTS student_users;
+
+-- enum values can't be dropped, so the type is recreated without 'student'
+UPDATE users SET role = 'teacher' WHERE role = 'student';
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
+ALTER TYPE user_role RENAME TO user_role_old;
+CREATE TYPE user_role AS ENUM ('admin', 'teacher', 'parent');
+ALTER TABLE users ALTER COLUMN role TYPE user_role USING role::text::user_role;
+ALTER TABLE users ALTER COLUMN role SET DEFAULT 'teacher';
+DROP TYPE user_role_old;
@@ -0,0 +1,53 @@
+-- Add up migration script here
+ALTER TYPE user_role ADD VALUE IF NOT EXISTS 'student';
+
+-- login accounts of students
+CREATE TABLE IF NOT EXISTS student_users(
+    student_id UUID NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL UNIQUE,
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS assignments(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    course_name TEXT NOT NULL,
+    term_id UUID,
+    title VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    due_at TIMESTAMPTZ NOT NULL,
+    max_score DOUBLE PRECISION NOT NULL DEFAULT 100 CHECK (max_score > 0),
+    weight DOUBLE PRECISION NOT NULL DEFAULT 1 CHECK (weight > 0),
+    teacher_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (term_id) REFERENCES terms(id),
+    FOREIGN KEY (teacher_id) REFERENCES teachers(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS assignments_course_idx ON assignments (course_name, term_id);
+
+-- one submission per student, resubmitting replaces it
+CREATE TABLE IF NOT EXISTS submissions(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    assignment_id UUID NOT NULL,
+    student_id UUID NOT NULL,
+    content TEXT,
+    submitted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    late BOOLEAN NOT NULL DEFAULT false,
+    grade_id UUID,
+    UNIQUE (assignment_id, student_id),
+    FOREIGN KEY (assignment_id) REFERENCES assignments(id) ON DELETE CASCADE,
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE,
+    FOREIGN KEY (grade_id) REFERENCES grades(id) ON DELETE SET NULL
+);
+
+-- file contents are kept in the storage under `storage_key`
+CREATE TABLE IF NOT EXISTS submission_files(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    submission_id UUID NOT NULL,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size BIGINT NOT NULL,
+    storage_key TEXT NOT NULL,
+    FOREIGN KEY (submission_id) REFERENCES submissions(id) ON DELETE CASCADE
+);
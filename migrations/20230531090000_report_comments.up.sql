@@ -0,0 +1,18 @@
+-- Add up migration script here
+-- teachers' comments for the report card, one per course and term
+CREATE TABLE IF NOT EXISTS report_comments(
+    id UUID NOT NULL UNIQUE PRIMARY KEY DEFAULT uuid_generate_v4(),
+    student_id UUID NOT NULL,
+    course_name TEXT NOT NULL,
+    term_id UUID,
+    comment TEXT NOT NULL,
+    teacher_id UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE,
+    FOREIGN KEY (term_id) REFERENCES terms(id),
+    FOREIGN KEY (teacher_id) REFERENCES teachers(id) ON DELETE SET NULL
+);
+
+-- comments without a term are unique too
+CREATE UNIQUE INDEX IF NOT EXISTS report_comments_course_idx ON report_comments
+    (student_id, course_name, coalesce(term_id, '00000000-0000-0000-0000-000000000000'));
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS courses_course_name_trgm_idx;
+DROP INDEX IF EXISTS students_email_trgm_idx;
+DROP INDEX IF EXISTS students_full_name_trgm_idx;
+DROP EXTENSION IF EXISTS pg_trgm;
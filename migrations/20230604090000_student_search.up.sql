@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- trigram indexes of the students search
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS students_full_name_trgm_idx ON students USING gin (full_name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS students_email_trgm_idx ON students USING gin (email gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS courses_course_name_trgm_idx ON courses USING gin (course_name gin_trgm_ops);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- uploaded avatars, thumbnails are kept in the file storage
+CREATE TABLE IF NOT EXISTS student_avatars(
+    student_id UUID NOT NULL PRIMARY KEY,
+    -- md5 of the uploaded image
+    etag TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE
+);
@@ -0,0 +1,521 @@
+use actix_multipart::Multipart;
+use actix_web::{
+    delete, get,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    patch, post, put, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use futures_util::TryStreamExt;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_add_assignment, db_delete_assignment, db_get_assignment, db_get_course_assignments,
+        db_get_submission, db_get_submission_file, db_get_submissions, db_get_teacher_by_user,
+        db_grade_submission, db_patch_assignment, db_submit_assignment,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddAssignment, AuditContext, GradeSubmission, PatchAssignment, Role, UploadedFile},
+};
+
+//files of a submission
+const MAX_SUBMISSION_FILES: usize = 10;
+//content types of submission files served as they were uploaded, others are sent as binary data
+const ALLOWED_CONTENT_TYPES: [&str; 9] = [
+    "application/pdf",
+    "application/zip",
+    "application/msword",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "text/plain",
+    "text/csv",
+];
+
+#[post("/courses/{course_name}/assignments")]
+#[instrument(skip_all,name="Publish assignment",fields(uri = %req.uri(), method= %req.method(),course_name=%course_name,data=?form))]
+pub async fn post_assignment(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    form: web::Json<AddAssignment>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can publish assignments");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    //assignment is signed by the teacher linked to the logged user, if any
+    let teacher_id = db_get_teacher_by_user(auth.user_id, &state.connection)
+        .await
+        .ok()
+        .map(|t| t.id);
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_assignment(
+        &course_name,
+        form.into_inner(),
+        teacher_id,
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(assignment) => {
+            tracing::info!(
+                "Assignment_id {} - Assignment has been published",
+                assignment.id
+            );
+            HttpResponse::Ok().json(assignment)
+        }
+        Err(e) => {
+            tracing::error!("Failed to publish assignment: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/courses/{course_name}/assignments")]
+#[instrument(skip(state,req),name="Get course's assignments",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_course_assignments(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_course_assignments(&course_name, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get assignments of course '{}'", course_name);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get course's assignments: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/assignments/{assignment_id}")]
+#[instrument(skip(state,req),name="Get assignment",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_assignment(
+    assignment_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_assignment(*assignment_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get assignment with id: '{}'", assignment_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get assignment: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/assignments/{assignment_id}")]
+#[instrument(skip_all,name="Patch assignment",fields(uri = %req.uri(), method= %req.method(),assignment_id=%assignment_id,data=?form))]
+pub async fn patch_assignment(
+    assignment_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchAssignment>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can change assignments");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_assignment(*assignment_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(assignment) => {
+            tracing::info!(
+                "Assignment_id {} - Assignment has been patched",
+                assignment_id
+            );
+            HttpResponse::Ok().json(assignment)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/assignments/{assignment_id}")]
+#[instrument(skip(state,req,auth),name="Delete assignment",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_assignment(
+    assignment_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can delete assignments");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_assignment(
+        *assignment_id,
+        state.storage.as_ref(),
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(_) => {
+            tracing::info!(
+                "Successfully delete assignment with id: '{}'",
+                assignment_id
+            );
+            HttpResponse::Ok().json(format!("Deleted assignment:{}", assignment_id))
+        }
+        Err(e) => {
+            tracing::error!("Failed delete assignment: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//multipart form with the text in `content` and any number of `files`
+#[post("/assignments/{assignment_id}/submissions/{student_id}")]
+#[instrument(skip(state,req,auth,payload),name="Submit assignment",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn submit_assignment(
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+    payload: Multipart,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    let (assignment_id, student_id) = path.into_inner();
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Student]) {
+        tracing::error!("Only students can submit assignments");
+        return e.error_response();
+    }
+    if let Err(e) = auth
+        .require_student_access(student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    let (content, files) = match read_submission(payload, state.max_file_size).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Invalid submission: {}", e);
+            return e.error_response();
+        }
+    };
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_submit_assignment(
+        assignment_id,
+        student_id,
+        content,
+        files,
+        state.storage.as_ref(),
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(submission) => {
+            tracing::info!(
+                "Submission_id {} - Assignment has been submitted",
+                submission.id
+            );
+            HttpResponse::Ok().json(submission)
+        }
+        Err(e) => {
+            tracing::error!("Failed to submit assignment: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/assignments/{assignment_id}/submissions")]
+#[instrument(skip(state,req,auth),name="Get assignment's submissions",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_submissions(
+    assignment_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can get all submissions");
+        return e.error_response();
+    }
+
+    match db_get_submissions(*assignment_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!(
+                "Successfully get submissions of assignment '{}'",
+                assignment_id
+            );
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get assignment's submissions: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/assignments/{assignment_id}/submissions/{student_id}")]
+#[instrument(skip(state,req,auth),name="Get student's submission",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_submission(
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    let (assignment_id, student_id) = path.into_inner();
+    if let Err(e) = auth
+        .require_student_access(student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_submission(assignment_id, student_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get submission of student '{}'", student_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get submission: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[put("/assignments/{assignment_id}/submissions/{student_id}/grade")]
+#[instrument(skip_all,name="Grade submission",fields(uri = %req.uri(), method= %req.method(),data=?form))]
+pub async fn grade_submission(
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+    form: web::Json<GradeSubmission>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    let (assignment_id, student_id) = path.into_inner();
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can grade submissions");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let teacher_id = db_get_teacher_by_user(auth.user_id, &state.connection)
+        .await
+        .ok()
+        .map(|t| t.id);
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_grade_submission(
+        assignment_id,
+        student_id,
+        form.score,
+        teacher_id,
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(submission) => {
+            tracing::info!(
+                "Submission_id {} - Submission has been graded",
+                submission.id
+            );
+            HttpResponse::Ok().json(submission)
+        }
+        Err(e) => {
+            tracing::error!("Failed to grade submission: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/submissions/files/{file_id}")]
+#[instrument(skip(state,req,auth),name="Download submission's file",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_submission_file(
+    file_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    let (file, student_id, key) = match db_get_submission_file(*file_id, &state.connection).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Failed get file: {}", e);
+            return e.error_response();
+        }
+    };
+    if let Err(e) = auth
+        .require_student_access(student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match state.storage.get(&key).await {
+        Ok(data) => {
+            tracing::info!("Successfully get file with id: '{}'", file_id);
+            HttpResponse::Ok()
+                .content_type(served_content_type(&file.content_type))
+                .insert_header(("X-Content-Type-Options", "nosniff"))
+                .insert_header(ContentDisposition {
+                    disposition: DispositionType::Attachment,
+                    parameters: vec![DispositionParam::Filename(file.file_name)],
+                })
+                .body(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed read file from storage: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+async fn read_submission(
+    mut payload: Multipart,
+    max_file_size: usize,
+) -> Result<(Option<String>, Vec<UploadedFile>), Error> {
+    let mut content = None;
+    let mut files = Vec::new();
+
+    while let Some(mut field) = payload.try_next().await.map_err(multipart_error)? {
+        let name = field.name().to_string();
+        let file_name = field
+            .content_disposition()
+            .get_filename()
+            .map(str::to_string);
+        let content_type = field
+            .content_type()
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| "application/octet-stream".into());
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await.map_err(multipart_error)? {
+            if data.len() + chunk.len() > max_file_size {
+                return Err(Error::new(
+                    Some(format!("Maximum size is {} bytes", max_file_size)),
+                    Some("The file is too large".into()),
+                    ErrorTypes::ValidationError,
+                ));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        match (name.as_str(), file_name) {
+            ("content", None) => {
+                content = Some(String::from_utf8(data).map_err(|e| {
+                    Error::new(
+                        Some(e.to_string()),
+                        Some("Content must be a text".into()),
+                        ErrorTypes::ValidationError,
+                    )
+                })?)
+            }
+            ("files", Some(_)) if files.len() >= MAX_SUBMISSION_FILES => {
+                return Err(Error::new(
+                    Some(format!(
+                        "Maximum number of files is {}",
+                        MAX_SUBMISSION_FILES
+                    )),
+                    Some("Too many files".into()),
+                    ErrorTypes::ValidationError,
+                ))
+            }
+            ("files", Some(file_name)) if !file_name.is_empty() && file_name.len() <= 255 => files
+                .push(UploadedFile {
+                    file_name,
+                    content_type,
+                    data,
+                }),
+            _ => {
+                return Err(Error::new(
+                    Some(format!("Field: {}", name)),
+                    Some("Send `content` text and `files` with file names only".into()),
+                    ErrorTypes::ValidationError,
+                ))
+            }
+        }
+    }
+
+    if content.is_none() && files.is_empty() {
+        return Err(Error::new(
+            None,
+            Some("Submission must have a text or files".into()),
+            ErrorTypes::ValidationError,
+        ));
+    }
+    Ok((content, files))
+}
+
+pub(crate) fn multipart_error(e: actix_multipart::MultipartError) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Invalid multipart form".into()),
+        ErrorTypes::ValidationError,
+    )
+}
+
+//the content type is sent by the client, only the known ones are trusted
+fn served_content_type(content_type: &str) -> &str {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    if ALLOWED_CONTENT_TYPES
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(essence))
+    {
+        content_type
+    } else {
+        "application/octet-stream"
+    }
+}
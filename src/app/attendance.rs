@@ -0,0 +1,100 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{db_get_attendance_rates, db_get_student_attendance, db_mark_attendance},
+    errors::{Error, ErrorTypes},
+    schemas::{AttendanceFilter, AuditContext, MarkAttendance, Role},
+};
+
+#[post("/courses/{course_name}/attendance")]
+#[instrument(skip_all,name="Mark attendance",fields(uri = %req.uri(), method= %req.method(),course_name=%course_name,data=?form))]
+pub async fn mark_attendance(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    form: web::Json<MarkAttendance>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can mark attendance");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_mark_attendance(&course_name, form.into_inner(), &audit, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Attendance of course '{}' has been marked", course_name);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed to mark attendance: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/students/{student_id}/attendance")]
+#[instrument(skip(state,req,auth),name="Get student's attendance",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_student_attendance(
+    student_id: web::Path<Uuid>,
+    filter: web::Query<AttendanceFilter>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_student_attendance(*student_id, &filter, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get attendance of student '{}'", student_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's attendance: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/attendance/rates")]
+#[instrument(skip(state,req),name="Get attendance rates",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_attendance_rates(
+    filter: web::Query<AttendanceFilter>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_attendance_rates(&filter, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get attendance rates");
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get attendance rates: {}", e);
+            e.error_response()
+        }
+    }
+}
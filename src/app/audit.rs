@@ -0,0 +1,34 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use tracing::instrument;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::db_get_audit_log,
+    schemas::{AuditFilter, Role},
+};
+
+#[get("/audit")]
+#[instrument(skip(state,req,auth),name="Get audit log",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_audit_log(
+    filter: web::Query<AuditFilter>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can get audit log");
+        return e.error_response();
+    }
+
+    match db_get_audit_log(&filter, &state.connection).await {
+        Ok(entries) => {
+            tracing::info!("Successfully get {} audit entries", entries.len());
+            HttpResponse::Ok().json(entries)
+        }
+        Err(e) => {
+            tracing::error!("Failed get audit log: {}", e);
+            e.error_response()
+        }
+    }
+}
@@ -1,11 +1,60 @@
+use std::{io::Cursor, time::Duration};
+
+use actix_multipart::Multipart;
+use actix_web::{
+    delete, get,
+    http::header::{self, CacheControl, CacheDirective, ETag, EntityTag, IfNoneMatch},
+    put, web, HttpMessage, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use image::{
+    imageops::FilterType,
+    io::{Limits, Reader},
+    ImageFormat, ImageOutputFormat,
+};
 use md5;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::{identicon_png, initials_svg, multipart_error, AppState},
+    auth::JwtMiddleware,
+    db::{db_delete_avatar, db_get_avatar, db_get_student, db_set_avatar},
+    errors::{Error, ErrorTypes},
+    schemas::{AuditContext, AvatarQuery, Role, AVATAR_SIZES},
+};
 
+//avatars of students without an uploaded one
+#[async_trait]
+pub trait AvatarProvider: Send + Sync + std::fmt::Debug {
+    //URL saved with a new student, never fails, the default image is returned instead
+    async fn resolve(&self, student_id: Uuid, full_name: &str, email: &str) -> String;
+
+    //content type and the image generated by the server, None for images hosted elsewhere
+    fn render(
+        &self,
+        _full_name: &str,
+        _email: &str,
+        _size: u32,
+    ) -> Option<(&'static str, Vec<u8>)> {
+        None
+    }
+}
+
+//Gravatar images, optionally checked to exist
 #[derive(Debug)]
 pub struct AvatarClient {
     pub http_client: Client,
     pub base_url: String,
     pub default_img: String,
+    //ask Gravatar whether the email has an image, the default image is used if it hasn't
+    pub probe: bool,
+    //repeated requests after a failed probe
+    pub retries: u32,
 }
 
 impl AvatarClient {
@@ -14,15 +63,470 @@ impl AvatarClient {
             http_client: Client::new(),
             base_url,
             default_img,
+            probe: false,
+            retries: 0,
         }
     }
-    pub async fn send_request(&self, email: &str) -> Result<String, reqwest::Error> {
+
+    //check the images with the timeout of every request
+    pub fn with_probe(mut self, timeout: Duration, retries: u32) -> Self {
+        self.http_client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                tracing::error!("Can not build the avatar HTTP client: {}", e);
+                Client::new()
+            });
+        self.probe = true;
+        self.retries = retries;
+        self
+    }
+
+    fn hash_url(&self, email: &str) -> String {
         //create hash
-        let hash = md5::compute(email.as_bytes());
+        let hash = md5::compute(email.trim().to_lowercase().as_bytes());
+        format!("{}/{:x}", self.base_url.trim_end_matches('/'), hash)
+    }
+
+    pub fn avatar_url(&self, email: &str) -> String {
+        format!("{}?d={}", self.hash_url(email), self.default_img)
+    }
+
+    //Gravatar answers 404 for emails without an image with `d=404`
+    async fn probe(&self, email: &str) -> Result<bool, reqwest::Error> {
+        let response = self
+            .http_client
+            .get(format!("{}?d=404", self.hash_url(email)))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status().map(|_| true)
+    }
+}
+
+//Gravatar or a Libravatar-compatible server
+#[async_trait]
+impl AvatarProvider for AvatarClient {
+    #[instrument(name = "Resolve avatar", skip(self, _full_name))]
+    async fn resolve(&self, _student_id: Uuid, _full_name: &str, email: &str) -> String {
+        if !self.probe {
+            return self.avatar_url(email);
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.probe(email).await {
+                Ok(true) => return self.avatar_url(email),
+                Ok(false) => return self.default_img.clone(),
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    tracing::warn!("Avatar probe failed, attempt {}: {}", attempt, e);
+                    tokio::time::sleep(Duration::from_millis(100 * attempt as u64)).await;
+                }
+                Err(e) => {
+                    tracing::error!("Can not check the avatar, using the default one: {}", e);
+                    return self.default_img.clone();
+                }
+            }
+        }
+    }
+}
+
+//the same image for everybody, no requests are sent
+#[derive(Debug)]
+pub struct DefaultAvatar {
+    pub default_img: String,
+}
 
-        Ok(format!(
-            "{}/{:x}?d={}",
-            self.base_url, hash, self.default_img
-        ))
+#[async_trait]
+impl AvatarProvider for DefaultAvatar {
+    async fn resolve(&self, _student_id: Uuid, _full_name: &str, _email: &str) -> String {
+        self.default_img.clone()
     }
 }
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalAvatarStyle {
+    //SVG with the initials of the name
+    Initials,
+    //PNG pattern from the email
+    Identicon,
+}
+
+//images generated by the server, students get the URL of the avatar endpoint
+#[derive(Debug)]
+pub struct LocalAvatar {
+    pub style: LocalAvatarStyle,
+}
+
+#[async_trait]
+impl AvatarProvider for LocalAvatar {
+    async fn resolve(&self, student_id: Uuid, _full_name: &str, _email: &str) -> String {
+        local_avatar_url(student_id)
+    }
+
+    fn render(&self, full_name: &str, email: &str, size: u32) -> Option<(&'static str, Vec<u8>)> {
+        if self.style == LocalAvatarStyle::Identicon {
+            match identicon_png(email, size) {
+                Ok(png) => return Some(("image/png", png)),
+                Err(e) => tracing::error!("Can not render the identicon: {}", e),
+            }
+        }
+        Some(("image/svg+xml", initials_svg(full_name, size)))
+    }
+}
+
+pub fn local_avatar_url(student_id: Uuid) -> String {
+    format!("/students/{}/avatar", student_id)
+}
+
+#[put("/students/{student_id}/avatar")]
+#[instrument(skip(state,payload,req,auth),name="Upload student's avatar",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn upload_avatar(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    payload: Multipart,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher, Role::Student]) {
+        tracing::error!("User '{}' can't change avatars", auth.user_id);
+        return e.error_response();
+    }
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    let data = match read_avatar(payload, state.max_avatar_size).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Invalid avatar: {}", e);
+            return e.error_response();
+        }
+    };
+    let etag = format!("{:x}", md5::compute(&data));
+
+    //decoding and resizing take a while
+    let thumbnails = match web::block(move || avatar_thumbnails(&data)).await {
+        Ok(Ok(thumbnails)) => thumbnails,
+        Ok(Err(e)) => {
+            tracing::error!("Invalid avatar: {}", e);
+            return e.error_response();
+        }
+        Err(e) => {
+            let error = Error::new(
+                Some(e.to_string()),
+                Some("Can not create the thumbnails".into()),
+                ErrorTypes::DbError,
+            );
+            tracing::error!("Failed create thumbnails: {}", error);
+            return error.error_response();
+        }
+    };
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_set_avatar(
+        *student_id,
+        etag,
+        thumbnails,
+        state.storage.as_ref(),
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(avatar) => {
+            tracing::info!("Successfully upload avatar of student '{}'", student_id);
+            HttpResponse::Ok().json(avatar)
+        }
+        Err(e) => {
+            tracing::error!("Failed upload student's avatar: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//uploaded thumbnail, or redirect to the Gravatar image
+#[get("/students/{student_id}/avatar")]
+#[instrument(skip(state,query,req,auth),name="Get student's avatar",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_avatar(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    query: web::Query<AvatarQuery>,
+    provider: web::Data<dyn AvatarProvider>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = query.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let avatar = match db_get_avatar(*student_id, &state.connection).await {
+        Ok(Some(avatar)) => avatar,
+        Ok(None) => {
+            return match db_get_student(*student_id, &state.connection).await {
+                Ok(student) => {
+                    tracing::info!("Student '{}' has no uploaded avatar", student_id);
+                    //students of the local provider point to this endpoint, they get initials
+                    //even if the provider has changed
+                    let rendered = provider
+                        .render(&student.full_name, &student.email, query.size)
+                        .or_else(|| {
+                            (student.img == local_avatar_url(student.id)).then(|| {
+                                (
+                                    "image/svg+xml",
+                                    initials_svg(&student.full_name, query.size),
+                                )
+                            })
+                        });
+                    match rendered {
+                        Some((content_type, data)) => {
+                            let etag = EntityTag::new_strong(format!("{:x}", md5::compute(&data)));
+                            image_response(&req, etag, content_type, data)
+                        }
+                        None => {
+                            let separator = if student.img.contains('?') { '&' } else { '?' };
+                            HttpResponse::Found()
+                                .insert_header((
+                                    header::LOCATION,
+                                    format!("{}{}s={}", student.img, separator, query.size),
+                                ))
+                                .finish()
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed get student's avatar: {}", e);
+                    e.error_response()
+                }
+            };
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's avatar: {}", e);
+            return e.error_response();
+        }
+    };
+
+    let etag = EntityTag::new_strong(format!("{}-{}", avatar.etag, query.size));
+    if is_not_modified(&req, &etag) {
+        return not_modified_response(etag);
+    }
+
+    let key = avatar_key(*student_id, &avatar.etag, query.size);
+    match state.storage.get(&key).await {
+        Ok(data) => {
+            tracing::info!(
+                "Successfully get student's avatar with id: '{}'",
+                student_id
+            );
+            image_response(&req, etag, "image/png", data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's avatar: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/students/{student_id}/avatar")]
+#[instrument(skip(state,req,auth),name="Delete student's avatar",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_avatar(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher, Role::Student]) {
+        tracing::error!("User '{}' can't change avatars", auth.user_id);
+        return e.error_response();
+    }
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_avatar(
+        *student_id,
+        state.storage.as_ref(),
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(()) => {
+            tracing::info!("Successfully delete avatar of student '{}'", student_id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => {
+            tracing::error!("Failed delete student's avatar: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//browsers revalidate the image, unchanged one isn't sent again
+fn image_response(
+    req: &HttpRequest,
+    etag: EntityTag,
+    content_type: &str,
+    data: Vec<u8>,
+) -> HttpResponse {
+    if is_not_modified(req, &etag) {
+        return not_modified_response(etag);
+    }
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ETag(etag))
+        .insert_header(CacheControl(vec![
+            CacheDirective::Private,
+            CacheDirective::NoCache,
+        ]))
+        .body(data)
+}
+
+fn is_not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
+
+fn not_modified_response(etag: EntityTag) -> HttpResponse {
+    HttpResponse::NotModified()
+        .insert_header(ETag(etag))
+        .insert_header(CacheControl(vec![
+            CacheDirective::Private,
+            CacheDirective::NoCache,
+        ]))
+        .finish()
+}
+
+//the image is sent in the `avatar` field
+async fn read_avatar(mut payload: Multipart, max_size: usize) -> Result<Vec<u8>, Error> {
+    let mut avatar = None;
+    while let Some(mut field) = payload.try_next().await.map_err(multipart_error)? {
+        let is_image = field
+            .content_type()
+            .map(|m| m.type_().as_str() == "image")
+            .unwrap_or(false);
+        if field.name() != "avatar" || avatar.is_some() || !is_image {
+            return Err(Error::new(
+                Some(format!("Field: {}", field.name())),
+                Some("Send one image in the `avatar` field".into()),
+                ErrorTypes::ValidationError,
+            ));
+        }
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await.map_err(multipart_error)? {
+            if data.len() + chunk.len() > max_size {
+                return Err(Error::new(
+                    Some(format!("Maximum size is {} bytes", max_size)),
+                    Some("The image is too large".into()),
+                    ErrorTypes::ValidationError,
+                ));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        avatar = Some(data);
+    }
+
+    avatar.ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Send the image in the `avatar` field".into()),
+            ErrorTypes::ValidationError,
+        )
+    })
+}
+
+//uploaded images are at most that wide and high
+const MAX_IMAGE_DIMENSION: u32 = 4096;
+
+//formats of the uploaded avatars
+const AVATAR_FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+//storage key of the thumbnail, a new upload doesn't overwrite the files of the old one
+pub fn avatar_key(student_id: Uuid, etag: &str, size: u32) -> String {
+    format!("avatars/{}/{}-{}.png", student_id, etag, size)
+}
+
+//square PNG thumbnails of all avatar sizes, the image is cropped to the center
+pub fn avatar_thumbnails(data: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, Error> {
+    let mut reader = Reader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(invalid_image)?;
+    match reader.format() {
+        Some(format) if AVATAR_FORMATS.contains(&format) => {}
+        _ => {
+            return Err(Error::new(
+                None,
+                Some("Avatar must be a PNG, JPEG, GIF or WebP image".into()),
+                ErrorTypes::ValidationError,
+            ))
+        }
+    }
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    reader.limits(limits);
+    let image = reader.decode().map_err(invalid_image)?;
+
+    let mut thumbnails = Vec::with_capacity(AVATAR_SIZES.len());
+    for size in AVATAR_SIZES {
+        let mut thumbnail = Vec::new();
+        image
+            .resize_to_fill(size, size, FilterType::Lanczos3)
+            .write_to(&mut Cursor::new(&mut thumbnail), ImageOutputFormat::Png)
+            .map_err(|e| {
+                Error::new(
+                    Some(e.to_string()),
+                    Some("Can not create the thumbnail".into()),
+                    ErrorTypes::DbError,
+                )
+            })?;
+        thumbnails.push((size, thumbnail));
+    }
+    Ok(thumbnails)
+}
+
+fn invalid_image<E: std::fmt::Display>(e: E) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Invalid image".into()),
+        ErrorTypes::ValidationError,
+    )
+}
@@ -1,7 +1,16 @@
+use std::{num::NonZeroU64, sync::Arc};
+
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool, Pool, Postgres};
 
-use crate::schemas::Jwt;
+use crate::{
+    app::{
+        AvatarClient, AvatarProvider, DefaultAvatar, FileMailer, LocalAvatar, LocalAvatarStyle,
+        LocalStorage, Mailer, SmtpMailer, StatsCache, Storage,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::Jwt,
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct Settings {
@@ -9,6 +18,12 @@ pub struct Settings {
     pub app: AppSettings,
     pub avatar: AvatarSettings,
     pub auth: AuthSettings,
+    pub purge: PurgeSettings,
+    pub academic: AcademicSettings,
+    pub storage: StorageSettings,
+    pub stats: StatsSettings,
+    pub email: EmailSettings,
+    pub webhooks: WebhookSettings,
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AppSettings {
@@ -27,8 +42,62 @@ pub struct DbSettings {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AvatarSettings {
-    pub base_url: String,
+    pub provider: AvatarProviderKind,
+    //server of `gravatar` and `libravatar` providers, their public one if empty
+    pub base_url: Option<String>,
     pub default_img: String,
+    //size limit of the uploaded avatars
+    pub max_file_size_kb: usize,
+    //check that the server has an image of the email
+    pub probe: bool,
+    pub timeout_ms: u64,
+    pub retries: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarProviderKind {
+    Gravatar,
+    Libravatar,
+    //generated by the server, no requests are sent
+    Initials,
+    Identicon,
+    //everybody gets the default image
+    Default,
+}
+
+impl AvatarSettings {
+    pub fn build(&self) -> Arc<dyn AvatarProvider> {
+        let base_url = match self.provider {
+            AvatarProviderKind::Gravatar => "https://gravatar.com/avatar",
+            AvatarProviderKind::Libravatar => "https://seccdn.libravatar.org/avatar",
+            AvatarProviderKind::Initials => {
+                return Arc::new(LocalAvatar {
+                    style: LocalAvatarStyle::Initials,
+                })
+            }
+            AvatarProviderKind::Identicon => {
+                return Arc::new(LocalAvatar {
+                    style: LocalAvatarStyle::Identicon,
+                })
+            }
+            AvatarProviderKind::Default => {
+                return Arc::new(DefaultAvatar {
+                    default_img: self.default_img.clone(),
+                })
+            }
+        };
+        let base_url = self.base_url.clone().unwrap_or_else(|| base_url.into());
+        let client = AvatarClient::new(base_url, self.default_img.clone());
+        if self.probe {
+            Arc::new(client.with_probe(
+                std::time::Duration::from_millis(self.timeout_ms),
+                self.retries,
+            ))
+        } else {
+            Arc::new(client)
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -42,11 +111,146 @@ pub struct TokenConfig {
 pub struct AuthSettings {
     pub access: TokenConfig,
     pub refresh: TokenConfig,
+    //email of the user who gets the admin role on signup while there is no admin
+    pub bootstrap_admin: Option<String>,
+}
+
+//hard deletion of soft deleted students
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PurgeSettings {
+    //days deleted students are kept before purging
+    pub retention_days: i64,
+    //zero is rejected when the configuration is loaded
+    pub interval_minutes: NonZeroU64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AcademicSettings {
+    //code of the term new enrollments belong to
+    pub current_term: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Local,
+}
+
+//uploaded files
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StorageSettings {
+    pub backend: StorageBackend,
+    //directory of the local storage
+    pub path: String,
+    pub max_file_size_mb: usize,
+}
+
+impl StorageSettings {
+    pub fn build(&self) -> Arc<dyn Storage> {
+        match self.backend {
+            StorageBackend::Local => Arc::new(LocalStorage::new(&self.path)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StatsSettings {
+    //how long computed statistics are returned from the cache
+    pub cache_seconds: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailBackend {
+    Smtp,
+    //`.eml` files in a directory
+    File,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    //no authentication if empty
+    pub username: String,
+    pub password: String,
+    //STARTTLS, only local test servers can do without it
+    pub tls: bool,
+}
+
+//background sender of queued emails or webhook deliveries
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OutboxSettings {
+    //zero is rejected when the configuration is loaded
+    pub poll_interval_seconds: NonZeroU64,
+    //rows sent at once
+    pub batch_size: i64,
+    pub max_attempts: i32,
+    //delay after the first failure, it doubles after every next one
+    pub backoff_seconds: f64,
+}
+
+//outbound emails, they are queued and sent by a background job
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EmailSettings {
+    pub backend: EmailBackend,
+    pub from: String,
+    //directory of the file backend
+    pub path: String,
+    pub smtp: SmtpSettings,
+    #[serde(flatten)]
+    pub outbox: OutboxSettings,
+}
+
+impl EmailSettings {
+    pub fn build(&self) -> Result<Arc<dyn Mailer>, Error> {
+        let from = self
+            .from
+            .parse()
+            .map_err(|e: lettre::address::AddressError| {
+                Error::new(
+                    Some(e.to_string()),
+                    Some("Invalid sender of emails".into()),
+                    ErrorTypes::ValidationError,
+                )
+            })?;
+        match self.backend {
+            EmailBackend::File => Ok(Arc::new(FileMailer::new(from, &self.path))),
+            EmailBackend::Smtp => {
+                let credentials = (!self.smtp.username.is_empty())
+                    .then(|| (self.smtp.username.clone(), self.smtp.password.clone()));
+                Ok(Arc::new(SmtpMailer::new(
+                    from,
+                    &self.smtp.host,
+                    self.smtp.port,
+                    credentials,
+                    self.smtp.tls,
+                )?))
+            }
+        }
+    }
+}
+
+//delivery of the events to the registered webhooks
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct WebhookSettings {
+    #[serde(flatten)]
+    pub outbox: OutboxSettings,
+    //for every request
+    pub timeout_ms: u64,
 }
 
 pub struct AppState {
     pub connection: Pool<Postgres>,
     pub jwt: Jwt,
+    pub current_term: Option<String>,
+    pub storage: Arc<dyn Storage>,
+    //in bytes
+    pub max_file_size: usize,
+    pub stats: StatsCache,
+    //in bytes
+    pub max_avatar_size: usize,
+    pub bootstrap_admin: Option<String>,
 }
 
 enum Environment {
@@ -106,6 +310,12 @@ impl Settings {
         Ok(AppState {
             connection,
             jwt: Jwt::new(&self.auth.access, &self.auth.refresh),
+            current_term: self.academic.current_term.clone(),
+            storage: self.storage.build(),
+            max_file_size: self.storage.max_file_size_mb * 1024 * 1024,
+            max_avatar_size: self.avatar.max_file_size_kb * 1024,
+            stats: StatsCache::new(std::time::Duration::from_secs(self.stats.cache_seconds)),
+            bootstrap_admin: self.auth.bootstrap_admin.clone(),
         })
     }
 }
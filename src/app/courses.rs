@@ -0,0 +1,177 @@
+use actix_web::{delete, get, put, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use tracing::instrument;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_get_course_capacity, db_get_prerequisites, db_set_course_capacity, db_set_prerequisites,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AuditContext, Role, SetCapacity, SetPrerequisites},
+};
+
+#[get("/courses/{course_name}/capacity")]
+#[instrument(skip(state,req,auth),name="Get course's capacity",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_course_capacity(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't get course's waitlist", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_course_capacity(&course_name, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get capacity of course '{}'", course_name);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get course's capacity: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[put("/courses/{course_name}/capacity")]
+#[instrument(skip_all,name="Set course's capacity",fields(uri = %req.uri(), method= %req.method(),course_name=%course_name,data=?form))]
+pub async fn set_course_capacity(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    form: web::Json<SetCapacity>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change course's capacity");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_set_course_capacity(
+        &course_name,
+        Some(form.max_seats),
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(capacity) => {
+            tracing::info!("Course {} - Capacity has been set", course_name);
+            HttpResponse::Ok().json(capacity)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//the course has no seat limit anymore, everyone from the waitlist is enrolled
+#[delete("/courses/{course_name}/capacity")]
+#[instrument(skip(state,req,auth),name="Remove course's capacity",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_course_capacity(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change course's capacity");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_set_course_capacity(&course_name, None, &audit, &state.connection).await {
+        Ok(capacity) => {
+            tracing::info!("Course {} - Capacity has been removed", course_name);
+            HttpResponse::Ok().json(capacity)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/courses/{course_name}/prerequisites")]
+#[instrument(skip(state,req),name="Get course's prerequisites",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_prerequisites(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_prerequisites(&course_name, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get prerequisites of course '{}'", course_name);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get course's prerequisites: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[put("/courses/{course_name}/prerequisites")]
+#[instrument(skip_all,name="Set course's prerequisites",fields(uri = %req.uri(), method= %req.method(),course_name=%course_name,data=?form))]
+pub async fn set_prerequisites(
+    course_name: web::Path<String>,
+    state: web::Data<AppState>,
+    form: web::Json<SetPrerequisites>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change course's prerequisites");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_set_prerequisites(
+        &course_name,
+        form.into_inner().prerequisites,
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(prerequisites) => {
+            tracing::info!("Course {} - Prerequisites have been set", course_name);
+            HttpResponse::Ok().json(prerequisites)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{
+    errors::{Error, ErrorTypes},
+    schemas::QueuedEmail,
+};
+
+//sends emails of the outbox
+#[async_trait]
+pub trait Mailer: Send + Sync + std::fmt::Debug {
+    async fn send(&self, email: &QueuedEmail) -> Result<(), Error>;
+}
+
+//sends emails to the SMTP server
+#[derive(Debug)]
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    //without tls the connection isn't encrypted, only for local test servers
+    pub fn new(
+        from: Mailbox,
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+        tls: bool,
+    ) -> Result<Self, Error> {
+        let builder = if tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(mailer_error)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        let builder = match credentials {
+            Some((username, password)) => builder.credentials(Credentials::new(username, password)),
+            None => builder,
+        };
+        Ok(SmtpMailer {
+            from,
+            transport: builder.port(port).build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &QueuedEmail) -> Result<(), Error> {
+        let message = build_message(&self.from, email)?;
+        self.transport.send(message).await.map_err(mailer_error)?;
+        Ok(())
+    }
+}
+
+//writes emails as `<id>.eml` files to the directory, for tests and local development
+#[derive(Debug)]
+pub struct FileMailer {
+    from: Mailbox,
+    pub root: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(from: Mailbox, root: impl Into<PathBuf>) -> Self {
+        FileMailer {
+            from,
+            root: root.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &QueuedEmail) -> Result<(), Error> {
+        let message = build_message(&self.from, email)?;
+        tokio::fs::create_dir_all(&self.root)
+            .await
+            .map_err(mailer_error)?;
+        tokio::fs::write(
+            self.root.join(format!("{}.eml", email.id)),
+            message.formatted(),
+        )
+        .await
+        .map_err(mailer_error)
+    }
+}
+
+fn build_message(from: &Mailbox, email: &QueuedEmail) -> Result<Message, Error> {
+    let to: Mailbox = email
+        .recipient
+        .parse()
+        .map_err(|e: lettre::address::AddressError| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Invalid email recipient".into()),
+                ErrorTypes::ValidationError,
+            )
+        })?;
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body.clone())
+        .map_err(mailer_error)
+}
+
+fn mailer_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Can not send the email".into()),
+        ErrorTypes::MailerError,
+    )
+}
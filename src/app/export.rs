@@ -0,0 +1,213 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use futures_util::StreamExt;
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+use tracing::instrument;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::db_export_students,
+    errors::{Error, ErrorTypes},
+    schemas::{ExportFormat, ExportQuery, ExportedStudent, Role, StudentFilter},
+};
+
+//CSV and JSON Lines chunks are sent when they reach the size
+const CHUNK_SIZE: usize = 8 * 1024;
+//the whole XLSX workbook is kept in memory, larger exports must use CSV or JSON Lines
+const XLSX_MAX_ROWS: u32 = 50_000;
+
+#[get("/students/export")]
+#[instrument(skip_all,name="Export students",fields(uri = %req.uri(), method= %req.method(),query=?query))]
+pub async fn export_students(
+    state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+    filter: web::Query<StudentFilter>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't export students", auth.user_id);
+        return e.error_response();
+    }
+
+    let mut filter = filter.into_inner();
+    if filter.mine {
+        filter.created_by = Some(auth.user_id);
+    }
+
+    //only admins can see deleted students
+    if filter.include_deleted {
+        if let Err(e) = auth.require_role(&[Role::Admin]) {
+            tracing::error!("Only admins can export deleted students");
+            return e.error_response();
+        }
+    }
+
+    let format = query.format;
+    let connection = state.connection.clone();
+    let body = async_stream::stream! {
+        let mut encoder = match StudentsEncoder::new(format) {
+            Ok(encoder) => encoder,
+            Err(e) => {
+                tracing::error!("Failed export students: {}", e);
+                yield Err::<web::Bytes, actix_web::Error>(e.into());
+                return;
+            }
+        };
+        let mut students = db_export_students(&filter, &connection);
+        let mut count = 0;
+        while let Some(student) = students.next().await {
+            match student.and_then(|student| encoder.push(&student)) {
+                Ok(Some(chunk)) => yield Ok(web::Bytes::from(chunk)),
+                Ok(None) => {}
+                Err(e) => {
+                    //the response has already started, the connection is closed
+                    tracing::error!("Failed export students: {}", e);
+                    yield Err(e.into());
+                    return;
+                }
+            }
+            count += 1;
+        }
+
+        match encoder.finish() {
+            Ok(chunk) => {
+                tracing::info!("Successfully export {} students", count);
+                yield Ok(web::Bytes::from(chunk));
+            }
+            Err(e) => {
+                tracing::error!("Failed export students: {}", e);
+                yield Err(e.into());
+            }
+        }
+    };
+
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Jsonl => ("application/x-ndjson", "jsonl"),
+        ExportFormat::Xlsx => (
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "xlsx",
+        ),
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"students.{}\"", extension),
+        ))
+        .streaming(body)
+}
+
+//encodes exported students, CSV and JSON Lines are encoded row by row.
+//XLSX is a ZIP archive, so the file is only written at the end
+enum StudentsEncoder {
+    Csv(Vec<u8>),
+    Jsonl(Vec<u8>),
+    Xlsx(Box<Worksheet>, u32),
+}
+
+impl StudentsEncoder {
+    //writes the header
+    fn new(format: ExportFormat) -> Result<Self, Error> {
+        match format {
+            ExportFormat::Csv => {
+                let mut buffer = Vec::new();
+                write_csv_record(&mut buffer, ExportedStudent::HEADERS)?;
+                Ok(StudentsEncoder::Csv(buffer))
+            }
+            ExportFormat::Jsonl => Ok(StudentsEncoder::Jsonl(Vec::new())),
+            ExportFormat::Xlsx => {
+                let mut worksheet = Worksheet::new();
+                let bold = Format::new().set_bold();
+                for (col, header) in ExportedStudent::HEADERS.iter().enumerate() {
+                    worksheet
+                        .write_string_with_format(0, col as u16, *header, &bold)
+                        .map_err(export_error)?;
+                }
+                Ok(StudentsEncoder::Xlsx(Box::new(worksheet), 0))
+            }
+        }
+    }
+
+    //returns a chunk when there is enough data to send
+    fn push(&mut self, student: &ExportedStudent) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            StudentsEncoder::Csv(buffer) => {
+                write_csv_record(buffer, student.to_record())?;
+                Ok(take_chunk(buffer))
+            }
+            StudentsEncoder::Jsonl(buffer) => {
+                serde_json::to_writer(&mut *buffer, student).map_err(export_error)?;
+                buffer.push(b'\n');
+                Ok(take_chunk(buffer))
+            }
+            StudentsEncoder::Xlsx(worksheet, row) => {
+                if *row >= XLSX_MAX_ROWS {
+                    return Err(Error::new(
+                        None,
+                        Some(format!(
+                            "XLSX export is limited to {} students, use CSV or JSON Lines",
+                            XLSX_MAX_ROWS
+                        )),
+                        ErrorTypes::ValidationError,
+                    ));
+                }
+                *row += 1;
+                for (col, value) in student.to_record().iter().enumerate() {
+                    //age is a number
+                    if col == 2 {
+                        worksheet.write_number(*row, col as u16, student.age as f64)
+                    } else {
+                        worksheet.write_string(*row, col as u16, value)
+                    }
+                    .map_err(export_error)?;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    //returns the rest of the file
+    fn finish(self) -> Result<Vec<u8>, Error> {
+        match self {
+            StudentsEncoder::Csv(buffer) => Ok(buffer),
+            StudentsEncoder::Jsonl(buffer) => Ok(buffer),
+            StudentsEncoder::Xlsx(mut worksheet, _) => {
+                worksheet.set_name("Students").map_err(export_error)?;
+                let mut workbook = Workbook::new();
+                workbook.push_worksheet(*worksheet);
+                workbook.save_to_buffer().map_err(export_error)
+            }
+        }
+    }
+}
+
+//a writer per record, `into_inner` flushes it so the buffer always holds whole records
+fn write_csv_record<I, T>(buffer: &mut Vec<u8>, record: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(std::mem::take(buffer));
+    writer.write_record(record).map_err(export_error)?;
+    *buffer = writer.into_inner().map_err(export_error)?;
+    Ok(())
+}
+
+fn take_chunk(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    if buffer.len() < CHUNK_SIZE {
+        return None;
+    }
+    Some(std::mem::take(buffer))
+}
+
+fn export_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Can not export students".into()),
+        ErrorTypes::DbError,
+    )
+}
@@ -0,0 +1,132 @@
+use actix_web::{get, patch, post, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{db_add_grade, db_get_student_grades, db_get_teacher_by_user, db_patch_grade},
+    errors::{Error, ErrorTypes},
+    schemas::{AddGrade, AuditContext, PatchGrade, Role},
+};
+
+#[post("/students/{student_id}/grades")]
+#[instrument(skip_all,name="Record grade",fields(uri = %req.uri(), method= %req.method(),student_id=%student_id,data=?form))]
+pub async fn post_grade(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<AddGrade>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can record grades");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    //grade is signed by the teacher linked to the logged user, if any
+    let teacher_id = db_get_teacher_by_user(auth.user_id, &state.connection)
+        .await
+        .ok()
+        .map(|t| t.id);
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_grade(
+        *student_id,
+        form.into_inner(),
+        teacher_id,
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(grade) => {
+            tracing::info!("Grade_id {} - Grade has been recorded", grade.id);
+            HttpResponse::Ok().json(grade)
+        }
+        Err(e) => {
+            tracing::error!("Failed to record grade: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/students/{student_id}/grades")]
+#[instrument(skip(state,req,auth),name="Get student's grades",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_student_grades(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_student_grades(*student_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get grades of student '{}'", student_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's grades: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/grades/{grade_id}")]
+#[instrument(skip_all,name="Patch grade",fields(uri = %req.uri(), method= %req.method(),grade_id=%grade_id,data=?form))]
+pub async fn patch_grade(
+    grade_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchGrade>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can change grades");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_grade(*grade_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(grade) => {
+            tracing::info!("Grade_id {} - Grade has been changed", grade_id);
+            HttpResponse::Ok().json(grade)
+        }
+        Err(e) => {
+            tracing::error!("Failed to change grade: {}", e);
+            e.error_response()
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use actix_web::{
+    delete, get, patch, post, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_add_group, db_add_group_members, db_delete_group, db_get_all_groups, db_get_group,
+        db_get_group_students, db_get_homeroom_groups, db_get_teacher_by_user, db_patch_group,
+        db_remove_group_member,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddGroup, AuditContext, GroupFilter, GroupMembers, PatchGroup, Role},
+};
+
+#[post("/groups")]
+#[instrument(skip_all,name="Add new group",fields(uri = %req.uri(), method= %req.method(),data=?form))]
+pub async fn post_group(
+    state: web::Data<AppState>,
+    form: web::Json<AddGroup>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can add groups");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_group(form.into_inner(), &audit, &state.connection).await {
+        Ok(group) => {
+            tracing::info!("Group_id {} - Group has been saved", group.id);
+            HttpResponse::Ok().json(group)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/groups")]
+#[instrument(skip_all,name="Get all groups",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_all_groups(
+    state: web::Data<AppState>,
+    filter: web::Query<GroupFilter>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_all_groups(&filter, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get all groups");
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get all groups: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//groups of the logged homeroom teacher
+#[get("/groups/mine")]
+#[instrument(skip(state,req,auth),name="Get my groups",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_my_groups(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    let teacher = match db_get_teacher_by_user(auth.user_id, &state.connection).await {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!("Failed get teacher of user '{}': {}", auth.user_id, e);
+            return e.error_response();
+        }
+    };
+
+    match db_get_homeroom_groups(teacher.id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!(
+                "Successfully get homeroom groups of teacher '{}'",
+                teacher.id
+            );
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get homeroom groups: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/groups/{group_id}")]
+#[instrument(skip(state,req),name="Get group",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_group(
+    group_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_group(*group_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get group with id: '{}'", group_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get group: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/groups/{group_id}")]
+#[instrument(skip_all,name="Patch group",fields(uri = %req.uri(), method= %req.method(),group_id=%group_id,data=?form))]
+pub async fn patch_group(
+    group_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchGroup>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change groups");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_group(*group_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(group) => {
+            tracing::info!("Group_id {} - Group has been patched", group_id);
+            HttpResponse::Ok().json(group)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/groups/{group_id}")]
+#[instrument(skip(state,req,auth),name="Delete group",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_group(
+    group_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can delete groups");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_group(*group_id, &audit, &state.connection).await {
+        Ok(_) => {
+            tracing::info!("Successfully delete group with id: '{}'", group_id);
+            HttpResponse::Ok().json(format!("Deleted group:{}", group_id))
+        }
+        Err(e) => {
+            tracing::error!("Failed delete group: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/groups/{group_id}/students")]
+#[instrument(skip(state,req,auth),name="Get group's students",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_group_students(
+    group_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't get group's students", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_group_students(*group_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get students of group '{}'", group_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get group's students: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[post("/groups/{group_id}/students")]
+#[instrument(skip_all,name="Add students to group",fields(uri = %req.uri(), method= %req.method(),group_id=%group_id,data=?form))]
+pub async fn add_group_students(
+    group_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<GroupMembers>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change group's members");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_group_members(*group_id, &form.students, &audit, &state.connection).await {
+        Ok(group) => {
+            tracing::info!("Group_id {} - Students has been added", group_id);
+            HttpResponse::Ok().json(group)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/groups/{group_id}/students/{student_id}")]
+#[instrument(skip(state,req,auth),name="Remove student from group",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn remove_group_student(
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change group's members");
+        return e.error_response();
+    }
+
+    let (group_id, student_id) = path.into_inner();
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_remove_group_member(group_id, student_id, &audit, &state.connection).await {
+        Ok(group) => {
+            tracing::info!("Group_id {} - Student has been removed", group_id);
+            HttpResponse::Ok().json(group)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use actix_web::{
+    delete, get, patch, post, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_add_guardian, db_delete_guardian, db_get_all_guardians, db_get_guardian,
+        db_get_guardian_children, db_get_student_guardians, db_link_guardian, db_patch_guardian,
+        db_unlink_guardian,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddGuardian, AuditContext, LinkGuardian, PatchGuardian, Role},
+};
+
+#[post("/guardians")]
+#[instrument(skip_all,name="Add new guardian",fields(uri = %req.uri(), method= %req.method(),data=?form))]
+pub async fn post_guardian(
+    state: web::Data<AppState>,
+    form: web::Json<AddGuardian>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can add guardians");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_guardian(form.into_inner(), &audit, &state.connection).await {
+        Ok(guardian) => {
+            tracing::info!(
+                "Guardian_id {} - Guardian's details has been saved",
+                guardian.id
+            );
+            HttpResponse::Ok().json(guardian)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/guardians")]
+#[instrument(skip_all,name="Get all guardians",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_all_guardians(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't get all guardians", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_all_guardians(&state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get all guardians");
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get all guardians: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//children of the logged parent
+#[get("/guardians/me/students")]
+#[instrument(skip(state,req,auth),name="Get my children",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_my_children(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    match db_get_guardian_children(auth.user_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get children of user '{}'", auth.user_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get guardian's children: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/guardians/{guardian_id}")]
+#[instrument(skip(state,req,auth),name="Get guardian",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_guardian(
+    guardian_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't get other guardians", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_guardian(*guardian_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get guardian with id: '{}'", guardian_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get guardian: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/guardians/{guardian_id}")]
+#[instrument(skip_all,name="Patch guardian",fields(uri = %req.uri(), method= %req.method(),guardian_id=%guardian_id,data=?form))]
+pub async fn patch_guardian(
+    guardian_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchGuardian>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change guardians");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_guardian(*guardian_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(guardian) => {
+            tracing::info!(
+                "Guardian_id {} - Guardian details has been patched",
+                guardian_id
+            );
+            HttpResponse::Ok().json(guardian)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/guardians/{guardian_id}")]
+#[instrument(skip(state,req,auth),name="Delete guardian",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_guardian(
+    guardian_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can delete guardians");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_guardian(*guardian_id, &audit, &state.connection).await {
+        Ok(_) => {
+            tracing::info!("Successfully delete guardian with id: '{}'", guardian_id);
+            HttpResponse::Ok().json(format!("Deleted guardian:{}", guardian_id))
+        }
+        Err(e) => {
+            tracing::error!("Failed delete guardian: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/students/{student_id}/guardians")]
+#[instrument(skip(state,req,auth),name="Get student's guardians",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_student_guardians(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_student_guardians(*student_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get guardians of student '{}'", student_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's guardians: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[post("/students/{student_id}/guardians")]
+#[instrument(skip_all,name="Link guardian to student",fields(uri = %req.uri(), method= %req.method(),student_id=%student_id,data=?form))]
+pub async fn link_student_guardian(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<LinkGuardian>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change student's guardians");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_link_guardian(*student_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(guardians) => {
+            tracing::info!("Student_id {} - Guardian has been linked", student_id);
+            HttpResponse::Ok().json(guardians)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/students/{student_id}/guardians/{guardian_id}")]
+#[instrument(skip(state,req,auth),name="Unlink guardian from student",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn unlink_student_guardian(
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change student's guardians");
+        return e.error_response();
+    }
+
+    let (student_id, guardian_id) = path.into_inner();
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_unlink_guardian(student_id, guardian_id, &audit, &state.connection).await {
+        Ok(guardians) => {
+            tracing::info!("Student_id {} - Guardian has been unlinked", student_id);
+            HttpResponse::Ok().json(guardians)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::io::Cursor;
+
+use image::{imageops::FilterType, ImageOutputFormat, Rgb, RgbImage};
+
+//colors and patterns are derived from the hash, the same student always gets the same image
+fn color(hash: &[u8; 16]) -> Rgb<u8> {
+    //not too light for the white initials and background
+    Rgb([hash[0] / 2 + 64, hash[1] / 2 + 64, hash[2] / 2 + 64])
+}
+
+//first letters of the first two words, e.g. `AS` for `Anna Smith`
+pub fn initials(full_name: &str) -> String {
+    full_name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+//SVG with the initials on a colored circle
+pub fn initials_svg(full_name: &str, size: u32) -> Vec<u8> {
+    let Rgb([r, g, b]) = color(&md5::compute(full_name.as_bytes()).0);
+    let text: String = initials(full_name)
+        .chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 100 100"><circle cx="50" cy="50" r="50" fill="#{r:02x}{g:02x}{b:02x}"/><text x="50" y="50" dy="0.35em" text-anchor="middle" font-family="Helvetica, Arial, sans-serif" font-size="40" fill="#ffffff">{text}</text></svg>"##
+    )
+    .into_bytes()
+}
+
+//symmetric 5x5 pattern on a white background as PNG
+pub fn identicon_png(email: &str, size: u32) -> Result<Vec<u8>, image::ImageError> {
+    let hash = md5::compute(email.trim().to_lowercase().as_bytes()).0;
+    let foreground = color(&hash);
+    let background = Rgb([255, 255, 255]);
+
+    //the pattern with a margin of one cell
+    let mut pattern = RgbImage::from_pixel(7, 7, background);
+    for row in 0..5 {
+        for col in 0..3 {
+            //bits of the hash after the bytes of the color
+            let bit = row * 3 + col;
+            if (hash[3 + bit as usize / 8] >> (bit % 8)) & 1 == 1 {
+                pattern.put_pixel(col + 1, row + 1, foreground);
+                pattern.put_pixel(5 - col, row + 1, foreground);
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    image::imageops::resize(&pattern, size, size, FilterType::Nearest)
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)?;
+    Ok(png)
+}
@@ -0,0 +1,180 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+use crate::{
+    db::{
+        db_claim_deliveries, db_claim_emails, db_delivery_failed, db_delivery_succeeded,
+        db_email_failed, db_email_sent, db_purge_deleted_students,
+    },
+    errors::Error,
+    schemas::{AuditContext, QueuedDelivery},
+};
+
+use super::{
+    webhook_signature, EmailSettings, Mailer, OutboxSettings, PurgeSettings, Storage,
+    WebhookSettings,
+};
+
+//periodically hard delete students whose retention period is over
+pub async fn purge_deleted_students_job(
+    connection: PgPool,
+    storage: Arc<dyn Storage>,
+    settings: PurgeSettings,
+) {
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(settings.interval_minutes.get() * 60));
+
+    loop {
+        interval.tick().await;
+
+        let cutoff = OffsetDateTime::now_utc() - time::Duration::days(settings.retention_days);
+        match db_purge_deleted_students(
+            cutoff,
+            storage.as_ref(),
+            &AuditContext::system(),
+            &connection,
+        )
+        .await
+        {
+            Ok(purged) => tracing::info!("Purged {} deleted students", purged),
+            Err(e) => tracing::error!("Failed to purge deleted students: {}", e),
+        }
+    }
+}
+
+//sends a batch of the outbox every poll interval, `send_batch` returns the number of sent rows
+async fn poll_outbox<F, Fut>(settings: &OutboxSettings, name: &str, mut send_batch: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<usize, Error>>,
+{
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(settings.poll_interval_seconds.get()));
+
+    loop {
+        interval.tick().await;
+
+        match send_batch().await {
+            Ok(0) => {}
+            Ok(sent) => tracing::info!("Sent {} {}", sent, name),
+            Err(e) => tracing::error!("Failed to send {}: {}", name, e),
+        }
+    }
+}
+
+//periodically send queued emails
+pub async fn send_emails_job(connection: PgPool, mailer: Arc<dyn Mailer>, settings: EmailSettings) {
+    poll_outbox(&settings.outbox, "emails", || {
+        send_queued_emails(&connection, mailer.as_ref(), &settings)
+    })
+    .await
+}
+
+//send one batch of due emails, failed ones are retried later. Returns the number of sent emails
+pub async fn send_queued_emails(
+    connection: &PgPool,
+    mailer: &dyn Mailer,
+    settings: &EmailSettings,
+) -> Result<usize, Error> {
+    let emails = db_claim_emails(settings.outbox.batch_size, connection).await?;
+
+    let mut sent = 0;
+    for email in emails.iter() {
+        match mailer.send(email).await {
+            Ok(()) => {
+                db_email_sent(email.id, connection).await?;
+                sent += 1;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to send email {} (attempt {}): {}",
+                    email.id,
+                    email.attempts,
+                    e
+                );
+                db_email_failed(email, &e.to_string(), &settings.outbox, connection).await?;
+            }
+        }
+    }
+    Ok(sent)
+}
+
+//periodically deliver queued webhook events
+pub async fn deliver_webhooks_job(
+    connection: PgPool,
+    client: reqwest::Client,
+    settings: WebhookSettings,
+) {
+    poll_outbox(&settings.outbox, "webhook events", || {
+        deliver_queued_webhooks(&connection, &client, &settings)
+    })
+    .await
+}
+
+//send one batch of due deliveries, failed ones are retried later. Returns the number of delivered events
+pub async fn deliver_queued_webhooks(
+    connection: &PgPool,
+    client: &reqwest::Client,
+    settings: &WebhookSettings,
+) -> Result<usize, Error> {
+    let deliveries = db_claim_deliveries(settings.outbox.batch_size, connection).await?;
+
+    let mut delivered = 0;
+    for delivery in deliveries.iter() {
+        match send_webhook(client, delivery, settings).await {
+            Ok(status) => {
+                db_delivery_succeeded(delivery.id, status, connection).await?;
+                delivered += 1;
+            }
+            Err((status, e)) => {
+                tracing::warn!(
+                    "Failed to deliver webhook event {} (attempt {}): {}",
+                    delivery.id,
+                    delivery.attempts,
+                    e
+                );
+                db_delivery_failed(delivery, status, &e, &settings.outbox, connection).await?;
+            }
+        }
+    }
+    Ok(delivered)
+}
+
+//POST the signed payload, any 2xx status is a success.
+//Returns the status, on errors also the status if there was a response
+async fn send_webhook(
+    client: &reqwest::Client,
+    delivery: &QueuedDelivery,
+    settings: &WebhookSettings,
+) -> Result<i32, (Option<i32>, String)> {
+    let body = serde_json::to_vec(&delivery.payload).map_err(|e| (None, e.to_string()))?;
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+    let response = client
+        .post(&delivery.url)
+        .timeout(Duration::from_millis(settings.timeout_ms))
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Id", delivery.id.to_string())
+        .header("X-Webhook-Event", &delivery.event)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header(
+            "X-Webhook-Signature",
+            webhook_signature(&delivery.secret, timestamp, &body),
+        )
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16() as i32)
+    } else {
+        Err((
+            Some(status.as_u16() as i32),
+            format!("HTTP status {}", status),
+        ))
+    }
+}
@@ -1,23 +1,62 @@
+pub mod assignments;
+pub mod attendance;
+pub mod audit;
 pub mod avatar;
 pub mod configurations;
+pub mod courses;
+pub mod email;
+pub mod export;
+pub mod grades;
+pub mod groups;
+pub mod guardians;
+pub mod identicon;
+pub mod jobs;
+pub mod reports;
 pub mod services;
+pub mod stats;
+pub mod storage;
+pub mod teachers;
+pub mod terms;
+pub mod timetable;
+pub mod webhooks;
 
+pub use assignments::*;
+pub use attendance::*;
+pub use audit::*;
 pub use avatar::*;
 pub use configurations::*;
+pub use courses::*;
+pub use email::*;
+pub use export::*;
+pub use grades::*;
+pub use groups::*;
+pub use guardians::*;
+pub use identicon::*;
+pub use jobs::*;
+pub use reports::*;
 pub use services::*;
+pub use stats::*;
+pub use storage::*;
+pub use teachers::*;
+pub use terms::*;
+pub use timetable::*;
+pub use webhooks::*;
 
-use std::net::TcpListener;
+use std::{net::TcpListener, sync::Arc};
 
-use crate::auth::{login_user, logout_handler, refresh_auth, register_user};
+use crate::auth::{
+    change_user_role, link_student_account, login_user, logout_handler, refresh_auth,
+    register_user,
+};
 use actix_web::{dev::Server, middleware::Logger, web, App, HttpServer};
 
 pub fn run_app(
     listener: TcpListener,
     app_state: AppState,
-    avatar_client: AvatarClient,
+    avatar_client: Arc<dyn AvatarProvider>,
 ) -> std::io::Result<Server> {
     let data = web::Data::new(app_state);
-    let avatar_client = web::Data::new(avatar_client);
+    let avatar_client: web::Data<dyn AvatarProvider> = web::Data::from(avatar_client);
 
     let server = HttpServer::new(move || {
         App::new()
@@ -27,15 +66,95 @@ pub fn run_app(
             .service(health_check)
             .service(index)
             .service(post_student)
+            .service(import_students)
             .service(get_all_students)
+            //before `/students/{student_id}`
+            .service(export_students)
+            .service(search_students)
             .service(get_student)
             .service(change_student)
+            .service(patch_student)
             .service(get_avatar)
+            .service(upload_avatar)
+            .service(delete_avatar)
             .service(delete_student)
+            .service(restore_student)
             .service(register_user)
             .service(login_user)
             .service(logout_handler)
             .service(refresh_auth)
+            .service(change_user_role)
+            .service(link_student_account)
+            .service(get_audit_log)
+            .service(post_teacher)
+            .service(get_all_teachers)
+            .service(get_my_students)
+            .service(get_teacher)
+            .service(patch_teacher)
+            .service(delete_teacher)
+            .service(assign_teacher_courses)
+            .service(unassign_teacher_course)
+            .service(post_grade)
+            .service(get_student_grades)
+            .service(patch_grade)
+            .service(mark_attendance)
+            .service(get_student_attendance)
+            .service(get_attendance_rates)
+            .service(post_group)
+            .service(get_all_groups)
+            .service(get_my_groups)
+            .service(get_group)
+            .service(patch_group)
+            .service(delete_group)
+            .service(get_group_students)
+            .service(add_group_students)
+            .service(remove_group_student)
+            .service(post_academic_year)
+            .service(get_academic_years)
+            .service(post_term)
+            .service(get_current_term)
+            .service(get_student_term_courses)
+            .service(post_slot)
+            .service(patch_slot)
+            .service(delete_slot)
+            .service(get_student_timetable)
+            .service(export_student_timetable)
+            .service(get_teacher_timetable)
+            .service(post_guardian)
+            .service(get_all_guardians)
+            .service(get_my_children)
+            .service(get_guardian)
+            .service(patch_guardian)
+            .service(delete_guardian)
+            .service(get_student_guardians)
+            .service(link_student_guardian)
+            .service(unlink_student_guardian)
+            .service(get_course_capacity)
+            .service(set_course_capacity)
+            .service(delete_course_capacity)
+            .service(get_prerequisites)
+            .service(set_prerequisites)
+            .service(post_assignment)
+            .service(get_course_assignments)
+            .service(get_assignment)
+            .service(patch_assignment)
+            .service(delete_assignment)
+            .service(submit_assignment)
+            .service(get_submissions)
+            .service(get_submission)
+            .service(grade_submission)
+            .service(get_submission_file)
+            .service(set_report_comment)
+            .service(get_report_card)
+            .service(export_report_card)
+            .service(export_group_report_cards)
+            .service(get_stats)
+            .service(post_webhook)
+            .service(get_all_webhooks)
+            .service(get_webhook)
+            .service(patch_webhook)
+            .service(delete_webhook)
+            .service(get_webhook_deliveries)
     })
     .listen(listener)?
     .run();
@@ -0,0 +1,442 @@
+use std::io::Write;
+
+use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use printpdf::{IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference};
+use time::macros::format_description;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_get_group_students, db_get_report_card, db_get_teacher_by_user, db_set_report_comment,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddReportComment, AuditContext, ReportCard, Role},
+};
+
+#[put("/students/{student_id}/report-card/comments")]
+#[instrument(skip_all,name="Set report card comment",fields(uri = %req.uri(), method= %req.method(),student_id=%student_id,data=?form))]
+pub async fn set_report_comment(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<AddReportComment>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can comment report cards");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    //comment is signed by the teacher linked to the logged user, if any
+    let teacher_id = db_get_teacher_by_user(auth.user_id, &state.connection)
+        .await
+        .ok()
+        .map(|t| t.id);
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_set_report_comment(
+        *student_id,
+        form.into_inner(),
+        teacher_id,
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(comments) => {
+            tracing::info!(
+                "Student_id {} - Report card comment has been set",
+                student_id
+            );
+            HttpResponse::Ok().json(comments)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/students/{student_id}/report-card")]
+#[instrument(skip(state,req,auth),name="Get student's report card",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_report_card(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_report_card(*student_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get report card of student '{}'", student_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's report card: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/students/{student_id}/report-card.pdf")]
+#[instrument(skip(state,req,auth),name="Export student's report card",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn export_report_card(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    let card = match db_get_report_card(*student_id, &state.connection).await {
+        Ok(card) => card,
+        Err(e) => {
+            tracing::error!("Failed get student's report card: {}", e);
+            return e.error_response();
+        }
+    };
+
+    //rendering takes a while
+    let pdf = match web::block(move || report_card_pdf(&card)).await {
+        Ok(Ok(pdf)) => pdf,
+        Ok(Err(e)) => {
+            tracing::error!("Failed export student's report card: {}", e);
+            return e.error_response();
+        }
+        Err(e) => {
+            let error = Error::new(
+                Some(e.to_string()),
+                Some("Can not create the report card".into()),
+                ErrorTypes::DbError,
+            );
+            tracing::error!("Failed export student's report card: {}", error);
+            return error.error_response();
+        }
+    };
+
+    tracing::info!(
+        "Successfully export report card of student '{}'",
+        student_id
+    );
+    HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"report-card-{}.pdf\"", student_id),
+        ))
+        .body(pdf)
+}
+
+//report cards of all students of the group, one PDF per student
+#[get("/groups/{group_id}/report-cards.zip")]
+#[instrument(skip(state,req,auth),name="Export group's report cards",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn export_group_report_cards(
+    group_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only teachers can export group's report cards");
+        return e.error_response();
+    }
+
+    let students = match db_get_group_students(*group_id, &state.connection).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Failed get group's students: {}", e);
+            return e.error_response();
+        }
+    };
+
+    let mut cards = Vec::new();
+    for student in students.iter() {
+        match db_get_report_card(student.id, &state.connection).await {
+            Ok(card) => cards.push(card),
+            Err(e) => {
+                tracing::error!("Failed get student's report card: {}", e);
+                return e.error_response();
+            }
+        }
+    }
+
+    //a PDF for every student takes a while
+    let archive = match web::block(move || report_cards_zip(&cards)).await {
+        Ok(Ok(archive)) => archive,
+        Ok(Err(e)) => {
+            tracing::error!("Failed export group's report cards: {}", e);
+            return e.error_response();
+        }
+        Err(e) => {
+            let error = Error::new(
+                Some(e.to_string()),
+                Some("Can not create the archive".into()),
+                ErrorTypes::DbError,
+            );
+            tracing::error!("Failed export group's report cards: {}", error);
+            return error.error_response();
+        }
+    };
+
+    tracing::info!("Successfully export report cards of group '{}'", group_id);
+    HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"report-cards-{}.zip\"", group_id),
+        ))
+        .body(archive)
+}
+
+const PAGE_WIDTH: f64 = 210.0;
+const PAGE_HEIGHT: f64 = 297.0;
+const MARGIN: f64 = 20.0;
+
+//the built-in PDF fonts have only Latin letters, names can be written in any script
+const REGULAR_FONT: &[u8] = include_bytes!("../../templates/fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../../templates/fonts/DejaVuSans-Bold.ttf");
+
+//writes lines from the top of the page, starts a new page when the page is full
+struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f64,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> Result<Self, Error> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Report card");
+        let regular = doc.add_external_font(REGULAR_FONT).map_err(pdf_error)?;
+        let bold = doc.add_external_font(BOLD_FONT).map_err(pdf_error)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(PdfWriter {
+            doc,
+            layer,
+            regular,
+            bold,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn line(&mut self, text: &str, size: f64, bold: bool) {
+        //line height in mm for the font size in points
+        let height = size * 0.5;
+        if self.y - height < MARGIN {
+            let (page, layer) = self
+                .doc
+                .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Report card");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer
+            .use_text(text, size, Mm(MARGIN), Mm(self.y), font);
+    }
+
+    //long text is wrapped by words
+    fn paragraph(&mut self, text: &str, size: f64) {
+        //approximate number of characters in a line
+        let width = ((PAGE_WIDTH - 2.0 * MARGIN) / (size * 0.2)) as usize;
+        let mut line = String::new();
+        for word in text.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + word.chars().count() + 1 > width {
+                self.line(&line, size, false);
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        if !line.is_empty() {
+            self.line(&line, size, false);
+        }
+    }
+
+    fn space(&mut self) {
+        self.y -= 4.0;
+    }
+
+    fn finish(self) -> Result<Vec<u8>, Error> {
+        self.doc.save_to_bytes().map_err(pdf_error)
+    }
+}
+
+fn report_card_pdf(card: &ReportCard) -> Result<Vec<u8>, Error> {
+    let date_format = format_description!("[year]-[month]-[day]");
+    let student = &card.student;
+    let mut pdf = PdfWriter::new(&format!("Report card - {}", student.full_name))?;
+
+    pdf.line("Report card", 20.0, true);
+    if let Some(term) = &card.term {
+        pdf.line(
+            &format!(
+                "{} ({} - {})",
+                term.name,
+                term.starts_on.format(&date_format).unwrap_or_default(),
+                term.ends_on.format(&date_format).unwrap_or_default()
+            ),
+            12.0,
+            false,
+        );
+    }
+    pdf.space();
+
+    pdf.line("Student", 14.0, true);
+    pdf.line(&format!("Name: {}", student.full_name), 11.0, false);
+    pdf.line(&format!("Email: {}", student.email), 11.0, false);
+    pdf.line(&format!("Age: {}", student.age), 11.0, false);
+    pdf.line(
+        &format!(
+            "Registered: {}",
+            student
+                .registration_date
+                .format(&date_format)
+                .unwrap_or_default()
+        ),
+        11.0,
+        false,
+    );
+    pdf.line(
+        &format!("Courses: {}", student.courses.join(", ")),
+        11.0,
+        false,
+    );
+    pdf.space();
+
+    pdf.line("Grades", 14.0, true);
+    if card.grades.courses.is_empty() {
+        pdf.line("No grades", 11.0, false);
+    }
+    for course in card.grades.courses.iter() {
+        pdf.line(
+            &format!(
+                "{}: {:.2}% ({} grades)",
+                course.course_name, course.average, course.grades_count
+            ),
+            11.0,
+            false,
+        );
+    }
+    if let Some(gpa) = card.grades.gpa {
+        pdf.line(&format!("GPA: {:.2}", gpa), 11.0, true);
+    }
+    pdf.space();
+
+    pdf.line("Attendance", 14.0, true);
+    for course in card.attendance.iter() {
+        pdf.line(
+            &format!(
+                "{}: {} ({} present, {} late, {} absent, {} excused)",
+                course.course_name,
+                percent(course.rate),
+                course.present,
+                course.late,
+                course.absent,
+                course.excused
+            ),
+            11.0,
+            false,
+        );
+    }
+    pdf.line(
+        &format!("Total: {}", percent(card.attendance_rate)),
+        11.0,
+        true,
+    );
+    pdf.space();
+
+    pdf.line("Teachers' comments", 14.0, true);
+    if card.comments.is_empty() {
+        pdf.line("No comments", 11.0, false);
+    }
+    for comment in card.comments.iter() {
+        let teacher = comment.teacher_name.as_deref().unwrap_or("Teacher");
+        pdf.line(
+            &format!("{} - {}", comment.course_name, teacher),
+            11.0,
+            true,
+        );
+        pdf.paragraph(&comment.comment, 11.0);
+    }
+
+    pdf.finish()
+}
+
+fn report_cards_zip(cards: &[ReportCard]) -> Result<Vec<u8>, Error> {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for card in cards {
+        let name: String = card
+            .student
+            .full_name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        zip.start_file(format!("{}-{}.pdf", name, card.student.id), options)
+            .map_err(zip_error)?;
+        zip.write_all(&report_card_pdf(card)?).map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not create the archive".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+    }
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+fn percent(rate: Option<f64>) -> String {
+    rate.map(|r| format!("{:.2}%", r))
+        .unwrap_or_else(|| "-".into())
+}
+
+fn pdf_error(e: printpdf::Error) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Can not create the report card".into()),
+        ErrorTypes::DbError,
+    )
+}
+
+fn zip_error(e: zip::result::ZipError) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Can not create the archive".into()),
+        ErrorTypes::DbError,
+    )
+}
@@ -3,19 +3,27 @@ use crate::{
     auth::JwtMiddleware,
     db::{
         db_change_student, db_delete_student, db_get_all_students, db_get_student,
-        db_insert_new_student,
+        db_import_students, db_insert_new_student, db_load_grade_summaries, db_patch_student,
+        db_restore_student, db_search_students,
     },
     errors::{Error, ErrorTypes},
-    schemas::{AddStudent, EditStudent},
+    schemas::{
+        parse_students_csv, AddStudent, AuditContext, EditStudent, ImportQuery, PatchStudent, Role,
+        SearchQuery, StudentFilter, StudentQuery,
+    },
+};
+use actix_web::{
+    delete, get,
+    http::header::{ETag, EntityTag, IfMatch},
+    patch, post, web, HttpMessage, HttpRequest, HttpResponse, Responder, ResponseError,
 };
-use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, ResponseError};
 
 use tracing::instrument;
 
 use uuid::Uuid;
 use validator::Validate;
 
-use super::AvatarClient;
+use super::AvatarProvider;
 
 #[get("/health_check")]
 #[instrument(skip_all, name = "Health check")]
@@ -37,11 +45,16 @@ pub async fn index(req: HttpRequest, _: JwtMiddleware) -> impl Responder {
 #[instrument(skip_all,name="Add new student",fields(uri = %req.uri(), method= %req.method(),data=?form))]
 pub async fn post_student(
     state: web::Data<AppState>,
-    avatar_client: web::Data<AvatarClient>,
+    avatar_client: web::Data<dyn AvatarProvider>,
     form: web::Json<AddStudent>,
     req: HttpRequest,
-    _: JwtMiddleware,
+    auth: JwtMiddleware,
 ) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't add students", auth.user_id);
+        return e.error_response();
+    }
+
     //Data validation
     if let Err(error) = form.validate().map_err(|e| {
         Error::new(
@@ -54,8 +67,10 @@ pub async fn post_student(
         return error.error_response();
     }
 
+    let audit = AuditContext::new(Some(auth.user_id), &req);
     match db_insert_new_student(
         form.into_inner(),
+        &audit,
         &state.connection,
         avatar_client.into_inner(),
     )
@@ -66,7 +81,9 @@ pub async fn post_student(
                 "Student_id {} - Student's details has been saved",
                 student.id,
             );
-            HttpResponse::Ok().json(web::Json(student))
+            HttpResponse::Ok()
+                .insert_header(student_etag(student.version))
+                .json(web::Json(student))
         }
         Err(e) => {
             tracing::error!("Failed to execute query: {}", e);
@@ -75,6 +92,62 @@ pub async fn post_student(
     }
 }
 
+//CSV with `fullName,email,age,courses` header, courses are separated by `;`
+#[post("/students/import")]
+#[instrument(skip_all,name="Import students",fields(uri = %req.uri(), method= %req.method(),query=?query))]
+pub async fn import_students(
+    state: web::Data<AppState>,
+    avatar_client: web::Data<dyn AvatarProvider>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only admins and teachers can import students");
+        return e.error_response();
+    }
+
+    let (rows, errors) = match parse_students_csv(&body) {
+        Ok(data) => data,
+        Err(e) => {
+            let error = Error::new(
+                Some(e.to_string()),
+                Some("Invalid CSV file".into()),
+                ErrorTypes::ValidationError,
+            );
+            tracing::error!("Invalid input data. Errors: {}", error);
+            return error.error_response();
+        }
+    };
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_import_students(
+        rows,
+        errors,
+        query.dry_run,
+        &audit,
+        &state.connection,
+        avatar_client.into_inner(),
+    )
+    .await
+    {
+        Ok(report) => {
+            tracing::info!(
+                "Imported {} of {} students, dry run: {}",
+                report.imported,
+                report.total,
+                report.dry_run
+            );
+            HttpResponse::Ok().json(report)
+        }
+        Err(e) => {
+            tracing::error!("Failed to import students: {}", e);
+            e.error_response()
+        }
+    }
+}
+
 #[post("/students/change/{id}")]
 #[instrument(skip_all,name="Change student",fields(uri = %req.uri(), method= %req.method(),student_id=%id,data=?form))]
 pub async fn change_student(
@@ -82,8 +155,13 @@ pub async fn change_student(
     state: web::Data<AppState>,
     form: web::Json<EditStudent>,
     req: HttpRequest,
-    _: JwtMiddleware,
+    auth: JwtMiddleware,
 ) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't change students", auth.user_id);
+        return e.error_response();
+    }
+
     //Data validation
     if let Err(error) = form.validate().map_err(|e| {
         Error::new(
@@ -96,10 +174,86 @@ pub async fn change_student(
         return error.error_response();
     }
 
-    match db_change_student(*id, form.into_inner(), &state.connection).await {
+    let expected_versions = match if_match_versions(&req) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Invalid If-Match header: {}", e);
+            return e.error_response();
+        }
+    };
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_change_student(
+        *id,
+        form.into_inner(),
+        expected_versions,
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
         Ok(student) => {
             tracing::info!("Student_id {} - Student details has been saved", id);
-            HttpResponse::Ok().json(student)
+            HttpResponse::Ok()
+                .insert_header(student_etag(student.version))
+                .json(student)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/students/{id}")]
+#[instrument(skip_all,name="Patch student",fields(uri = %req.uri(), method= %req.method(),student_id=%id,data=?form))]
+pub async fn patch_student(
+    id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchStudent>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't change students", auth.user_id);
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let expected_versions = match if_match_versions(&req) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Invalid If-Match header: {}", e);
+            return e.error_response();
+        }
+    };
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_student(
+        *id,
+        form.into_inner(),
+        expected_versions,
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(student) => {
+            tracing::info!("Student_id {} - Student details has been patched", id);
+            HttpResponse::Ok()
+                .insert_header(student_etag(student.version))
+                .json(student)
         }
         Err(e) => {
             tracing::error!("Failed to execute query: {}", e);
@@ -112,73 +266,175 @@ pub async fn change_student(
 #[instrument(skip_all,name="Get all students",fields(uri = %req.uri(), method= %req.method()))]
 pub async fn get_all_students(
     state: web::Data<AppState>,
+    filter: web::Query<StudentFilter>,
     req: HttpRequest,
-    _: JwtMiddleware,
+    auth: JwtMiddleware,
 ) -> impl Responder {
-    match db_get_all_students(&state.connection).await {
-        Ok(data) => {
-            tracing::info!("Successfully get all students");
-            HttpResponse::Ok().json(data)
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't get all students", auth.user_id);
+        return e.error_response();
+    }
+
+    let mut filter = filter.into_inner();
+    if filter.mine {
+        filter.created_by = Some(auth.user_id);
+    }
+
+    //only admins can see deleted students
+    if filter.include_deleted {
+        if let Err(e) = auth.require_role(&[Role::Admin]) {
+            tracing::error!("Only admins can get deleted students");
+            return e.error_response();
         }
+    }
+
+    let mut students = match db_get_all_students(&filter, &state.connection).await {
+        Ok(data) => data,
         Err(e) => {
             tracing::error!("Failed get all students: {}", e);
+            return e.error_response();
+        }
+    };
+
+    if filter.include_grades() {
+        if let Err(e) = db_load_grade_summaries(&mut students, &state.connection).await {
+            tracing::error!("Failed get students' grades: {}", e);
+            return e.error_response();
+        }
+    }
+
+    tracing::info!("Successfully get all students");
+    HttpResponse::Ok().json(students)
+}
+
+#[get("/students/search")]
+#[instrument(skip_all,name="Search students",fields(uri = %req.uri(), method= %req.method(),query=?query))]
+pub async fn search_students(
+    state: web::Data<AppState>,
+    query: web::Query<SearchQuery>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't search students", auth.user_id);
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = query.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    match db_search_students(&query, &state.connection).await {
+        Ok(students) => {
+            tracing::info!("Successfully found {} students", students.len());
+            HttpResponse::Ok().json(students)
+        }
+        Err(e) => {
+            tracing::error!("Failed search students: {}", e);
             e.error_response()
         }
     }
 }
 
 #[get("/students/{student_id}")]
-#[instrument(skip(state,req),name="Get student",fields(uri = %req.uri(), method= %req.method()))]
+#[instrument(skip(state,query,req,auth),name="Get student",fields(uri = %req.uri(), method= %req.method()))]
 pub async fn get_student(
     student_id: web::Path<Uuid>,
     state: web::Data<AppState>,
+    query: web::Query<StudentQuery>,
     req: HttpRequest,
-    _: JwtMiddleware,
+    auth: JwtMiddleware,
 ) -> impl Responder {
-    match db_get_student(*student_id, &state.connection).await {
-        Ok(data) => {
-            tracing::info!("Successfully get student with id: '{}'", student_id);
-            HttpResponse::Ok().json(data)
-        }
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    let mut student = match db_get_student(*student_id, &state.connection).await {
+        Ok(data) => data,
         Err(e) => {
             tracing::error!("Failed get student: {}", e);
-            e.error_response()
+            return e.error_response();
+        }
+    };
+
+    if query.include_grades() {
+        if let Err(e) =
+            db_load_grade_summaries(std::slice::from_mut(&mut student), &state.connection).await
+        {
+            tracing::error!("Failed get student's grades: {}", e);
+            return e.error_response();
         }
     }
+
+    tracing::info!("Successfully get student with id: '{}'", student_id);
+    HttpResponse::Ok()
+        .insert_header(student_etag(student.version))
+        .json(student)
 }
 
-#[get("/students/{student_id}/avatar")]
-#[instrument(skip(state,req),name="Get student's avatar",fields(uri = %req.uri(), method= %req.method()))]
-pub async fn get_avatar(
+#[post("/students/{student_id}/restore")]
+#[instrument(skip(state,req,auth),name="Restore student",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn restore_student(
     student_id: web::Path<Uuid>,
     state: web::Data<AppState>,
     req: HttpRequest,
-    _: JwtMiddleware,
+    auth: JwtMiddleware,
 ) -> impl Responder {
-    match db_get_student(*student_id, &state.connection).await {
-        Ok(data) => {
-            tracing::info!(
-                "Successfully get student's avatar with id: '{}'",
-                student_id
-            );
-            HttpResponse::Ok().json(data.img)
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can restore students");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_restore_student(*student_id, &audit, &state.connection).await {
+        Ok(student) => {
+            tracing::info!("Successfully restore student with id: '{}'", student_id);
+            HttpResponse::Ok()
+                .insert_header(student_etag(student.version))
+                .json(student)
         }
         Err(e) => {
-            tracing::error!("Failed get student's avatar: {}", e);
+            tracing::error!("Failed restore student: {}", e);
             e.error_response()
         }
     }
 }
 
 #[delete("/delete/{student_id}")]
-#[instrument(skip(state,req),name="Delete student",fields(uri = %req.uri(), method= %req.method()))]
+#[instrument(skip(state,req,auth),name="Delete student",fields(uri = %req.uri(), method= %req.method()))]
 pub async fn delete_student(
     student_id: web::Path<Uuid>,
     state: web::Data<AppState>,
     req: HttpRequest,
-    _: JwtMiddleware,
+    auth: JwtMiddleware,
 ) -> impl Responder {
-    match db_delete_student(*student_id, &state.connection).await {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't delete students", auth.user_id);
+        return e.error_response();
+    }
+
+    let expected_versions = match if_match_versions(&req) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Invalid If-Match header: {}", e);
+            return e.error_response();
+        }
+    };
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_student(*student_id, expected_versions, &audit, &state.connection).await {
         Ok(_) => {
             tracing::info!("Successfully delete student with id: '{}'", student_id);
             HttpResponse::Ok().json(format!("Deleted student:{}", student_id))
@@ -189,3 +445,37 @@ pub async fn delete_student(
         }
     }
 }
+
+fn student_etag(version: i32) -> ETag {
+    ETag(EntityTag::new_strong(version.to_string()))
+}
+
+//versions the client expects, the student is changed if it has one of them, `*` is any version.
+//If-Match uses the strong comparison, so weak ETags never match
+fn if_match_versions(req: &HttpRequest) -> Result<Option<Vec<i32>>, Error> {
+    let tags = match req.get_header::<IfMatch>() {
+        Some(IfMatch::Any) => return Ok(None),
+        Some(IfMatch::Items(tags)) => tags,
+        None => {
+            return Err(Error::new(
+                None,
+                Some("If-Match header with the student's ETag is required".into()),
+                ErrorTypes::PreconditionRequired,
+            ))
+        }
+    };
+
+    let versions: Vec<i32> = tags
+        .iter()
+        .filter(|tag| !tag.weak)
+        .filter_map(|tag| tag.tag().parse::<i32>().ok())
+        .collect();
+    if versions.is_empty() {
+        return Err(Error::new(
+            None,
+            Some("If-Match header has no strong ETag of the student".into()),
+            ErrorTypes::PreconditionFailed,
+        ));
+    }
+    Ok(Some(versions))
+}
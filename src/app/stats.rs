@@ -0,0 +1,71 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use tracing::instrument;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::db_get_stats,
+    schemas::{Role, Stats},
+};
+
+//statistics are computed at most once per the interval
+pub struct StatsCache {
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, Stats)>>,
+}
+
+impl StatsCache {
+    pub fn new(ttl: Duration) -> Self {
+        StatsCache {
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn get(&self) -> Option<Stats> {
+        let cached = self.cached.lock().unwrap();
+        cached
+            .as_ref()
+            .filter(|(created, _)| created.elapsed() < self.ttl)
+            .map(|(_, stats)| stats.clone())
+    }
+
+    pub fn set(&self, stats: Stats) {
+        *self.cached.lock().unwrap() = Some((Instant::now(), stats));
+    }
+}
+
+#[get("/stats")]
+#[instrument(skip_all,name="Get statistics",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_stats(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("User '{}' can't get statistics", auth.user_id);
+        return e.error_response();
+    }
+
+    if let Some(stats) = state.stats.get() {
+        tracing::info!("Statistics from the cache");
+        return HttpResponse::Ok().json(stats);
+    }
+
+    match db_get_stats(&state.connection).await {
+        Ok(stats) => {
+            state.stats.set(stats.clone());
+            tracing::info!("Successfully get statistics");
+            HttpResponse::Ok().json(stats)
+        }
+        Err(e) => {
+            tracing::error!("Failed get statistics: {}", e);
+            e.error_response()
+        }
+    }
+}
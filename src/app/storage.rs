@@ -0,0 +1,81 @@
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+
+use crate::errors::{Error, ErrorTypes};
+
+//uploaded files, keys are generated by the server
+#[async_trait]
+pub trait Storage: Send + Sync + std::fmt::Debug {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), Error>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Error>;
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+}
+
+//files in a directory of the local filesystem
+#[derive(Debug)]
+pub struct LocalStorage {
+    pub root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStorage { root: root.into() }
+    }
+
+    //keys can't leave the root directory
+    fn path(&self, key: &str) -> Result<PathBuf, Error> {
+        let key = Path::new(key);
+        if key.as_os_str().is_empty()
+            || !key.components().all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(Error::new(
+                Some(format!("Storage key: {:?}", key)),
+                Some("Invalid file path".into()),
+                ErrorTypes::ValidationError,
+            ));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), Error> {
+        let path = self.path(key)?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(storage_error)?;
+        }
+        tokio::fs::write(path, data).await.map_err(storage_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        let path = self.path(key)?;
+        tokio::fs::read(path).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::new(
+                Some(e.to_string()),
+                Some("File not found".into()),
+                ErrorTypes::NotFoundError,
+            ),
+            _ => storage_error(e),
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(storage_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn storage_error(e: std::io::Error) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Can not access the file storage".into()),
+        ErrorTypes::DbError,
+    )
+}
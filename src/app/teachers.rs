@@ -0,0 +1,259 @@
+use actix_web::{
+    delete, get, patch, post, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_add_teacher, db_assign_courses, db_delete_teacher, db_get_all_teachers, db_get_teacher,
+        db_get_teacher_by_user, db_get_teacher_students, db_patch_teacher, db_unassign_course,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddTeacher, AuditContext, PatchTeacher, Role, TeacherCourses},
+};
+
+#[post("/teachers")]
+#[instrument(skip_all,name="Add new teacher",fields(uri = %req.uri(), method= %req.method(),data=?form))]
+pub async fn post_teacher(
+    state: web::Data<AppState>,
+    form: web::Json<AddTeacher>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can add teachers");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_teacher(form.into_inner(), &audit, &state.connection).await {
+        Ok(teacher) => {
+            tracing::info!(
+                "Teacher_id {} - Teacher's details has been saved",
+                teacher.id
+            );
+            HttpResponse::Ok().json(teacher)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/teachers")]
+#[instrument(skip_all,name="Get all teachers",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_all_teachers(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_all_teachers(&state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get all teachers");
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get all teachers: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//students of the logged teacher's courses
+#[get("/teachers/me/students")]
+#[instrument(skip(state,req,auth),name="Get my students",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_my_students(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    let teacher = match db_get_teacher_by_user(auth.user_id, &state.connection).await {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!("Failed get teacher of user '{}': {}", auth.user_id, e);
+            return e.error_response();
+        }
+    };
+
+    match db_get_teacher_students(teacher.id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get students of teacher '{}'", teacher.id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get teacher's students: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/teachers/{teacher_id}")]
+#[instrument(skip(state,req),name="Get teacher",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_teacher(
+    teacher_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_teacher(*teacher_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get teacher with id: '{}'", teacher_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get teacher: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/teachers/{teacher_id}")]
+#[instrument(skip_all,name="Patch teacher",fields(uri = %req.uri(), method= %req.method(),teacher_id=%teacher_id,data=?form))]
+pub async fn patch_teacher(
+    teacher_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchTeacher>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change teachers");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_teacher(*teacher_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(teacher) => {
+            tracing::info!(
+                "Teacher_id {} - Teacher details has been patched",
+                teacher_id
+            );
+            HttpResponse::Ok().json(teacher)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/teachers/{teacher_id}")]
+#[instrument(skip(state,req,auth),name="Delete teacher",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_teacher(
+    teacher_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can delete teachers");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_teacher(*teacher_id, &audit, &state.connection).await {
+        Ok(_) => {
+            tracing::info!("Successfully delete teacher with id: '{}'", teacher_id);
+            HttpResponse::Ok().json(format!("Deleted teacher:{}", teacher_id))
+        }
+        Err(e) => {
+            tracing::error!("Failed delete teacher: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[post("/teachers/{teacher_id}/courses")]
+#[instrument(skip_all,name="Assign courses to teacher",fields(uri = %req.uri(), method= %req.method(),teacher_id=%teacher_id,data=?form))]
+pub async fn assign_teacher_courses(
+    teacher_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<TeacherCourses>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can assign courses");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_assign_courses(*teacher_id, &form.courses, &audit, &state.connection).await {
+        Ok(teacher) => {
+            tracing::info!("Teacher_id {} - Courses has been assigned", teacher_id);
+            HttpResponse::Ok().json(teacher)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/teachers/{teacher_id}/courses/{course_name}")]
+#[instrument(skip(state,req,auth),name="Unassign course from teacher",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn unassign_teacher_course(
+    path: web::Path<(Uuid, String)>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can unassign courses");
+        return e.error_response();
+    }
+
+    let (teacher_id, course_name) = path.into_inner();
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_unassign_course(teacher_id, &course_name, &audit, &state.connection).await {
+        Ok(teacher) => {
+            tracing::info!("Teacher_id {} - Course has been unassigned", teacher_id);
+            HttpResponse::Ok().json(teacher)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_add_academic_year, db_add_term, db_get_academic_years, db_get_current_term,
+        db_get_student_term_courses,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddAcademicYear, AddTerm, AuditContext, Role, TermQuery},
+};
+
+#[post("/academic-years")]
+#[instrument(skip_all,name="Add new academic year",fields(uri = %req.uri(), method= %req.method(),data=?form))]
+pub async fn post_academic_year(
+    state: web::Data<AppState>,
+    form: web::Json<AddAcademicYear>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can add academic years");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_academic_year(form.into_inner(), &audit, &state.connection).await {
+        Ok(year) => {
+            tracing::info!("Academic year {} has been saved", year.id);
+            HttpResponse::Ok().json(year)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/academic-years")]
+#[instrument(skip_all,name="Get academic years",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_academic_years(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_academic_years(&state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get academic years");
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get academic years: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[post("/academic-years/{year_id}/terms")]
+#[instrument(skip_all,name="Add new term",fields(uri = %req.uri(), method= %req.method(),year_id=%year_id,data=?form))]
+pub async fn post_term(
+    year_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<AddTerm>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can add terms");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_term(
+        *year_id,
+        form.into_inner(),
+        state.current_term.as_deref(),
+        &audit,
+        &state.connection,
+    )
+    .await
+    {
+        Ok(term) => {
+            tracing::info!("Term {} has been saved", term.code);
+            HttpResponse::Ok().json(term)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/terms/current")]
+#[instrument(skip_all,name="Get current term",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_current_term(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    _: JwtMiddleware,
+) -> impl Responder {
+    match db_get_current_term(&state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get current term");
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get current term: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/students/{student_id}/courses")]
+#[instrument(skip(state,req,auth),name="Get student's courses of term",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_student_term_courses(
+    student_id: web::Path<Uuid>,
+    query: web::Query<TermQuery>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_student_term_courses(*student_id, query.term.as_deref(), &state.connection).await
+    {
+        Ok(data) => {
+            tracing::info!("Successfully get courses of student '{}'", student_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's courses: {}", e);
+            e.error_response()
+        }
+    }
+}
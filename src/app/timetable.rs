@@ -0,0 +1,280 @@
+use actix_web::{
+    delete, get, patch, post, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use time::{macros::format_description, Date, Duration, OffsetDateTime};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_add_slot, db_delete_slot, db_get_current_term, db_get_student_timetable,
+        db_get_teacher_timetable, db_patch_slot,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddSlot, AuditContext, PatchSlot, Role, ScheduleSlot, Term},
+};
+
+#[post("/schedule")]
+#[instrument(skip_all,name="Add schedule slot",fields(uri = %req.uri(), method= %req.method(),data=?form))]
+pub async fn post_slot(
+    state: web::Data<AppState>,
+    form: web::Json<AddSlot>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change the schedule");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_slot(form.into_inner(), &audit, &state.connection).await {
+        Ok(slot) => {
+            tracing::info!("Slot_id {} - Slot has been saved", slot.id);
+            HttpResponse::Ok().json(slot)
+        }
+        Err(e) => {
+            tracing::error!("Failed to add slot: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/schedule/{slot_id}")]
+#[instrument(skip_all,name="Patch schedule slot",fields(uri = %req.uri(), method= %req.method(),slot_id=%slot_id,data=?form))]
+pub async fn patch_slot(
+    slot_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchSlot>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change the schedule");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_slot(*slot_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(slot) => {
+            tracing::info!("Slot_id {} - Slot has been patched", slot_id);
+            HttpResponse::Ok().json(slot)
+        }
+        Err(e) => {
+            tracing::error!("Failed to change slot: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/schedule/{slot_id}")]
+#[instrument(skip(state,req,auth),name="Delete schedule slot",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_slot(
+    slot_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change the schedule");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_slot(*slot_id, &audit, &state.connection).await {
+        Ok(_) => {
+            tracing::info!("Successfully delete slot with id: '{}'", slot_id);
+            HttpResponse::Ok().json(format!("Deleted slot:{}", slot_id))
+        }
+        Err(e) => {
+            tracing::error!("Failed delete slot: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/students/{student_id}/timetable")]
+#[instrument(skip(state,req,auth),name="Get student's timetable",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_student_timetable(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    match db_get_student_timetable(*student_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get timetable of student '{}'", student_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get student's timetable: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//student's timetable as weekly recurring events of the current term
+#[get("/students/{student_id}/timetable.ics")]
+#[instrument(skip(state,req,auth),name="Export student's timetable",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn export_student_timetable(
+    student_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth
+        .require_student_access(*student_id, &state.connection)
+        .await
+    {
+        tracing::error!("User '{}' can't access the student", auth.user_id);
+        return e.error_response();
+    }
+
+    let slots = match db_get_student_timetable(*student_id, &state.connection).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Failed get student's timetable: {}", e);
+            return e.error_response();
+        }
+    };
+
+    //without a current term the events start this week and never end
+    let term = db_get_current_term(&state.connection).await.ok();
+
+    tracing::info!("Successfully export timetable of student '{}'", student_id);
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"timetable-{}.ics\"", student_id),
+        ))
+        .body(timetable_ics(
+            &slots,
+            term.as_ref(),
+            OffsetDateTime::now_utc(),
+        ))
+}
+
+#[get("/teachers/{teacher_id}/timetable")]
+#[instrument(skip(state,req,auth),name="Get teacher's timetable",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_teacher_timetable(
+    teacher_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin, Role::Teacher]) {
+        tracing::error!("Only admins and teachers can get teachers' timetables");
+        return e.error_response();
+    }
+
+    match db_get_teacher_timetable(*teacher_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get timetable of teacher '{}'", teacher_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get teacher's timetable: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+fn timetable_ics(slots: &[ScheduleSlot], term: Option<&Term>, now: OffsetDateTime) -> String {
+    let date_format = format_description!("[year][month][day]");
+    let time_format = format_description!("[hour][minute][second]");
+
+    let starts_on = term.map(|t| t.starts_on).unwrap_or_else(|| now.date());
+    let until = term
+        .and_then(|t| t.ends_on.format(&date_format).ok())
+        .map(|d| format!(";UNTIL={}T235959", d))
+        .unwrap_or_default();
+    let stamp = format!(
+        "{}T{}Z",
+        now.date().format(&date_format).unwrap_or_default(),
+        now.time().format(&time_format).unwrap_or_default()
+    );
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//zero2prod//timetable//EN".to_string(),
+    ];
+    for slot in slots {
+        let day = first_day(starts_on, slot.day_of_week)
+            .format(&date_format)
+            .unwrap_or_default();
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@zero2prod", slot.id),
+            format!("DTSTAMP:{}", stamp),
+            format!(
+                "DTSTART:{}T{}",
+                day,
+                slot.starts_at.format(&time_format).unwrap_or_default()
+            ),
+            format!(
+                "DTEND:{}T{}",
+                day,
+                slot.ends_at.format(&time_format).unwrap_or_default()
+            ),
+            format!("RRULE:FREQ=WEEKLY{}", until),
+            format!("SUMMARY:{}", ics_escape(&slot.course_name)),
+            format!("LOCATION:{}", ics_escape(&slot.room)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = lines.join("\r\n");
+    ics.push_str("\r\n");
+    ics
+}
+
+//first date on or after `from` falling on the ISO day of week
+fn first_day(from: Date, day_of_week: i16) -> Date {
+    let from_day = from.weekday().number_from_monday() as i64;
+    from + Duration::days((day_of_week as i64 - from_day).rem_euclid(7))
+}
+
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,208 @@
+use actix_web::{
+    delete, get, patch, post, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app::AppState,
+    auth::JwtMiddleware,
+    db::{
+        db_add_webhook, db_delete_webhook, db_get_all_webhooks, db_get_webhook,
+        db_get_webhook_deliveries, db_patch_webhook,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AddWebhook, AuditContext, DeliveryFilter, PatchWebhook, Role},
+};
+
+//`sha256=` and the hex HMAC-SHA256 of `<timestamp>.<body>` with the webhook's secret
+pub fn webhook_signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[post("/webhooks")]
+#[instrument(skip_all,name="Add new webhook",fields(uri = %req.uri(), method= %req.method(),url=%form.url))]
+pub async fn post_webhook(
+    state: web::Data<AppState>,
+    form: web::Json<AddWebhook>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can add webhooks");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_add_webhook(form.into_inner(), &audit, &state.connection).await {
+        Ok(webhook) => {
+            tracing::info!("Webhook_id {} - Webhook has been saved", webhook.webhook.id);
+            HttpResponse::Ok().json(webhook)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/webhooks")]
+#[instrument(skip_all,name="Get all webhooks",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_all_webhooks(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can get webhooks");
+        return e.error_response();
+    }
+
+    match db_get_all_webhooks(&state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get all webhooks");
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get all webhooks: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[get("/webhooks/{webhook_id}")]
+#[instrument(skip(state,req,auth),name="Get webhook",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_webhook(
+    webhook_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can get webhooks");
+        return e.error_response();
+    }
+
+    match db_get_webhook(*webhook_id, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get webhook with id: '{}'", webhook_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get webhook: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[patch("/webhooks/{webhook_id}")]
+#[instrument(skip_all,name="Patch webhook",fields(uri = %req.uri(), method= %req.method(),webhook_id=%webhook_id))]
+pub async fn patch_webhook(
+    webhook_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: web::Json<PatchWebhook>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change webhooks");
+        return e.error_response();
+    }
+
+    //Data validation
+    if let Err(error) = form.validate().map_err(|e| {
+        Error::new(
+            Some(serde_json::to_string_pretty(&e).unwrap()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    }) {
+        tracing::error!("Invalid input data. Errors: {}", error);
+        return error.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_patch_webhook(*webhook_id, form.into_inner(), &audit, &state.connection).await {
+        Ok(webhook) => {
+            tracing::info!("Webhook_id {} - Webhook has been patched", webhook_id);
+            HttpResponse::Ok().json(webhook)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+#[delete("/webhooks/{webhook_id}")]
+#[instrument(skip(state,req,auth),name="Delete webhook",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn delete_webhook(
+    webhook_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can delete webhooks");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_delete_webhook(*webhook_id, &audit, &state.connection).await {
+        Ok(_) => {
+            tracing::info!("Successfully delete webhook with id: '{}'", webhook_id);
+            HttpResponse::Ok().json(format!("Deleted webhook:{}", webhook_id))
+        }
+        Err(e) => {
+            tracing::error!("Failed delete webhook: {}", e);
+            e.error_response()
+        }
+    }
+}
+
+//delivery log of the webhook
+#[get("/webhooks/{webhook_id}/deliveries")]
+#[instrument(skip(state,req,auth),name="Get webhook deliveries",fields(uri = %req.uri(), method= %req.method()))]
+pub async fn get_webhook_deliveries(
+    webhook_id: web::Path<Uuid>,
+    filter: web::Query<DeliveryFilter>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can get webhook deliveries");
+        return e.error_response();
+    }
+
+    match db_get_webhook_deliveries(*webhook_id, &filter, &state.connection).await {
+        Ok(data) => {
+            tracing::info!("Successfully get deliveries of webhook '{}'", webhook_id);
+            HttpResponse::Ok().json(data)
+        }
+        Err(e) => {
+            tracing::error!("Failed get webhook deliveries: {}", e);
+            e.error_response()
+        }
+    }
+}
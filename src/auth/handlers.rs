@@ -1,6 +1,6 @@
 use actix_web::{
     cookie::{time::Duration as ActixWebDuration, Cookie},
-    get, post, web, HttpRequest, HttpResponse, Responder, ResponseError,
+    get, post, put, web, HttpRequest, HttpResponse, Responder, ResponseError,
 };
 
 use serde_json::json;
@@ -11,16 +11,23 @@ use validator::Validate;
 use crate::{
     app::AppState,
     auth::JwtMiddleware,
-    db::{db_add_user, db_find_user, user_login},
+    db::{
+        db_add_user, db_bootstrap_admin, db_change_user_role, db_find_user, db_link_student_user,
+        user_login,
+    },
     errors::{Error, ErrorTypes},
-    schemas::{LoginUser, RegisterUser, TokenClaims, TokenType},
+    schemas::{
+        AuditContext, ChangeRole, LinkStudent, LoginUser, RegisterUser, Role, StudentAccount,
+        TokenClaims, TokenType,
+    },
 };
 
 #[post("/auth/signup")]
-#[instrument(skip(state), name = "Sign up user")]
+#[instrument(skip(state, req), name = "Sign up user")]
 pub async fn register_user(
     data: web::Json<RegisterUser>,
     state: web::Data<AppState>,
+    req: HttpRequest,
 ) -> impl Responder {
     if let Err(error) = data.validate().map_err(|e| {
         Error::new(
@@ -33,10 +40,31 @@ pub async fn register_user(
         return error.error_response();
     }
 
-    match db_add_user(data.into_inner(), &state.connection).await {
-        Ok(user) => HttpResponse::Ok().json(user),
+    let audit = AuditContext::new(None, &req);
+    let user = match db_add_user(data.into_inner(), &audit, &state.connection).await {
+        Ok(user) => user,
         Err(e) => {
             tracing::error!("Error insert new user: '{:?}'", e);
+            return e.error_response();
+        }
+    };
+
+    //new users get the least privileged role, the configured email becomes the first admin
+    let is_bootstrap_admin = state
+        .bootstrap_admin
+        .as_deref()
+        .is_some_and(|email| email.eq_ignore_ascii_case(&user.email));
+    if !is_bootstrap_admin {
+        return HttpResponse::Ok().json(user);
+    }
+    match db_bootstrap_admin(user.id, &audit, &state.connection).await {
+        Ok(Some(admin)) => {
+            tracing::info!("User '{}' is the first admin", admin.id);
+            HttpResponse::Ok().json(admin)
+        }
+        Ok(None) => HttpResponse::Ok().json(user),
+        Err(e) => {
+            tracing::error!("Error set the first admin: '{:?}'", e);
             e.error_response()
         }
     }
@@ -63,7 +91,7 @@ async fn login_user(data: web::Json<LoginUser>, state: web::Data<AppState>) -> i
         }
     };
     let access_token = match state.jwt.encode(
-        &TokenClaims::new(user.id.to_string(), state.jwt.access.exp),
+        &TokenClaims::new(user.id.to_string(), user.role, state.jwt.access.exp),
         TokenType::Access,
     ) {
         Ok(t) => t,
@@ -74,7 +102,7 @@ async fn login_user(data: web::Json<LoginUser>, state: web::Data<AppState>) -> i
     };
 
     let refresh_token = match state.jwt.encode(
-        &TokenClaims::new(user.id.to_string(), state.jwt.refresh.exp),
+        &TokenClaims::new(user.id.to_string(), user.role, state.jwt.refresh.exp),
         TokenType::Refresh,
     ) {
         Ok(t) => t,
@@ -128,7 +156,7 @@ async fn refresh_auth(
     };
 
     let new_token = match state.jwt.encode(
-        &TokenClaims::new(user.id.to_string(), Duration::minutes(1)),
+        &TokenClaims::new(user.id.to_string(), user.role, Duration::minutes(1)),
         TokenType::Access,
     ) {
         Ok(t) => t,
@@ -169,3 +197,56 @@ async fn logout_handler(_: JwtMiddleware) -> impl Responder {
         .cookie(cookie2)
         .json(json!({"status": "success"}))
 }
+
+#[put("/users/{user_id}/role")]
+#[instrument(skip(state, req, auth), name = "Change user's role")]
+async fn change_user_role(
+    user_id: web::Path<uuid::Uuid>,
+    data: web::Json<ChangeRole>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can change user's role");
+        return e.error_response();
+    }
+
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_change_user_role(*user_id, data.role, &audit, &state.connection).await {
+        Ok(user) => HttpResponse::Ok().json(user),
+        Err(e) => {
+            tracing::error!("Error change user's role: '{:?}'", e);
+            e.error_response()
+        }
+    }
+}
+
+//the user logs in as the student, see `require_student_access`
+#[put("/users/{user_id}/student")]
+#[instrument(skip(state, req, auth), name = "Link user to student")]
+async fn link_student_account(
+    user_id: web::Path<uuid::Uuid>,
+    data: web::Json<LinkStudent>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    auth: JwtMiddleware,
+) -> impl Responder {
+    if let Err(e) = auth.require_role(&[Role::Admin]) {
+        tracing::error!("Only admins can link students' accounts");
+        return e.error_response();
+    }
+
+    let account = StudentAccount {
+        student_id: data.student_id,
+        user_id: *user_id,
+    };
+    let audit = AuditContext::new(Some(auth.user_id), &req);
+    match db_link_student_user(account, &audit, &state.connection).await {
+        Ok(account) => HttpResponse::Ok().json(account),
+        Err(e) => {
+            tracing::error!("Error link student's account: '{:?}'", e);
+            e.error_response()
+        }
+    }
+}
@@ -1,25 +1,65 @@
 use actix_web::{dev::Payload, web, FromRequest, HttpMessage, HttpRequest};
 
+use futures_util::future::LocalBoxFuture;
+use sqlx::PgPool;
+use std::future::ready;
 use time::OffsetDateTime;
 use tracing::instrument;
-
-use std::future::{ready, Ready};
+use uuid::Uuid;
 
 use crate::{
     app::AppState,
+    db::{db_get_user_role, db_is_guardian_of, db_is_student_user},
     errors::{Auth, Error, ErrorTypes},
-    schemas::TokenType,
+    schemas::{Role, TokenType},
 };
 
 //custom middleware to check if token exist in request and refresh it
 #[derive(Debug)]
 pub struct JwtMiddleware {
     pub user_id: uuid::Uuid,
+    pub role: Role,
+}
+
+impl JwtMiddleware {
+    //check if logged user has one of the roles
+    pub fn require_role(&self, roles: &[Role]) -> Result<(), Error> {
+        if roles.contains(&self.role) {
+            return Ok(());
+        }
+        Err(Error {
+            cause: Some(format!("User's role: {:?}", self.role)),
+            message: Some("You don't have permission for this action".into()),
+            error_type: ErrorTypes::Auth(Auth::Authorization),
+        })
+    }
+
+    //parents can only access their own children and students only themselves,
+    //other roles can access everyone
+    pub async fn require_student_access(
+        &self,
+        student_id: Uuid,
+        connection: &PgPool,
+    ) -> Result<(), Error> {
+        let allowed = match self.role {
+            Role::Parent => db_is_guardian_of(self.user_id, student_id, connection).await?,
+            Role::Student => db_is_student_user(self.user_id, student_id, connection).await?,
+            Role::Admin | Role::Teacher => true,
+        };
+        if allowed {
+            return Ok(());
+        }
+        Err(Error {
+            cause: Some(format!("User's role: {:?}", self.role)),
+            message: Some("You can not access this student".into()),
+            error_type: ErrorTypes::Auth(Auth::Authorization),
+        })
+    }
 }
 
 impl FromRequest for JwtMiddleware {
     type Error = Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     #[instrument(skip_all,name="Check authorization",fields(uri = %req.uri(), method=%req.method()))]
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
@@ -32,11 +72,11 @@ impl FromRequest for JwtMiddleware {
             Some(token) => token,
             None => {
                 tracing::error!("Access token not found");
-                return ready(Err(Error {
+                return Box::pin(ready(Err(Error {
                     cause: None,
                     message: Some("Access token not found. Log in first!".into()),
                     error_type: ErrorTypes::Auth(Auth::Authorization),
-                }));
+                })));
             }
         };
 
@@ -45,25 +85,25 @@ impl FromRequest for JwtMiddleware {
             Err(e) => {
                 tracing::error!("Invalid jwt access token");
 
-                return ready(Err(Error {
+                return Box::pin(ready(Err(Error {
                     cause: Some(e.to_string()),
                     message: Some("Invalid jwt access token".into()),
                     error_type: ErrorTypes::JwtError,
-                }));
+                })));
             }
         };
 
         //check if token is valid only if it's not a refresh request
-        if req.uri() != "/auth/refresh" {
-            if OffsetDateTime::now_utc().unix_timestamp() as usize > token.exp {
-                tracing::error!("Log in timed out");
+        if req.uri() != "/auth/refresh"
+            && OffsetDateTime::now_utc().unix_timestamp() as usize > token.exp
+        {
+            tracing::error!("Log in timed out");
 
-                return ready(Err(Error {
-                    cause: None,
-                    message: Some("Login timed out".into()),
-                    error_type: ErrorTypes::Auth(Auth::Authorization),
-                }));
-            }
+            return Box::pin(ready(Err(Error {
+                cause: None,
+                message: Some("Login timed out".into()),
+                error_type: ErrorTypes::Auth(Auth::Authorization),
+            })));
         }
 
         //insert Uuid to request
@@ -71,6 +111,12 @@ impl FromRequest for JwtMiddleware {
         req.extensions_mut()
             .insert::<uuid::Uuid>(user_id.to_owned());
 
-        ready(Ok(JwtMiddleware { user_id }))
+        //the role of the token can be outdated, the user could be demoted or deleted
+        let connection = state.connection.clone();
+        let check_role = async move {
+            let role = db_get_user_role(user_id, &connection).await?;
+            Ok(JwtMiddleware { user_id, role })
+        };
+        Box::pin(check_role)
     }
 }
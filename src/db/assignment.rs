@@ -0,0 +1,731 @@
+use std::collections::HashMap;
+
+use crate::{
+    app::Storage,
+    db::{
+        db_patch_grade, db_write_audit,
+        grade::{check_enrolled, grade_recorded, insert_grade},
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{
+        validate_scores, AddAssignment, AddGrade, Assignment, AuditContext, PatchAssignment,
+        PatchGrade, Submission, SubmissionFile, UploadedFile,
+    },
+};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+//submission without files
+struct SubmissionRecord {
+    id: Uuid,
+    assignment_id: Uuid,
+    student_id: Uuid,
+    content: Option<String>,
+    submitted_at: OffsetDateTime,
+    late: bool,
+    grade_id: Option<Uuid>,
+}
+
+//assignments belong to the current term
+#[instrument(name = "Adding a new assignment to db", skip(connection), ret(Debug))]
+pub async fn db_add_assignment(
+    course_name: &str,
+    data: AddAssignment,
+    teacher_id: Option<Uuid>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Assignment, Error> {
+    let query_span = tracing::info_span!("Saving new assignment in database", %course_name);
+    let assignment = sqlx::query_as!(
+        Assignment,
+        r#"
+            insert into assignments
+                (course_name, term_id, title, description, due_at, max_score, weight, teacher_id)
+            values (
+                $1, (select id from terms where is_current), $2, $3, $4,
+                coalesce($5::float8, 100), coalesce($6::float8, 1), $7
+            )
+            returning *;
+        "#,
+        course_name,
+        data.title,
+        data.description,
+        data.due_at,
+        data.max_score,
+        data.weight,
+        teacher_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the assignment to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "create",
+        "assignment",
+        Some(assignment.id.to_string()),
+        None,
+        serde_json::to_value(&assignment).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(assignment)
+}
+
+#[instrument(name = "Get course's assignments", skip(connection))]
+pub async fn db_get_course_assignments(
+    course_name: &str,
+    connection: &PgPool,
+) -> Result<Vec<Assignment>, Error> {
+    let query_span = tracing::info_span!("Get assignments of course",%course_name);
+    sqlx::query_as!(
+        Assignment,
+        r#"
+            select * from assignments
+            where course_name = $1
+                and term_id is not distinct from (select id from terms where is_current)
+            order by due_at, title;
+        "#,
+        course_name
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get course's assignments".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+#[instrument(name = "Get assignment from db", skip(connection))]
+pub async fn db_get_assignment(
+    assignment_id: Uuid,
+    connection: &PgPool,
+) -> Result<Assignment, Error> {
+    let query_span = tracing::info_span!("Get assignment",%assignment_id);
+    sqlx::query_as!(
+        Assignment,
+        "select * from assignments where id = $1;",
+        assignment_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the assignment from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find assignment with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })
+}
+
+#[instrument(name = "Patching assignment", skip(connection), ret(Debug))]
+pub async fn db_patch_assignment(
+    assignment_id: Uuid,
+    data: PatchAssignment,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Assignment, Error> {
+    let before = db_get_assignment(assignment_id, connection).await?;
+
+    let query_span = tracing::info_span!("Updating assignment's data", %assignment_id);
+    let result = sqlx::query_as!(
+        Assignment,
+        r#"
+            update assignments set
+                title = coalesce($1, title),
+                description = coalesce($2, description),
+                due_at = coalesce($3, due_at),
+                max_score = coalesce($4, max_score),
+                weight = coalesce($5, weight)
+            where id = $6
+            returning *;
+        "#,
+        data.title,
+        data.description,
+        data.due_at,
+        data.max_score,
+        data.weight,
+        assignment_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new assignment's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "update",
+        "assignment",
+        Some(assignment_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//submissions are removed with the assignment, grades stay in the gradebook
+#[instrument(name = "Delete assignment from db", skip(storage, connection))]
+pub async fn db_delete_assignment(
+    assignment_id: Uuid,
+    storage: &dyn Storage,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = db_get_assignment(assignment_id, connection).await?;
+
+    let query_span = tracing::info_span!("Get files of assignment",%assignment_id);
+    let files = sqlx::query!(
+        r#"
+            select f.storage_key from submission_files f
+            join submissions s on s.id = f.submission_id
+            where s.assignment_id = $1;
+        "#,
+        assignment_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get files of the assignment".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Delete assignment",%assignment_id);
+    sqlx::query!("delete from assignments where id = $1;", assignment_id)
+        .execute(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not delete the assignment".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    remove_files(files.iter().map(|rec| rec.storage_key.as_str()), storage).await;
+
+    db_write_audit(
+        audit,
+        "delete",
+        "assignment",
+        Some(assignment_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    Ok(())
+}
+
+//resubmitting replaces the text and the files until the submission is graded
+#[instrument(name = "Submit assignment", skip(storage, connection), ret(Debug))]
+pub async fn db_submit_assignment(
+    assignment_id: Uuid,
+    student_id: Uuid,
+    content: Option<String>,
+    files: Vec<UploadedFile>,
+    storage: &dyn Storage,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Submission, Error> {
+    let assignment = db_get_assignment(assignment_id, connection).await?;
+
+    let query_span = tracing::info_span!("Check student's course",%student_id,course_name=%assignment.course_name);
+    let enrolled = sqlx::query!(
+        r#"
+            select exists (
+                select 1 from current_courses c
+                join students s on s.id = c.student_id
+                where c.student_id = $1 and c.course_name = $2 and s.deleted_at is null
+            ) as "exists!";
+        "#,
+        student_id,
+        assignment.course_name
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check student's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .exists;
+    if !enrolled {
+        return Err(Error::new(
+            None,
+            Some(format!(
+                "Student isn't enrolled in the course '{}'",
+                assignment.course_name
+            )),
+            ErrorTypes::ValidationError,
+        ));
+    }
+
+    //files are stored first, the database only references saved files
+    let mut keys = Vec::new();
+    for file in files.iter() {
+        let key = format!(
+            "submissions/{}/{}/{}",
+            assignment_id,
+            student_id,
+            Uuid::new_v4()
+        );
+        if let Err(e) = storage.put(&key, &file.data).await {
+            remove_files(keys.iter().map(String::as_str), storage).await;
+            return Err(e);
+        }
+        keys.push(key);
+    }
+
+    let result = save_submission(
+        assignment_id,
+        student_id,
+        content,
+        &files,
+        &keys,
+        connection,
+    )
+    .await;
+    let (submission_id, old_keys) = match result {
+        Ok(saved) => saved,
+        Err(e) => {
+            remove_files(keys.iter().map(String::as_str), storage).await;
+            return Err(e);
+        }
+    };
+    remove_files(old_keys.iter().map(String::as_str), storage).await;
+
+    let submission = db_get_submission(assignment_id, student_id, connection).await?;
+    if submission.late {
+        tracing::warn!(
+            "Submission_id {} - Submitted after the due date",
+            submission_id
+        );
+    }
+
+    db_write_audit(
+        audit,
+        "submit",
+        "assignment",
+        Some(assignment_id.to_string()),
+        None,
+        serde_json::to_value(&submission).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(submission)
+}
+
+//returns id of the submission and storage keys of the replaced files
+async fn save_submission(
+    assignment_id: Uuid,
+    student_id: Uuid,
+    content: Option<String>,
+    files: &[UploadedFile],
+    keys: &[String],
+    connection: &PgPool,
+) -> Result<(Uuid, Vec<String>), Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Saving submission",%assignment_id,%student_id);
+    let submission_id = sqlx::query!(
+        r#"
+            insert into submissions (assignment_id, student_id, content, submitted_at, late)
+            select a.id, $2, $3, now(), now() > a.due_at from assignments a where a.id = $1
+            on conflict (assignment_id, student_id) do update set
+                content = excluded.content,
+                submitted_at = excluded.submitted_at,
+                late = excluded.late
+            where submissions.grade_id is null
+            returning id;
+        "#,
+        assignment_id,
+        student_id,
+        content
+    )
+    .fetch_optional(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not save the submission".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    //graded submissions can't be replaced
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("The submission is already graded".into()),
+            ErrorTypes::Conflict,
+        )
+    })?
+    .id;
+
+    let query_span = tracing::info_span!("Remove old files",%submission_id);
+    let old_keys = sqlx::query!(
+        "delete from submission_files where submission_id = $1 returning storage_key;",
+        submission_id
+    )
+    .fetch_all(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not replace submission's files".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.storage_key)
+    .collect();
+
+    let names: Vec<String> = files.iter().map(|f| f.file_name.clone()).collect();
+    let types: Vec<String> = files.iter().map(|f| f.content_type.clone()).collect();
+    let sizes: Vec<i64> = files.iter().map(|f| f.data.len() as i64).collect();
+    let query_span = tracing::info_span!("Saving submission's files",%submission_id,files=?names);
+    sqlx::query!(
+        r#"
+            insert into submission_files (submission_id, file_name, content_type, size, storage_key)
+            select $1, * from unnest($2::varchar[], $3::varchar[], $4::int8[], $5::text[]);
+        "#,
+        submission_id,
+        &names,
+        &types,
+        &sizes,
+        keys
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not save submission's files".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not save the submission".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok((submission_id, old_keys))
+}
+
+//files are removed after the database changes, failures leave orphan files only
+pub(crate) async fn remove_files<'a>(keys: impl Iterator<Item = &'a str>, storage: &dyn Storage) {
+    for key in keys {
+        if let Err(e) = storage.delete(key).await {
+            tracing::error!("Can not remove file '{}': {}", key, e);
+        }
+    }
+}
+
+#[instrument(name = "Get assignment's submissions", skip(connection))]
+pub async fn db_get_submissions(
+    assignment_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<Submission>, Error> {
+    let query_span = tracing::info_span!("Get submissions of assignment",%assignment_id);
+    let submissions = sqlx::query_as!(
+        SubmissionRecord,
+        r#"
+            select sb.id, sb.assignment_id, sb.student_id, sb.content, sb.submitted_at,
+                sb.late, sb.grade_id
+            from submissions sb
+            join students s on s.id = sb.student_id
+            where sb.assignment_id = $1 and s.deleted_at is null
+            order by sb.submitted_at;
+        "#,
+        assignment_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get assignment's submissions".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    load_files(submissions, connection).await
+}
+
+#[instrument(name = "Get student's submission", skip(connection))]
+pub async fn db_get_submission(
+    assignment_id: Uuid,
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<Submission, Error> {
+    let query_span = tracing::info_span!("Get submission",%assignment_id,%student_id);
+    let submission = sqlx::query_as!(
+        SubmissionRecord,
+        r#"
+            select id, assignment_id, student_id, content, submitted_at, late, grade_id
+            from submissions where assignment_id = $1 and student_id = $2;
+        "#,
+        assignment_id,
+        student_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the submission from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("The student hasn't submitted the assignment".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    let mut submissions = load_files(vec![submission], connection).await?;
+    Ok(submissions.remove(0))
+}
+
+//file's details, id of the student who submitted it and the storage key
+#[instrument(name = "Get submission's file", skip(connection))]
+pub async fn db_get_submission_file(
+    file_id: Uuid,
+    connection: &PgPool,
+) -> Result<(SubmissionFile, Uuid, String), Error> {
+    let query_span = tracing::info_span!("Get file",%file_id);
+    let rec = sqlx::query!(
+        r#"
+            select f.id, f.file_name, f.content_type, f.size, f.storage_key, s.student_id
+            from submission_files f
+            join submissions s on s.id = f.submission_id
+            where f.id = $1;
+        "#,
+        file_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the file from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find file with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    let file = SubmissionFile {
+        id: rec.id,
+        file_name: rec.file_name,
+        content_type: rec.content_type,
+        size: rec.size,
+    };
+    Ok((file, rec.student_id, rec.storage_key))
+}
+
+//the grade is recorded in the gradebook, grading again changes the same grade
+#[instrument(name = "Grade submission", skip(connection), ret(Debug))]
+pub async fn db_grade_submission(
+    assignment_id: Uuid,
+    student_id: Uuid,
+    score: f64,
+    teacher_id: Option<Uuid>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Submission, Error> {
+    let assignment = db_get_assignment(assignment_id, connection).await?;
+    let submission = db_get_submission(assignment_id, student_id, connection).await?;
+
+    validate_scores(score, assignment.max_score, None).map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    })?;
+
+    match submission.grade_id {
+        Some(grade_id) => {
+            let data = PatchGrade {
+                assessment: None,
+                score: Some(score),
+                max_score: Some(assignment.max_score),
+                weight: Some(assignment.weight),
+                graded_on: None,
+            };
+            db_patch_grade(grade_id, data, audit, connection).await?;
+        }
+        None => {
+            let data = AddGrade {
+                course_name: assignment.course_name,
+                assessment: assignment.title,
+                score,
+                max_score: assignment.max_score,
+                weight: Some(assignment.weight),
+                graded_on: None,
+            };
+            check_enrolled(student_id, &data.course_name, connection).await?;
+
+            //the grade is kept only if it's linked, a concurrent grading links its own
+            let mut transaction = connection.begin().await.map_err(|e| {
+                Error::new(
+                    Some(e.to_string()),
+                    Some("Can not start transaction".into()),
+                    ErrorTypes::DbError,
+                )
+            })?;
+            let grade = insert_grade(student_id, &data, teacher_id, &mut transaction).await?;
+
+            let query_span = tracing::info_span!("Link grade to submission",submission_id=%submission.id,grade_id=%grade.id);
+            let linked = sqlx::query!(
+                "update submissions set grade_id = $1 where id = $2 and grade_id is null returning id;",
+                grade.id,
+                submission.id
+            )
+            .fetch_optional(&mut transaction)
+            .instrument(query_span)
+            .await
+            .map_err(|e| {
+                Error::new(
+                    Some(e.to_string()),
+                    Some("Can not link the grade to the submission".into()),
+                    ErrorTypes::DbError,
+                )
+            })?;
+            if linked.is_none() {
+                return Err(Error::new(
+                    None,
+                    Some("The submission has been graded meanwhile, grade it again".into()),
+                    ErrorTypes::Conflict,
+                ));
+            }
+            transaction.commit().await.map_err(|e| {
+                Error::new(
+                    Some(e.to_string()),
+                    Some("Can not link the grade to the submission".into()),
+                    ErrorTypes::DbError,
+                )
+            })?;
+
+            grade_recorded(&grade, audit, connection).await;
+        }
+    }
+
+    db_get_submission(assignment_id, student_id, connection).await
+}
+
+async fn load_files(
+    submissions: Vec<SubmissionRecord>,
+    connection: &PgPool,
+) -> Result<Vec<Submission>, Error> {
+    let ids: Vec<Uuid> = submissions.iter().map(|s| s.id).collect();
+    let query_span = tracing::info_span!("Get submissions' files");
+    let rows = sqlx::query!(
+        r#"
+            select id, submission_id, file_name, content_type, size from submission_files
+            where submission_id = any($1) order by file_name;
+        "#,
+        &ids
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get submissions' files".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let mut files: HashMap<Uuid, Vec<SubmissionFile>> = HashMap::new();
+    for rec in rows {
+        files
+            .entry(rec.submission_id)
+            .or_default()
+            .push(SubmissionFile {
+                id: rec.id,
+                file_name: rec.file_name,
+                content_type: rec.content_type,
+                size: rec.size,
+            });
+    }
+
+    Ok(submissions
+        .into_iter()
+        .map(|s| Submission {
+            files: files.remove(&s.id).unwrap_or_default(),
+            id: s.id,
+            assignment_id: s.assignment_id,
+            student_id: s.student_id,
+            content: s.content,
+            submitted_at: s.submitted_at,
+            late: s.late,
+            grade_id: s.grade_id,
+        })
+        .collect())
+}
@@ -0,0 +1,215 @@
+use crate::{
+    db::{db_notify_absences, db_write_audit},
+    errors::{Error, ErrorTypes},
+    schemas::{
+        Attendance, AttendanceFilter, AttendanceRate, AttendanceStatus, AuditContext,
+        MarkAttendance,
+    },
+};
+use sqlx::PgPool;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+//set attendance of the class, already marked students are overwritten
+#[instrument(name = "Marking attendance", skip(connection))]
+pub async fn db_mark_attendance(
+    course_name: &str,
+    data: MarkAttendance,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Vec<Attendance>, Error> {
+    //all students must be enrolled in the course
+    let student_ids: Vec<Uuid> = data.records.iter().map(|r| r.student_id).collect();
+    let query_span = tracing::info_span!("Check course's students", %course_name);
+    let enrolled: Vec<Uuid> = sqlx::query!(
+        r#"
+            select s.id from students s
+            join current_courses c on c.student_id = s.id
+            where c.course_name = $1 and s.id = any($2) and s.deleted_at is null;
+        "#,
+        course_name,
+        &student_ids
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get course's students".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.id)
+    .collect();
+
+    let not_enrolled: Vec<String> = student_ids
+        .iter()
+        .filter(|id| !enrolled.contains(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !not_enrolled.is_empty() {
+        return Err(Error::new(
+            Some(not_enrolled.join(", ")),
+            Some(format!(
+                "Students aren't enrolled in the course '{}'",
+                course_name
+            )),
+            ErrorTypes::ValidationError,
+        ));
+    }
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let date = data.date;
+    let mut marked = Vec::with_capacity(data.records.len());
+    for record in data.records {
+        let query_span = tracing::info_span!("Save attendance", student_id=%record.student_id);
+        let attendance = sqlx::query_as!(
+            Attendance,
+            r#"
+                insert into attendance
+                    (student_id, course_name, session_date, status, note, recorded_by)
+                values ($1, $2, $3, $4, $5, $6)
+                on conflict (student_id, course_name, session_date) do update set
+                    status = excluded.status,
+                    note = excluded.note,
+                    recorded_by = excluded.recorded_by,
+                    updated_at = now()
+                returning id, student_id, course_name, session_date,
+                    status as "status: AttendanceStatus", note, recorded_by,
+                    created_at, updated_at;
+            "#,
+            record.student_id,
+            course_name,
+            data.date,
+            record.status as AttendanceStatus,
+            record.note,
+            audit.actor_id
+        )
+        .fetch_one(&mut transaction)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not save attendance to db".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+        marked.push(attendance);
+    }
+
+    db_notify_absences(course_name, date, &marked, &mut transaction).await?;
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not save attendance to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "mark_attendance",
+        "course",
+        Some(course_name.to_string()),
+        None,
+        serde_json::to_value(&marked).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(marked)
+}
+
+#[instrument(name = "Get student's attendance", skip(connection))]
+pub async fn db_get_student_attendance(
+    student_id: Uuid,
+    filter: &AttendanceFilter,
+    connection: &PgPool,
+) -> Result<Vec<Attendance>, Error> {
+    let query_span = tracing::info_span!("Get attendance of student", %student_id);
+    sqlx::query_as!(
+        Attendance,
+        r#"
+            select id, student_id, course_name, session_date,
+                status as "status: AttendanceStatus", note, recorded_by,
+                created_at, updated_at
+            from attendance
+            where student_id = $1
+                and ($2::text is null or course_name = $2)
+                and ($3::date is null or session_date >= $3)
+                and ($4::date is null or session_date <= $4)
+            order by session_date desc, course_name;
+        "#,
+        student_id,
+        filter.course,
+        filter.from,
+        filter.to
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's attendance".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//attendance rates of every course in the date range
+#[instrument(name = "Get attendance rates", skip(connection))]
+pub async fn db_get_attendance_rates(
+    filter: &AttendanceFilter,
+    connection: &PgPool,
+) -> Result<Vec<AttendanceRate>, Error> {
+    let query_span = tracing::info_span!("Aggregate attendance by course");
+    sqlx::query_as!(
+        AttendanceRate,
+        r#"
+            select
+                a.course_name,
+                count(*) as "records!",
+                count(*) filter (where a.status = 'present') as "present!",
+                count(*) filter (where a.status = 'absent') as "absent!",
+                count(*) filter (where a.status = 'late') as "late!",
+                count(*) filter (where a.status = 'excused') as "excused!",
+                round(
+                    100.0 * count(*) filter (where a.status in ('present', 'late'))
+                    / nullif(count(*) filter (where a.status <> 'excused'), 0),
+                    2
+                )::float8 as rate
+            from attendance a
+            join students s on s.id = a.student_id
+            where s.deleted_at is null
+                and ($1::text is null or a.course_name = $1)
+                and ($2::date is null or a.session_date >= $2)
+                and ($3::date is null or a.session_date <= $3)
+            group by a.course_name
+            order by a.course_name;
+        "#,
+        filter.course,
+        filter.from,
+        filter.to
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get attendance rates".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
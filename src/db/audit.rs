@@ -0,0 +1,113 @@
+use crate::{
+    errors::{Error, ErrorTypes},
+    schemas::{AuditContext, AuditEntry, AuditFilter},
+};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use tracing::{instrument, Instrument};
+
+//save the change to the audit log. Failures are only logged, they mustn't break the operation
+#[instrument(name = "Write audit log", skip(connection, before, after))]
+pub async fn db_write_audit(
+    context: &AuditContext,
+    action: &str,
+    entity: &str,
+    entity_id: Option<String>,
+    before: Option<Value>,
+    after: Option<Value>,
+    connection: &PgPool,
+) {
+    let diff = json_diff(before.as_ref(), after.as_ref());
+
+    let query_span = tracing::info_span!("Inserting audit entry", %action, %entity);
+    if let Err(e) = sqlx::query!(
+        r#"
+            insert into audit_log
+                (actor_id, action, entity, entity_id, before_data, after_data, diff, request_id)
+            values ($1, $2, $3, $4, $5, $6, $7, $8);
+        "#,
+        context.actor_id,
+        action,
+        entity,
+        entity_id,
+        before,
+        after,
+        diff,
+        context.request_id
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    {
+        tracing::error!("Can not write audit entry: {}", e);
+    }
+}
+
+#[instrument(name = "Get audit log", skip(connection))]
+pub async fn db_get_audit_log(
+    filter: &AuditFilter,
+    connection: &PgPool,
+) -> Result<Vec<AuditEntry>, Error> {
+    let query_span = tracing::info_span!("Get audit entries");
+    sqlx::query_as!(
+        AuditEntry,
+        r#"
+            select id, actor_id, action, entity, entity_id,
+                before_data as before, after_data as after, diff, request_id, created_at
+            from audit_log
+            where ($1::uuid is null or actor_id = $1)
+                and ($2::text is null or action = $2)
+                and ($3::text is null or entity = $3)
+                and ($4::text is null or entity_id = $4)
+                and ($5::timestamptz is null or created_at >= $5)
+                and ($6::timestamptz is null or created_at <= $6)
+            order by created_at desc, id desc
+            limit $7 offset $8;
+        "#,
+        filter.actor_id,
+        filter.action,
+        filter.entity,
+        filter.entity_id,
+        filter.from,
+        filter.to,
+        filter.limit.unwrap_or(100).clamp(1, 1000),
+        filter.offset.unwrap_or(0).max(0)
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get audit log".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//changed fields of two json objects: {"field": {"before": .., "after": ..}}
+fn json_diff(before: Option<&Value>, after: Option<&Value>) -> Option<Value> {
+    let (Some(Value::Object(before)), Some(Value::Object(after))) = (before, after) else {
+        return None;
+    };
+
+    let mut diff = Map::new();
+    for (key, new_value) in after {
+        let old_value = before.get(key).unwrap_or(&Value::Null);
+        if old_value != new_value {
+            diff.insert(
+                key.clone(),
+                serde_json::json!({"before": old_value, "after": new_value}),
+            );
+        }
+    }
+    for (key, old_value) in before {
+        if !after.contains_key(key) {
+            diff.insert(
+                key.clone(),
+                serde_json::json!({"before": old_value, "after": Value::Null}),
+            );
+        }
+    }
+    Some(Value::Object(diff))
+}
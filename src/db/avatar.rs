@@ -0,0 +1,181 @@
+use crate::{
+    app::{avatar_key, Storage},
+    db::{assignment::remove_files, db_write_audit},
+    errors::{Error, ErrorTypes},
+    schemas::{AuditContext, StudentAvatar, AVATAR_SIZES},
+};
+use sqlx::PgPool;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Get student's avatar from db", skip(connection))]
+pub async fn db_get_avatar(
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<Option<StudentAvatar>, Error> {
+    let query_span = tracing::info_span!("Get avatar",%student_id);
+    let avatar = sqlx::query!(
+        r#"
+            select a.student_id, a.etag, a.updated_at from student_avatars a
+            join students s on s.id = a.student_id
+            where a.student_id = $1 and s.deleted_at is null;
+        "#,
+        student_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's avatar".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(avatar.map(|rec| StudentAvatar {
+        student_id: rec.student_id,
+        etag: rec.etag,
+        updated_at: rec.updated_at,
+        sizes: AVATAR_SIZES.to_vec(),
+    }))
+}
+
+//thumbnails are stored before the database changes, the old ones are removed after
+#[instrument(name = "Set student's avatar", skip(thumbnails, storage, connection))]
+pub async fn db_set_avatar(
+    student_id: Uuid,
+    etag: String,
+    thumbnails: Vec<(u32, Vec<u8>)>,
+    storage: &dyn Storage,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<StudentAvatar, Error> {
+    let before = db_get_avatar(student_id, connection).await?;
+
+    let mut keys = Vec::with_capacity(thumbnails.len());
+    for (size, data) in thumbnails.iter() {
+        let key = avatar_key(student_id, &etag, *size);
+        if let Err(e) = storage.put(&key, data).await {
+            remove_files(keys.iter().map(String::as_str), storage).await;
+            return Err(e);
+        }
+        keys.push(key);
+    }
+
+    let query_span = tracing::info_span!("Saving avatar",%student_id);
+    let saved = sqlx::query!(
+        r#"
+            insert into student_avatars (student_id, etag)
+            select s.id, $2 from students s where s.id = $1 and s.deleted_at is null
+            on conflict (student_id) do update set etag = excluded.etag, updated_at = now()
+            returning updated_at;
+        "#,
+        student_id,
+        etag
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await;
+
+    let updated_at = match saved {
+        Ok(Some(rec)) => rec.updated_at,
+        Ok(None) => {
+            remove_files(keys.iter().map(String::as_str), storage).await;
+            return Err(Error::new(
+                Some(format!("Student id: {}", student_id)),
+                Some("Student not found".into()),
+                ErrorTypes::NotFoundError,
+            ));
+        }
+        Err(e) => {
+            remove_files(keys.iter().map(String::as_str), storage).await;
+            return Err(Error::new(
+                Some(e.to_string()),
+                Some("Can not save student's avatar".into()),
+                ErrorTypes::DbError,
+            ));
+        }
+    };
+
+    let avatar = StudentAvatar {
+        student_id,
+        etag,
+        updated_at,
+        sizes: AVATAR_SIZES.to_vec(),
+    };
+    if let Some(old) = before.as_ref().filter(|old| old.etag != avatar.etag) {
+        let old_keys: Vec<String> = AVATAR_SIZES
+            .iter()
+            .map(|size| avatar_key(student_id, &old.etag, *size))
+            .collect();
+        remove_files(old_keys.iter().map(String::as_str), storage).await;
+    }
+
+    db_write_audit(
+        audit,
+        "set_avatar",
+        "student",
+        Some(student_id.to_string()),
+        before.and_then(|a| serde_json::to_value(a).ok()),
+        serde_json::to_value(&avatar).ok(),
+        connection,
+    )
+    .await;
+    Ok(avatar)
+}
+
+//the student gets the Gravatar image back
+#[instrument(name = "Delete student's avatar", skip(storage, connection))]
+pub async fn db_delete_avatar(
+    student_id: Uuid,
+    storage: &dyn Storage,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = db_get_avatar(student_id, connection).await?;
+
+    let query_span = tracing::info_span!("Delete avatar",%student_id);
+    let deleted = sqlx::query!(
+        "delete from student_avatars where student_id = $1 returning etag;",
+        student_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not delete student's avatar".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let etag = match deleted {
+        Some(rec) => rec.etag,
+        None => {
+            return Err(Error::new(
+                Some(format!("Student id: {}", student_id)),
+                Some("Student has no uploaded avatar".into()),
+                ErrorTypes::NotFoundError,
+            ))
+        }
+    };
+    let keys: Vec<String> = AVATAR_SIZES
+        .iter()
+        .map(|size| avatar_key(student_id, &etag, *size))
+        .collect();
+    remove_files(keys.iter().map(String::as_str), storage).await;
+
+    db_write_audit(
+        audit,
+        "delete_avatar",
+        "student",
+        Some(student_id.to_string()),
+        before.and_then(|a| serde_json::to_value(a).ok()),
+        None,
+        connection,
+    )
+    .await;
+    Ok(())
+}
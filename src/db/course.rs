@@ -0,0 +1,670 @@
+use crate::{
+    db::db_write_audit,
+    errors::{Error, ErrorTypes},
+    schemas::{AuditContext, CourseCapacity, Prerequisite, UnmetPrerequisite},
+};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Get course's capacity", skip(connection))]
+pub async fn db_get_course_capacity(
+    course_name: &str,
+    connection: &PgPool,
+) -> Result<CourseCapacity, Error> {
+    let query_span = tracing::info_span!("Get course's seats",%course_name);
+    let seats = sqlx::query!(
+        r#"
+            select
+                (select max_seats from course_capacities where course_name = $1) as max_seats,
+                (
+                    select count(*) from current_courses c
+                    join students s on s.id = c.student_id
+                    where c.course_name = $1 and s.deleted_at is null
+                ) as "enrolled!";
+        "#,
+        course_name
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get course's capacity".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Get course's waitlist",%course_name);
+    let waitlist = sqlx::query!(
+        r#"
+            select w.student_id as "student_id!" from current_waitlist w
+            join students s on s.id = w.student_id
+            where w.course_name = $1 and s.deleted_at is null
+            order by w.id;
+        "#,
+        course_name
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get course's waitlist".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(CourseCapacity {
+        course_name: course_name.to_string(),
+        max_seats: seats.max_seats,
+        enrolled: seats.enrolled,
+        waitlist: waitlist.into_iter().map(|rec| rec.student_id).collect(),
+    })
+}
+
+//no limit without `max_seats`, new seats go to the waitlist
+#[instrument(name = "Set course's capacity", skip(connection), ret(Debug))]
+pub async fn db_set_course_capacity(
+    course_name: &str,
+    max_seats: Option<i32>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<CourseCapacity, Error> {
+    let before = db_get_course_capacity(course_name, connection).await?;
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Set course's seats",%course_name,?max_seats);
+    let result = match max_seats {
+        Some(max_seats) => {
+            sqlx::query!(
+                r#"
+                    insert into course_capacities (course_name, max_seats) values ($1, $2)
+                    on conflict (course_name) do update set max_seats = excluded.max_seats;
+                "#,
+                course_name,
+                max_seats
+            )
+            .execute(&mut transaction)
+            .instrument(query_span)
+            .await
+        }
+        None => {
+            sqlx::query!(
+                "delete from course_capacities where course_name = $1;",
+                course_name
+            )
+            .execute(&mut transaction)
+            .instrument(query_span)
+            .await
+        }
+    };
+    result.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set course's capacity".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let promoted = promote_waitlist(&[course_name.to_string()], &mut transaction).await?;
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set course's capacity".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    audit_promoted(&promoted, audit, connection).await;
+
+    let result = db_get_course_capacity(course_name, connection).await?;
+    db_write_audit(
+        audit,
+        "set_capacity",
+        "course",
+        Some(course_name.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//locks the capacities of the courses until the end of the transaction, so concurrent
+//enrollments into the same courses count the seats one after another
+async fn lock_capacities(
+    courses: &[String],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Lock courses' capacities",courses=?courses);
+    sqlx::query!(
+        r#"
+            select course_name from course_capacities
+            where course_name = any($1)
+            order by course_name
+            for update;
+        "#,
+        courses
+    )
+    .fetch_all(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check courses' capacity".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
+
+//courses without free seats, the student's own enrollments don't need a new seat
+async fn full_courses(
+    courses: &[String],
+    student_id: Uuid,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<String>, Error> {
+    lock_capacities(courses, transaction).await?;
+
+    let query_span = tracing::info_span!("Check courses' seats",%student_id,courses=?courses);
+    Ok(sqlx::query!(
+        r#"
+            select cc.course_name from course_capacities cc
+            where cc.course_name = any($1)
+                and not exists (
+                    select 1 from current_courses c
+                    where c.student_id = $2 and c.course_name = cc.course_name
+                )
+                and (
+                    select count(*) from current_courses c
+                    join students s on s.id = c.student_id
+                    where c.course_name = cc.course_name and s.deleted_at is null
+                ) >= cc.max_seats
+            order by cc.course_name;
+        "#,
+        courses,
+        student_id
+    )
+    .fetch_all(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check courses' capacity".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.course_name)
+    .collect())
+}
+
+//courses with more enrolled students than seats, e.g. after a deleted student is restored
+//and its seats have been taken from the waitlist meanwhile
+pub(crate) async fn overfull_courses(
+    courses: &[String],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<String>, Error> {
+    lock_capacities(courses, transaction).await?;
+
+    let query_span = tracing::info_span!("Check courses' seats",courses=?courses);
+    Ok(sqlx::query!(
+        r#"
+            select cc.course_name from course_capacities cc
+            where cc.course_name = any($1)
+                and (
+                    select count(*) from current_courses c
+                    join students s on s.id = c.student_id
+                    where c.course_name = cc.course_name and s.deleted_at is null
+                ) > cc.max_seats
+            order by cc.course_name;
+        "#,
+        courses
+    )
+    .fetch_all(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check courses' capacity".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.course_name)
+    .collect())
+}
+
+//split courses into available and full ones, full courses are an error without the waitlist.
+//The seats stay locked until the transaction enrolling the student ends
+pub(crate) async fn check_capacity(
+    courses: &[String],
+    student_id: Uuid,
+    waitlist: bool,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(Vec<String>, Vec<String>), Error> {
+    let full = full_courses(courses, student_id, transaction).await?;
+    if !full.is_empty() && !waitlist {
+        return Err(Error::new(
+            Some(full.join(", ")),
+            Some("Courses are full. Send `waitlist: true` to join the waitlist".into()),
+            ErrorTypes::Conflict,
+        ));
+    }
+
+    let available = courses
+        .iter()
+        .filter(|c| !full.contains(c))
+        .cloned()
+        .collect();
+    Ok((available, full))
+}
+
+//put the student at the end of the courses' waitlists
+pub(crate) async fn join_waitlist(
+    student_id: Uuid,
+    courses: &[String],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Join waitlist",%student_id,courses=?courses);
+    sqlx::query!(
+        r#"
+            insert into course_waitlist (course_name, student_id, term_id)
+            select distinct c, $1::uuid, (select id from terms where is_current)
+            from unnest($2::text[]) as c
+            where not exists (
+                select 1 from current_waitlist where student_id = $1 and course_name = c
+            );
+        "#,
+        student_id,
+        courses
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not add the student to the waitlist".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(())
+}
+
+//remove the student from the waitlists of the courses, or of all other courses with `keep`
+pub(crate) async fn leave_waitlist(
+    student_id: Uuid,
+    courses: &[String],
+    keep: bool,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Leave waitlist",%student_id,courses=?courses,keep);
+    sqlx::query!(
+        r#"
+            delete from course_waitlist where student_id = $1
+                and term_id is not distinct from (select id from terms where is_current)
+                and (course_name = any($2)) <> $3;
+        "#,
+        student_id,
+        courses,
+        keep
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not remove the student from the waitlist".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(())
+}
+
+//enroll students from the waitlists of the courses which got free seats, returns the
+//enrolled students and courses for `audit_promoted`
+pub(crate) async fn promote_waitlist(
+    courses: &[String],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<(Uuid, String)>, Error> {
+    if courses.is_empty() {
+        return Ok(Vec::new());
+    }
+    lock_capacities(courses, transaction).await?;
+
+    let query_span = tracing::info_span!("Promote students from waitlist",courses=?courses);
+    let promoted = sqlx::query!(
+        r#"
+            with seats as (
+                select cc.course_name, cc.max_seats - (
+                    select count(*) from current_courses c
+                    join students s on s.id = c.student_id
+                    where c.course_name = cc.course_name and s.deleted_at is null
+                ) as free
+                from course_capacities cc
+                where cc.course_name = any($1)
+            ),
+            queue as (
+                select w.id, row_number() over (partition by w.course_name order by w.id) as position
+                from current_waitlist w
+                join students s on s.id = w.student_id
+                where w.course_name = any($1) and s.deleted_at is null and not exists (
+                    select 1 from current_courses c
+                    where c.student_id = w.student_id and c.course_name = w.course_name
+                )
+            ),
+            promoted as (
+                delete from course_waitlist w
+                using queue q
+                where w.id = q.id and q.position <= coalesce(
+                    (select free from seats where seats.course_name = w.course_name),
+                    q.position
+                )
+                returning w.student_id, w.course_name, w.term_id
+            )
+            insert into courses (student_id, course_name, term_id)
+            select student_id, course_name, term_id from promoted
+            returning student_id as "student_id!", course_name as "course_name!";
+        "#,
+        courses
+    )
+    .fetch_all(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not promote students from the waitlist".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(promoted
+        .into_iter()
+        .map(|rec| (rec.student_id, rec.course_name))
+        .collect())
+}
+
+//audit of the students enrolled by `promote_waitlist`, after the transaction is committed
+pub(crate) async fn audit_promoted(
+    promoted: &[(Uuid, String)],
+    audit: &AuditContext,
+    connection: &PgPool,
+) {
+    for (student_id, course_name) in promoted.iter() {
+        tracing::info!(
+            "Student_id {} - Enrolled in {} from the waitlist",
+            student_id,
+            course_name
+        );
+        db_write_audit(
+            audit,
+            "promote_waitlist",
+            "student",
+            Some(student_id.to_string()),
+            None,
+            serde_json::to_value(course_name).ok(),
+            connection,
+        )
+        .await;
+    }
+}
+
+//courses the student waits for in the current term
+pub(crate) async fn get_waitlist(
+    student_id: Uuid,
+    connection: impl PgExecutor<'_>,
+) -> Result<Vec<String>, Error> {
+    let query_span = tracing::info_span!("Get student's waitlist",%student_id);
+    Ok(sqlx::query!(
+        r#"
+            select course_name as "course_name!" from current_waitlist
+            where student_id = $1 order by course_name;
+        "#,
+        student_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's waitlist".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.course_name)
+    .collect())
+}
+
+#[instrument(name = "Get course's prerequisites", skip(connection))]
+pub async fn db_get_prerequisites(
+    course_name: &str,
+    connection: &PgPool,
+) -> Result<Vec<Prerequisite>, Error> {
+    let query_span = tracing::info_span!("Get prerequisites of course",%course_name);
+    sqlx::query_as!(
+        Prerequisite,
+        r#"
+            select prerequisite as course_name, min_grade from course_prerequisites
+            where course_name = $1 order by prerequisite;
+        "#,
+        course_name
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get course's prerequisites".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//replaces all prerequisites of the course
+#[instrument(name = "Set course's prerequisites", skip(connection), ret(Debug))]
+pub async fn db_set_prerequisites(
+    course_name: &str,
+    prerequisites: Vec<Prerequisite>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Vec<Prerequisite>, Error> {
+    if prerequisites.iter().any(|p| p.course_name == course_name) {
+        return Err(Error::new(
+            None,
+            Some("The course can't be a prerequisite of itself".into()),
+            ErrorTypes::ValidationError,
+        ));
+    }
+
+    let before = db_get_prerequisites(course_name, connection).await?;
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    //concurrent changes could close a cycle which neither of them sees
+    let query_span = tracing::info_span!("Lock prerequisites");
+    sqlx::query("lock table course_prerequisites in share row exclusive mode;")
+        .execute(&mut transaction)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not lock course's prerequisites".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    let query_span = tracing::info_span!("Remove old prerequisites",%course_name);
+    sqlx::query!(
+        "delete from course_prerequisites where course_name = $1;",
+        course_name
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not remove course's prerequisites".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let (names, grades): (Vec<String>, Vec<f64>) = prerequisites
+        .into_iter()
+        .map(|p| (p.course_name, p.min_grade))
+        .unzip();
+    let query_span =
+        tracing::info_span!("Insert new prerequisites",%course_name,prerequisites=?names);
+    sqlx::query!(
+        r#"
+            insert into course_prerequisites (course_name, prerequisite, min_grade)
+            select $1, * from unnest($2::text[], $3::float8[])
+            on conflict (course_name, prerequisite) do update set min_grade = excluded.min_grade;
+        "#,
+        course_name,
+        &names,
+        &grades
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not save course's prerequisites".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Check prerequisites for cycles",%course_name);
+    let cycle = sqlx::query_scalar!(
+        r#"
+            with recursive required(name) as (
+                select prerequisite from course_prerequisites where course_name = $1
+                union
+                select p.prerequisite from course_prerequisites p
+                join required r on p.course_name = r.name
+            )
+            select exists(select 1 from required where name = $1) as "cycle!";
+        "#,
+        course_name
+    )
+    .fetch_one(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check course's prerequisites".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    if cycle {
+        return Err(Error::new(
+            None,
+            Some("The course can't be a prerequisite of its prerequisites".into()),
+            ErrorTypes::ValidationError,
+        ));
+    }
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not save course's prerequisites".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let result = db_get_prerequisites(course_name, connection).await?;
+    db_write_audit(
+        audit,
+        "set_prerequisites",
+        "course",
+        Some(course_name.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//prerequisites of the new courses the student hasn't passed with the minimum grade
+pub(crate) async fn check_prerequisites(
+    courses: &[String],
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let query_span =
+        tracing::info_span!("Check courses' prerequisites",%student_id,courses=?courses);
+    let unmet = sqlx::query_as!(
+        UnmetPrerequisite,
+        r#"
+            select p.course_name, p.prerequisite, p.min_grade, g.grade
+            from course_prerequisites p
+            left join lateral (
+                select round((sum(score / max_score * weight) / sum(weight) * 100)::numeric, 2)::float8 as grade
+                from grades where student_id = $2 and course_name = p.prerequisite
+            ) g on true
+            where p.course_name = any($1)
+                and not exists (
+                    select 1 from current_courses c
+                    where c.student_id = $2 and c.course_name = p.course_name
+                )
+                and (g.grade is null or g.grade < p.min_grade)
+            order by p.course_name, p.prerequisite;
+        "#,
+        courses,
+        student_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check courses' prerequisites".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if !unmet.is_empty() {
+        return Err(Error::new(
+            Some(serde_json::to_string_pretty(&unmet).unwrap()),
+            Some("Prerequisites of the courses aren't met".into()),
+            ErrorTypes::ValidationError,
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use crate::{
+    app::OutboxSettings,
+    db::outbox::{retry_after, LEASE_SECONDS},
+    errors::{Error, ErrorTypes},
+    schemas::{Attendance, AttendanceStatus, EmailTemplate, FullStudent, Grade, QueuedEmail},
+};
+use sqlx::{PgPool, Postgres, Transaction};
+use time::Date;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+//put the email to the outbox in the transaction of the change it's about,
+//so it's sent only if the change is committed
+#[instrument(name = "Enqueue email", skip(vars, transaction))]
+pub async fn db_enqueue_email(
+    template: EmailTemplate,
+    recipient: &str,
+    vars: &[(&str, &str)],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let (subject, body) = template.render(vars);
+
+    let query_span = tracing::info_span!("Inserting email", template = template.name());
+    sqlx::query!(
+        r#"
+            insert into email_outbox (template, recipient, subject, body)
+            values ($1, $2, $3, $4);
+        "#,
+        template.name(),
+        recipient,
+        subject,
+        body
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not enqueue email".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
+
+pub async fn db_notify_welcome(
+    student: &FullStudent,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let courses = if student.courses.is_empty() {
+        "none yet".to_string()
+    } else {
+        student.courses.join(", ")
+    };
+    db_enqueue_email(
+        EmailTemplate::Welcome,
+        &student.email,
+        &[("student_name", &student.full_name), ("courses", &courses)],
+        transaction,
+    )
+    .await
+}
+
+#[instrument(name = "Notify about grade", skip(grade, transaction))]
+pub async fn db_notify_grade_posted(
+    grade: &Grade,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Get graded student", student_id=%grade.student_id);
+    let student = sqlx::query!(
+        "select full_name, email from students where id = $1 and deleted_at is null;",
+        grade.student_id
+    )
+    .fetch_optional(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get graded student".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    let Some(student) = student else {
+        return Ok(());
+    };
+
+    db_enqueue_email(
+        EmailTemplate::GradePosted,
+        &student.email,
+        &[
+            ("student_name", &student.full_name),
+            ("course_name", &grade.course_name),
+            ("assessment", &grade.assessment),
+            ("score", &grade.score.to_string()),
+            ("max_score", &grade.max_score.to_string()),
+            ("graded_on", &grade.graded_on.to_string()),
+        ],
+        transaction,
+    )
+    .await
+}
+
+//guardians of absent students are alerted
+#[instrument(name = "Notify about absences", skip(records, transaction))]
+pub async fn db_notify_absences(
+    course_name: &str,
+    date: Date,
+    records: &[Attendance],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let absent: Vec<Uuid> = records
+        .iter()
+        .filter(|r| r.status == AttendanceStatus::Absent)
+        .map(|r| r.student_id)
+        .collect();
+    if absent.is_empty() {
+        return Ok(());
+    }
+
+    let query_span = tracing::info_span!("Get guardians of absent students");
+    let guardians = sqlx::query!(
+        r#"
+            select s.full_name as "student_name!", g.full_name as "guardian_name!", g.email
+            from student_guardians sg
+            join students s on s.id = sg.student_id
+            join guardians g on g.id = sg.guardian_id
+            where sg.student_id = any($1) and s.deleted_at is null;
+        "#,
+        &absent
+    )
+    .fetch_all(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get guardians of absent students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let date = date.to_string();
+    for guardian in guardians {
+        db_enqueue_email(
+            EmailTemplate::AbsenceAlert,
+            &guardian.email,
+            &[
+                ("guardian_name", &guardian.guardian_name),
+                ("student_name", &guardian.student_name),
+                ("course_name", course_name),
+                ("date", &date),
+            ],
+            transaction,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+//take emails due to be sent, concurrent senders get different ones
+#[instrument(name = "Claim queued emails", skip(connection))]
+pub async fn db_claim_emails(
+    batch_size: i64,
+    connection: &PgPool,
+) -> Result<Vec<QueuedEmail>, Error> {
+    let query_span = tracing::info_span!("Claim emails");
+    sqlx::query_as!(
+        QueuedEmail,
+        r#"
+            update email_outbox set
+                attempts = attempts + 1,
+                next_attempt_at = now() + make_interval(secs => $2)
+            where id in (
+                select id from email_outbox
+                where sent_at is null and failed_at is null and next_attempt_at <= now()
+                order by next_attempt_at
+                limit $1
+                for update skip locked
+            )
+            returning id, recipient, subject, body, attempts;
+        "#,
+        batch_size,
+        LEASE_SECONDS
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get queued emails".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+#[instrument(name = "Mark email as sent", skip(connection))]
+pub async fn db_email_sent(email_id: Uuid, connection: &PgPool) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Update sent email", %email_id);
+    sqlx::query!(
+        "update email_outbox set sent_at = now(), last_error = null where id = $1;",
+        email_id
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not update the email".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
+
+//the email is retried later, or failed when all attempts are used
+#[instrument(name = "Mark email as failed", skip(email, settings, connection))]
+pub async fn db_email_failed(
+    email: &QueuedEmail,
+    error: &str,
+    settings: &OutboxSettings,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let (delay, failed) = retry_after(email.attempts, settings);
+    let query_span = tracing::info_span!("Update failed email", email_id=%email.id);
+    sqlx::query!(
+        r#"
+            update email_outbox set
+                last_error = $2,
+                next_attempt_at = now() + make_interval(secs => $3),
+                failed_at = case when $4 then now() end
+            where id = $1;
+        "#,
+        email.id,
+        error,
+        delay,
+        failed
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not update the email".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
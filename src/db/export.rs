@@ -0,0 +1,60 @@
+use futures_util::{stream::BoxStream, StreamExt};
+use sqlx::PgPool;
+
+use crate::{
+    errors::{Error, ErrorTypes},
+    schemas::{ExportedStudent, StudentFilter},
+};
+
+//rows are fetched one by one instead of loading all students
+pub fn db_export_students<'a>(
+    filter: &StudentFilter,
+    connection: &'a PgPool,
+) -> BoxStream<'a, Result<ExportedStudent, Error>> {
+    tracing::info!("Export students from db, filter: {:?}", filter);
+
+    sqlx::query_as!(
+        ExportedStudent,
+        r#"
+            select s.id, s.full_name, s.email, s.age, s.registration_date,
+                s.created_by, s.updated_by, s.updated_at, s.deleted_at,
+                coalesce((
+                    select array_agg(c.course_name::text order by c.course_name)
+                    from current_courses c where c.student_id = s.id
+                ), '{}') as "courses!"
+            from students s
+            where ($1 or s.deleted_at is null)
+                and ($2::uuid is null or s.created_by = $2)
+                and ($3::uuid is null or s.updated_by = $3)
+                and ($4::text is null or exists (
+                    select 1 from group_members gm
+                    where gm.student_id = s.id and gm.group_id = (
+                        select g.id from class_groups g
+                        where g.name = $4
+                        order by coalesce(g.academic_year = (
+                            select y.name from academic_years y
+                            join terms t on t.academic_year_id = y.id
+                            where t.is_current
+                        ), false) desc, g.academic_year desc
+                        limit 1
+                    )
+                ))
+            order by s.full_name
+        "#,
+        filter.include_deleted,
+        filter.created_by,
+        filter.updated_by,
+        filter.group
+    )
+    .fetch(connection)
+    .map(|row| {
+        row.map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not export students from db".into()),
+                ErrorTypes::DbError,
+            )
+        })
+    })
+    .boxed()
+}
@@ -1,145 +1,486 @@
 use std::sync::Arc;
 
 use crate::{
-    app::AvatarClient,
+    app::{avatar_key, AvatarProvider, Storage},
+    db::{
+        assignment::remove_files,
+        course::{
+            audit_promoted, check_capacity, check_prerequisites, get_waitlist, join_waitlist,
+            leave_waitlist, overfull_courses, promote_waitlist,
+        },
+        db_emit_webhook_event, db_notify_welcome, db_write_audit,
+    },
     errors::{Error, ErrorTypes},
-    schemas::{AddStudent, EditStudent, FullStudent, Student},
+    schemas::{
+        AddStudent, AuditContext, EditStudent, FullStudent, PatchStudent, Student, StudentFilter,
+        WebhookEvent, AVATAR_SIZES,
+    },
 };
-use sqlx::PgPool;
+use serde_json::Value;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, Transaction};
 use time::OffsetDateTime;
 use tracing::{instrument, Instrument};
 use uuid::Uuid;
 
 #[instrument(name = "Get students from db", skip(connection))]
 pub async fn db_get_student(student_id: Uuid, connection: &PgPool) -> Result<FullStudent, Error> {
+    let mut connection = connection.acquire().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get db connection".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    get_student(student_id, &mut connection).await
+}
+
+//the student as it's seen by the connection, e.g. inside the transaction changing it
+pub(crate) async fn get_student(
+    student_id: Uuid,
+    connection: &mut PgConnection,
+) -> Result<FullStudent, Error> {
     let query_span = tracing::info_span!("Get user",%student_id);
     //get student from students table without courses
-    let student = sqlx::query_as!(Student, "select * from students where id=$1", student_id)
-        .fetch_one(connection)
-        .instrument(query_span)
-        .await
-        .map_err(|e| {
-            Error::new(
-                Some(e.to_string()),
-                Some("Can not find student with the provided id".into()),
-                ErrorTypes::DbError,
-            )
-        })?;
+    let student = sqlx::query_as!(
+        Student,
+        "select * from students where id=$1 and deleted_at is null",
+        student_id
+    )
+    .fetch_optional(&mut *connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the student from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find student with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
 
-    let courses = get_courses(student_id, connection).await?;
+    let courses = get_courses(student_id, &mut *connection).await?;
 
-    Ok(FullStudent {
-        id: student.id,
-        full_name: student.full_name,
-        email: student.email,
-        age: student.age,
-        img: student.img,
-        registration_date: student.registration_date,
-        courses,
-    })
+    let mut student = student.with_courses(courses);
+    student.waitlist = get_waitlist(student_id, &mut *connection).await?;
+    Ok(student)
 }
 
 #[instrument(name = "Get all students from db", skip(connection), ret(Debug))]
-pub async fn db_get_all_students(connection: &PgPool) -> Result<Vec<FullStudent>, Error> {
+pub async fn db_get_all_students(
+    filter: &StudentFilter,
+    connection: &PgPool,
+) -> Result<Vec<FullStudent>, Error> {
     let query_span = tracing::info_span!("Get students from students table");
 
-    let students = sqlx::query_as!(Student, "select * from students")
-        .fetch_all(connection)
-        .instrument(query_span)
-        .await
-        .map_err(|e| {
-            Error::new(
-                Some(e.to_string()),
-                Some("Can not get all students from db".into()),
-                ErrorTypes::DbError,
-            )
-        })?;
+    let students = sqlx::query_as!(
+        Student,
+        r#"
+            select * from students
+            where ($1 or deleted_at is null)
+                and ($2::uuid is null or created_by = $2)
+                and ($3::uuid is null or updated_by = $3)
+                and ($4::text is null or exists (
+                    select 1 from group_members gm
+                    where gm.student_id = students.id and gm.group_id = (
+                        select g.id from class_groups g
+                        where g.name = $4
+                        order by coalesce(g.academic_year = (
+                            select y.name from academic_years y
+                            join terms t on t.academic_year_id = y.id
+                            where t.is_current
+                        ), false) desc, g.academic_year desc
+                        limit 1
+                    )
+                ))
+        "#,
+        filter.include_deleted,
+        filter.created_by,
+        filter.updated_by,
+        filter.group
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get all students from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
 
+    load_courses(students, connection).await
+}
+
+//get courses for every student
+pub(crate) async fn load_courses(
+    students: Vec<Student>,
+    connection: &PgPool,
+) -> Result<Vec<FullStudent>, Error> {
     let mut full_students = Vec::with_capacity(students.len());
 
     for student in students {
         let courses = get_courses(student.id, connection).await?;
-
-        full_students.push(FullStudent {
-            id: student.id,
-            full_name: student.full_name,
-            email: student.email,
-            age: student.age,
-            img: student.img,
-            registration_date: student.registration_date,
-            courses,
-        });
+        full_students.push(student.with_courses(courses));
     }
     Ok(full_students)
 }
 
+//mark the student as deleted, it's purged after the retention period
 #[instrument(name = "Delete student from db", skip(connection))]
-pub async fn db_delete_student(student_id: Uuid, connection: &PgPool) -> Result<(), Error> {
-    //delete all student's courses
-    let query_span = tracing::info_span!("Delete courses",%student_id);
-    //delete user from students table
-    let _ = sqlx::query!("delete from courses where student_id = $1;", student_id)
-        .execute(connection)
-        .instrument(query_span)
-        .await
-        .map_err(|e| {
-            Error::new(
-                Some(e.to_string()),
-                Some("Can not find student with the provided id".into()),
-                ErrorTypes::DbError,
-            )
-        })?;
+pub async fn db_delete_student(
+    student_id: Uuid,
+    expected_versions: Option<Vec<i32>>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = student_snapshot(student_id, connection).await;
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
 
     let query_span = tracing::info_span!("Delete student",%student_id);
-    //delete the student from students table
-    let _ = sqlx::query!("delete from students where id=$1;", student_id)
-        .execute(connection)
-        .instrument(query_span)
-        .await
-        .map_err(|e| {
-            Error::new(
-                Some(e.to_string()),
-                Some("Can not find student with the provided id".into()),
-                ErrorTypes::DbError,
-            )
-        })?;
+    let deleted = sqlx::query!(
+        r#"
+            update students set
+                deleted_at = now(), version = version + 1, updated_by = $3, updated_at = now()
+            where id=$1 and deleted_at is null and ($2::int[] is null or version = any($2))
+            returning array(
+                select course_name from current_courses where student_id = $1
+            ) as "courses!";
+        "#,
+        student_id,
+        expected_versions.as_deref(),
+        audit.actor_id
+    )
+    .fetch_optional(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not delete the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let courses = match deleted {
+        Some(rec) => rec.courses,
+        None => return Err(not_updated_error(student_id, connection).await),
+    };
+
+    //seats of the student are free now
+    let promoted = promote_waitlist(&courses, &mut transaction).await?;
+    db_emit_webhook_event(
+        WebhookEvent::StudentDeleted,
+        before
+            .clone()
+            .unwrap_or_else(|| serde_json::json!({ "id": student_id })),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not delete the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    audit_promoted(&promoted, audit, connection).await;
+
+    db_write_audit(
+        audit,
+        "delete",
+        "student",
+        Some(student_id.to_string()),
+        before,
+        None,
+        connection,
+    )
+    .await;
 
     Ok(())
 }
 
+#[instrument(name = "Restore deleted student", skip(connection), ret(Debug))]
+pub async fn db_restore_student(
+    student_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<FullStudent, Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Restore student",%student_id);
+    let restored = sqlx::query!(
+        r#"
+            update students set
+                deleted_at = null, version = version + 1, updated_by = $2, updated_at = now()
+            where id=$1 and deleted_at is not null
+            returning array(
+                select course_name from current_courses where student_id = $1
+            ) as "courses!";
+        "#,
+        student_id,
+        audit.actor_id
+    )
+    .fetch_optional(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not restore the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let courses = match restored {
+        Some(rec) => rec.courses,
+        None => {
+            return Err(Error::new(
+                None,
+                Some("Can not find deleted student with the provided id".into()),
+                ErrorTypes::NotFoundError,
+            ))
+        }
+    };
+
+    //the seats of the deleted student could have gone to the waitlist, the student waits
+    //for the courses which are full now
+    let overfull = overfull_courses(&courses, &mut transaction).await?;
+    remove_courses(student_id, &overfull, &mut transaction).await?;
+    join_waitlist(student_id, &overfull, &mut transaction).await?;
+    let student = get_student(student_id, &mut transaction).await?;
+    if let Ok(data) = serde_json::to_value(&student) {
+        db_emit_webhook_event(WebhookEvent::StudentUpdated, data, &mut transaction).await?;
+    }
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not restore the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "restore",
+        "student",
+        Some(student_id.to_string()),
+        None,
+        serde_json::to_value(&student).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(student)
+}
+
+//hard delete students which were deleted before the cutoff. Returns number of purged students.
+//Their avatars and submitted files are removed from the storage after the commit
+#[instrument(name = "Purge deleted students", skip(storage, connection))]
+pub async fn db_purge_deleted_students(
+    cutoff: OffsetDateTime,
+    storage: &dyn Storage,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<u64, Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    //rows of the files are removed with the students
+    let query_span = tracing::info_span!("Get avatars of purged students");
+    let avatars = sqlx::query!(
+        r#"
+            select a.student_id, a.etag from student_avatars a
+            join students s on s.id = a.student_id
+            where s.deleted_at < $1;
+        "#,
+        cutoff
+    )
+    .fetch_all(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get avatars of purged students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Get submitted files of purged students");
+    let files = sqlx::query!(
+        r#"
+            select f.storage_key from submission_files f
+            join submissions sub on sub.id = f.submission_id
+            join students s on s.id = sub.student_id
+            where s.deleted_at < $1;
+        "#,
+        cutoff
+    )
+    .fetch_all(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get submitted files of purged students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let keys: Vec<String> = avatars
+        .iter()
+        .flat_map(|rec| {
+            AVATAR_SIZES
+                .iter()
+                .map(|size| avatar_key(rec.student_id, &rec.etag, *size))
+        })
+        .chain(files.into_iter().map(|rec| rec.storage_key))
+        .collect();
+
+    //delete all purged students' courses
+    let query_span = tracing::info_span!("Delete courses of purged students");
+    sqlx::query!(
+        r#"
+            delete from courses where student_id in (
+                select id from students where deleted_at < $1
+            );
+        "#,
+        cutoff
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not delete purged students' courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Delete purged students");
+    let purged = sqlx::query!(
+        "delete from students where deleted_at < $1 returning id;",
+        cutoff
+    )
+    .fetch_all(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not purge deleted students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not purge deleted students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    remove_files(keys.iter().map(String::as_str), storage).await;
+
+    for student in purged.iter() {
+        db_write_audit(
+            audit,
+            "purge",
+            "student",
+            Some(student.id.to_string()),
+            None,
+            None,
+            connection,
+        )
+        .await;
+    }
+
+    Ok(purged.len() as u64)
+}
+
 #[instrument(name = "Adding a new student to db", skip(connection), ret(Debug))]
 pub async fn db_insert_new_student(
     data: AddStudent,
+    audit: &AuditContext,
     connection: &PgPool,
-    avatar_client: Arc<AvatarClient>,
+    avatar_client: Arc<dyn AvatarProvider>,
 ) -> Result<FullStudent, Error> {
-    let img = avatar_client.send_request(&data.email).await.unwrap();
+    let id = uuid::Uuid::new_v4();
+    check_prerequisites(&data.courses, id, connection).await?;
+    let img = avatar_client.resolve(id, &data.full_name, &data.email).await;
 
+    //the seats are counted and taken in one transaction
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    let (courses, waitlist) =
+        check_capacity(&data.courses, id, data.waitlist, &mut transaction).await?;
+
+    let registration_date = OffsetDateTime::now_utc();
     let new_student = FullStudent {
-        id: uuid::Uuid::new_v4(),
+        id,
         full_name: data.full_name.clone(),
         email: data.email,
         age: data.age,
         img,
-        registration_date: OffsetDateTime::now_utc(),
-        courses: data.courses,
+        registration_date,
+        courses,
+        version: 1,
+        deleted_at: None,
+        created_by: audit.actor_id,
+        updated_by: audit.actor_id,
+        updated_at: registration_date,
+        grades: None,
+        waitlist,
     };
 
     let query_span = tracing::info_span!("Saving new student in database", id=%new_student.id);
 
     sqlx::query!(
         r#"
-            insert into students (id, full_name, age, registration_date, email, img)
-            values ($1, $2, $3, $4, $5, $6);
+            insert into students
+                (id, full_name, age, registration_date, email, img, created_by, updated_by, updated_at)
+            values ($1, $2, $3, $4, $5, $6, $7, $7, $4);
         "#,
         new_student.id,
         new_student.full_name,
         new_student.age,
         new_student.registration_date,
         new_student.email,
-        new_student.img
+        new_student.img,
+        new_student.created_by
     )
-    .execute(connection)
+    .execute(&mut transaction)
     .instrument(query_span)
     .await
     .map_err(|e| {
@@ -151,7 +492,36 @@ pub async fn db_insert_new_student(
     })?;
 
     //inser courses
-    insert_courses(new_student.id, &new_student.courses, false, connection).await?;
+    insert_courses(
+        new_student.id,
+        &new_student.courses,
+        false,
+        &mut transaction,
+    )
+    .await?;
+    join_waitlist(new_student.id, &new_student.waitlist, &mut transaction).await?;
+    db_notify_welcome(&new_student, &mut transaction).await?;
+    if let Ok(data) = serde_json::to_value(&new_student) {
+        db_emit_webhook_event(WebhookEvent::StudentCreated, data, &mut transaction).await?;
+    }
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the student to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "create",
+        "student",
+        Some(new_student.id.to_string()),
+        None,
+        serde_json::to_value(&new_student).ok(),
+        connection,
+    )
+    .await;
 
     Ok(new_student)
 }
@@ -160,30 +530,139 @@ pub async fn db_insert_new_student(
 pub async fn db_change_student(
     student_id: Uuid,
     data: EditStudent,
+    expected_versions: Option<Vec<i32>>,
+    audit: &AuditContext,
     connection: &PgPool,
 ) -> Result<FullStudent, Error> {
-    //check if student exist
-    let query_span = tracing::info_span!("Finding student in db", %student_id);
-    sqlx::query!("select * from students where id=$1;", student_id)
-        .fetch_one(connection)
-        .instrument(query_span)
-        .await
-        .map_err(|e| {
-            Error::new(
-                Some(e.to_string()),
-                Some("Can not find student with the provided id".into()),
-                ErrorTypes::DbError,
-            )
-        })?;
+    let before = student_snapshot(student_id, connection).await;
+    check_prerequisites(&data.courses, student_id, connection).await?;
 
-    //insert new data to students table
-    sqlx::query!(
-        "update students set email=$1, age=$2 where id=$3;",
+    //the version check, the seats and the courses are in one transaction, a concurrent change
+    //either fails the check or waits for the commit
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    //insert new data to students table if nobody has changed the student
+    let query_span = tracing::info_span!("Updating student's data", %student_id);
+    let updated = sqlx::query!(
+        r#"
+            update students set
+                email=$1, age=$2, version = version + 1, updated_by = $5, updated_at = now()
+            where id=$3 and deleted_at is null and ($4::int[] is null or version = any($4))
+            returning array(
+                select course_name from current_courses where student_id = $3
+            ) as "old_courses!";
+        "#,
         data.email,
         data.age,
-        student_id
+        student_id,
+        expected_versions.as_deref(),
+        audit.actor_id
+    )
+    .fetch_optional(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new studet's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let old_courses = match updated {
+        Some(rec) => rec.old_courses,
+        None => return Err(not_updated_error(student_id, connection).await),
+    };
+    let (courses, waitlist) =
+        check_capacity(&data.courses, student_id, data.waitlist, &mut transaction).await?;
+
+    //update courses, the student stays only in the waitlists of the full courses
+    insert_courses(student_id, &courses, true, &mut transaction).await?;
+    leave_waitlist(student_id, &waitlist, true, &mut transaction).await?;
+    join_waitlist(student_id, &waitlist, &mut transaction).await?;
+    let left: Vec<String> = old_courses
+        .into_iter()
+        .filter(|c| !courses.contains(c))
+        .collect();
+    let promoted = promote_waitlist(&left, &mut transaction).await?;
+    let result = get_student(student_id, &mut transaction).await?;
+    if let Ok(data) = serde_json::to_value(&result) {
+        db_emit_webhook_event(WebhookEvent::StudentUpdated, data, &mut transaction).await?;
+    }
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new studet's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    audit_promoted(&promoted, audit, connection).await;
+
+    db_write_audit(
+        audit,
+        "update",
+        "student",
+        Some(student_id.to_string()),
+        before,
+        serde_json::to_value(&result).ok(),
+        connection,
     )
-    .execute(connection)
+    .await;
+
+    Ok(result)
+}
+
+#[instrument(name = "Patching student", skip(connection), ret(Debug))]
+pub async fn db_patch_student(
+    student_id: Uuid,
+    data: PatchStudent,
+    expected_versions: Option<Vec<i32>>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<FullStudent, Error> {
+    let before = student_snapshot(student_id, connection).await;
+    if let Some(courses) = &data.add_courses {
+        check_prerequisites(courses, student_id, connection).await?;
+    }
+
+    //the columns, the seats and the courses are changed together or not at all
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    //update only provided columns, keep old values for the rest
+    let query_span = tracing::info_span!("Updating student's data", %student_id);
+    let updated = sqlx::query!(
+        r#"
+            update students set
+                full_name = coalesce($1, full_name),
+                email = coalesce($2, email),
+                age = coalesce($3, age),
+                version = version + 1,
+                updated_by = $6,
+                updated_at = now()
+            where id = $4 and deleted_at is null and ($5::int[] is null or version = any($5))
+            returning id;
+        "#,
+        data.full_name,
+        data.email,
+        data.age,
+        student_id,
+        expected_versions.as_deref(),
+        audit.actor_id
+    )
+    .fetch_optional(&mut transaction)
+    .instrument(query_span)
     .await
     .map_err(|e| {
         Error::new(
@@ -193,17 +672,99 @@ pub async fn db_change_student(
         )
     })?;
 
-    //update courses
-    insert_courses(student_id, &data.courses, true, connection).await?;
-    let result = db_get_student(student_id, connection).await?;
+    if updated.is_none() {
+        return Err(not_updated_error(student_id, connection).await);
+    }
+
+    let (added, waitlist) = match &data.add_courses {
+        Some(courses) => {
+            check_capacity(courses, student_id, data.waitlist, &mut transaction).await?
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+    let removed = data.remove_courses.unwrap_or_default();
+    remove_courses(student_id, &removed, &mut transaction).await?;
+    leave_waitlist(student_id, &removed, false, &mut transaction).await?;
+    add_courses(student_id, &added, &mut transaction).await?;
+    leave_waitlist(student_id, &added, false, &mut transaction).await?;
+    join_waitlist(student_id, &waitlist, &mut transaction).await?;
+    let promoted = promote_waitlist(&removed, &mut transaction).await?;
+    let result = get_student(student_id, &mut transaction).await?;
+    if let Ok(data) = serde_json::to_value(&result) {
+        db_emit_webhook_event(WebhookEvent::StudentUpdated, data, &mut transaction).await?;
+    }
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new studet's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    audit_promoted(&promoted, audit, connection).await;
+
+    db_write_audit(
+        audit,
+        "update",
+        "student",
+        Some(student_id.to_string()),
+        before,
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
     Ok(result)
 }
 
-async fn get_courses(student_id: Uuid, connection: &PgPool) -> Result<Vec<String>, Error> {
-    //get student's courses
+//student's state for the audit log
+async fn student_snapshot(student_id: Uuid, connection: &PgPool) -> Option<Value> {
+    db_get_student(student_id, connection)
+        .await
+        .ok()
+        .and_then(|student| serde_json::to_value(student).ok())
+}
+
+//find out why the student hasn't been updated: it doesn't exist or has another version
+async fn not_updated_error(student_id: Uuid, connection: &PgPool) -> Error {
+    let query_span = tracing::info_span!("Get student's version", %student_id);
+    match sqlx::query!(
+        "select version from students where id=$1 and deleted_at is null;",
+        student_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    {
+        Ok(Some(student)) => outdated_version_error(student.version),
+        Ok(None) => Error::new(
+            None,
+            Some("Can not find student with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        ),
+        Err(e) => Error::new(
+            Some(e.to_string()),
+            Some("Can not find student with the provided id".into()),
+            ErrorTypes::DbError,
+        ),
+    }
+}
+
+fn outdated_version_error(current_version: i32) -> Error {
+    Error::new(
+        Some(format!("Current student's version is {}", current_version)),
+        Some("Student has been changed by someone else. Get it again and retry".into()),
+        ErrorTypes::PreconditionFailed,
+    )
+}
+
+async fn get_courses(
+    student_id: Uuid,
+    connection: impl PgExecutor<'_>,
+) -> Result<Vec<String>, Error> {
+    //get student's courses of the current term
     let query_span = tracing::info_span!("Get courses",%student_id);
     Ok(sqlx::query!(
-        "select course_name from courses where student_id=$1",
+        r#"select course_name as "course_name!" from current_courses where student_id=$1"#,
         student_id
     )
     .fetch_all(connection)
@@ -223,46 +784,117 @@ async fn get_courses(student_id: Uuid, connection: &PgPool) -> Result<Vec<String
 
 async fn insert_courses(
     student_id: Uuid,
-    courses: &Vec<String>,
+    courses: &[String],
     delete_old: bool,
-    connection: &PgPool,
+    transaction: &mut Transaction<'_, Postgres>,
 ) -> Result<(), Error> {
-    //delete all student's courses
+    //delete all student's courses of the current term, past terms are kept
     if delete_old {
-        sqlx::query!("delete from courses where student_id=$1;", student_id)
-            .execute(connection)
-            .await
-            .map_err(|e| {
-                Error::new(
-                    Some(e.to_string()),
-                    Some("Can not delete student's courses".into()),
-                    ErrorTypes::DbError,
-                )
-            })?;
-    }
-
-    //create query
-    let mut insert_query = "INSERT INTO courses (student_id,course_name) values ".to_string();
-    for item in courses.iter() {
-        insert_query.push_str(&format!("('{}','{}'),", student_id, item));
-    }
-    //delete comma in the end
-    insert_query.pop();
-
-    //insert new courses
-    let query_span =
-        tracing::info_span!("Saving new courses to database",%student_id,courses=?courses);
-    sqlx::query(&insert_query)
-        .execute(connection)
-        .instrument(query_span)
+        sqlx::query!(
+            r#"
+                delete from courses where student_id=$1
+                    and term_id is not distinct from (select id from terms where is_current);
+            "#,
+            student_id
+        )
+        .execute(&mut *transaction)
         .await
         .map_err(|e| {
             Error::new(
                 Some(e.to_string()),
-                Some("Can not insert new student's courses".into()),
+                Some("Can not delete student's courses".into()),
                 ErrorTypes::DbError,
             )
         })?;
+    }
+
+    if courses.is_empty() {
+        return Ok(());
+    }
+
+    //insert new courses
+    let query_span =
+        tracing::info_span!("Saving new courses to database",%student_id,courses=?courses);
+    sqlx::query!(
+        r#"
+            insert into courses (student_id, course_name, term_id)
+            select $1::uuid, c, (select id from terms where is_current)
+            from unnest($2::text[]) as c;
+        "#,
+        student_id,
+        courses
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert new student's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(())
+}
+
+//add courses the student isn't enrolled in yet
+async fn add_courses(
+    student_id: Uuid,
+    courses: &[String],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Adding courses",%student_id,courses=?courses);
+    sqlx::query!(
+        r#"
+            insert into courses (student_id, course_name, term_id)
+            select distinct $1::uuid, c, (select id from terms where is_current)
+            from unnest($2::text[]) as c
+            where not exists (
+                select 1 from current_courses where student_id = $1 and course_name = c
+            );
+        "#,
+        student_id,
+        courses
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert new student's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(())
+}
+
+async fn remove_courses(
+    student_id: Uuid,
+    courses: &[String],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Removing courses",%student_id,courses=?courses);
+    sqlx::query!(
+        r#"
+            delete from courses where student_id = $1 and course_name = any($2)
+                and term_id is not distinct from (select id from terms where is_current);
+        "#,
+        student_id,
+        courses
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not delete student's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
 
     Ok(())
 }
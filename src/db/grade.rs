@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use crate::{
+    db::{db_notify_grade_posted, db_write_audit},
+    errors::{Error, ErrorTypes},
+    schemas::{
+        validate_scores, AddGrade, AuditContext, FullStudent, Grade, GradeBook, GradeSummary,
+        PatchGrade,
+    },
+};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Adding a new grade to db", skip(connection), ret(Debug))]
+pub async fn db_add_grade(
+    student_id: Uuid,
+    data: AddGrade,
+    teacher_id: Option<Uuid>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Grade, Error> {
+    check_enrolled(student_id, &data.course_name, connection).await?;
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    let grade = insert_grade(student_id, &data, teacher_id, &mut transaction).await?;
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the grade to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    grade_recorded(&grade, audit, connection).await;
+    Ok(grade)
+}
+
+//grades can be recorded only for courses of the student
+pub(crate) async fn check_enrolled(
+    student_id: Uuid,
+    course_name: &str,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let courses = student_courses(student_id, connection).await?;
+    if !courses.iter().any(|c| c == course_name) {
+        return Err(Error::new(
+            None,
+            Some(format!(
+                "Student isn't enrolled in the course '{}'",
+                course_name
+            )),
+            ErrorTypes::ValidationError,
+        ));
+    }
+    Ok(())
+}
+
+//the grade and its email are saved with the change they belong to, e.g. a graded submission
+pub(crate) async fn insert_grade(
+    student_id: Uuid,
+    data: &AddGrade,
+    teacher_id: Option<Uuid>,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<Grade, Error> {
+    let query_span = tracing::info_span!("Saving new grade in database", %student_id);
+    let grade = sqlx::query_as!(
+        Grade,
+        r#"
+            insert into grades
+                (student_id, course_name, assessment, score, max_score, weight, graded_on, teacher_id)
+            values ($1, $2, $3, $4, $5, coalesce($6::float8, 1), coalesce($7, current_date), $8)
+            returning *;
+        "#,
+        student_id,
+        data.course_name,
+        data.assessment,
+        data.score,
+        data.max_score,
+        data.weight,
+        data.graded_on,
+        teacher_id
+    )
+    .fetch_one(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the grade to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_notify_grade_posted(&grade, transaction).await?;
+    Ok(grade)
+}
+
+//audit of a new grade, after the transaction is committed
+pub(crate) async fn grade_recorded(grade: &Grade, audit: &AuditContext, connection: &PgPool) {
+    db_write_audit(
+        audit,
+        "create",
+        "grade",
+        Some(grade.id.to_string()),
+        None,
+        serde_json::to_value(grade).ok(),
+        connection,
+    )
+    .await;
+}
+
+#[instrument(name = "Get grade from db", skip(connection))]
+pub async fn db_get_grade(grade_id: Uuid, connection: &PgPool) -> Result<Grade, Error> {
+    let query_span = tracing::info_span!("Get grade",%grade_id);
+    sqlx::query_as!(Grade, "select * from grades where id=$1", grade_id)
+        .fetch_optional(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not get the grade from db".into()),
+                ErrorTypes::DbError,
+            )
+        })?
+        .ok_or_else(|| {
+            Error::new(
+                None,
+                Some("Can not find grade with the provided id".into()),
+                ErrorTypes::NotFoundError,
+            )
+        })
+}
+
+#[instrument(name = "Patching grade", skip(connection), ret(Debug))]
+pub async fn db_patch_grade(
+    grade_id: Uuid,
+    data: PatchGrade,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Grade, Error> {
+    let before = db_get_grade(grade_id, connection).await?;
+
+    //check the scores after merging with the saved ones
+    validate_scores(
+        data.score.unwrap_or(before.score),
+        data.max_score.unwrap_or(before.max_score),
+        data.weight,
+    )
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Updating grade's data", %grade_id);
+    let grade = sqlx::query_as!(
+        Grade,
+        r#"
+            update grades set
+                assessment = coalesce($1, assessment),
+                score = coalesce($2, score),
+                max_score = coalesce($3, max_score),
+                weight = coalesce($4, weight),
+                graded_on = coalesce($5, graded_on),
+                updated_at = now()
+            where id = $6
+            returning *;
+        "#,
+        data.assessment,
+        data.score,
+        data.max_score,
+        data.weight,
+        data.graded_on,
+        grade_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new grade's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "update",
+        "grade",
+        Some(grade_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&grade).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(grade)
+}
+
+//all grades of the student with per-course averages and GPA
+#[instrument(name = "Get student's grades", skip(connection))]
+pub async fn db_get_student_grades(
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<GradeBook, Error> {
+    student_courses(student_id, connection).await?;
+
+    let query_span = tracing::info_span!("Get grades of student",%student_id);
+    let grades = sqlx::query_as!(
+        Grade,
+        "select * from grades where student_id=$1 order by course_name, graded_on, created_at",
+        student_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's grades".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let summary = GradeSummary::from_grades(&grades);
+    Ok(GradeBook { grades, summary })
+}
+
+//set grades summary of every student
+pub async fn db_load_grade_summaries(
+    students: &mut [FullStudent],
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let ids: Vec<Uuid> = students.iter().map(|s| s.id).collect();
+
+    let query_span = tracing::info_span!("Get grades of students");
+    let grades = sqlx::query_as!(
+        Grade,
+        "select * from grades where student_id = any($1)",
+        &ids
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get students' grades".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let mut by_student: HashMap<Uuid, Vec<Grade>> = HashMap::new();
+    for grade in grades {
+        by_student.entry(grade.student_id).or_default().push(grade);
+    }
+
+    for student in students.iter_mut() {
+        let grades = by_student.remove(&student.id).unwrap_or_default();
+        student.grades = Some(GradeSummary::from_grades(&grades));
+    }
+    Ok(())
+}
+
+//courses of the not deleted student
+async fn student_courses(student_id: Uuid, connection: &PgPool) -> Result<Vec<String>, Error> {
+    let query_span = tracing::info_span!("Get student's courses",%student_id);
+    let student = sqlx::query!(
+        r#"
+            select array(
+                select course_name from current_courses where student_id = s.id
+            ) as "courses!"
+            from students s
+            where s.id = $1 and s.deleted_at is null;
+        "#,
+        student_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not find student's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find student with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    Ok(student.courses)
+}
@@ -0,0 +1,361 @@
+use crate::{
+    db::{db_write_audit, load_courses},
+    errors::{Error, ErrorTypes},
+    schemas::{AddGroup, AuditContext, ClassGroup, FullStudent, GroupFilter, PatchGroup, Student},
+};
+use sqlx::PgPool;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Adding a new group to db", skip(connection), ret(Debug))]
+pub async fn db_add_group(
+    data: AddGroup,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<ClassGroup, Error> {
+    let query_span = tracing::info_span!("Saving new group in database");
+    let group_id = sqlx::query!(
+        r#"
+            insert into class_groups (name, academic_year, homeroom_teacher_id)
+            values ($1, $2, $3)
+            returning id;
+        "#,
+        data.name,
+        data.academic_year,
+        data.homeroom_teacher_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the group to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .id;
+
+    let group = db_get_group(group_id, connection).await?;
+    db_write_audit(
+        audit,
+        "create",
+        "group",
+        Some(group_id.to_string()),
+        None,
+        serde_json::to_value(&group).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(group)
+}
+
+#[instrument(name = "Get all groups from db", skip(connection))]
+pub async fn db_get_all_groups(
+    filter: &GroupFilter,
+    connection: &PgPool,
+) -> Result<Vec<ClassGroup>, Error> {
+    let query_span = tracing::info_span!("Get groups from class_groups table");
+    sqlx::query_as!(
+        ClassGroup,
+        r#"
+            select g.*, (
+                select count(*) from group_members gm
+                join students s on s.id = gm.student_id
+                where gm.group_id = g.id and s.deleted_at is null
+            ) as "students_count!"
+            from class_groups g
+            where ($1::text is null or g.academic_year = $1)
+            order by g.academic_year desc, g.name;
+        "#,
+        filter.academic_year
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get all groups from db".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+#[instrument(name = "Get group from db", skip(connection))]
+pub async fn db_get_group(group_id: Uuid, connection: &PgPool) -> Result<ClassGroup, Error> {
+    let query_span = tracing::info_span!("Get group",%group_id);
+    sqlx::query_as!(
+        ClassGroup,
+        r#"
+            select g.*, (
+                select count(*) from group_members gm
+                join students s on s.id = gm.student_id
+                where gm.group_id = g.id and s.deleted_at is null
+            ) as "students_count!"
+            from class_groups g
+            where g.id = $1;
+        "#,
+        group_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the group from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find group with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })
+}
+
+//groups where the teacher is the homeroom teacher
+#[instrument(name = "Get homeroom groups from db", skip(connection))]
+pub async fn db_get_homeroom_groups(
+    teacher_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<ClassGroup>, Error> {
+    let query_span = tracing::info_span!("Get homeroom groups",%teacher_id);
+    sqlx::query_as!(
+        ClassGroup,
+        r#"
+            select g.*, (
+                select count(*) from group_members gm
+                join students s on s.id = gm.student_id
+                where gm.group_id = g.id and s.deleted_at is null
+            ) as "students_count!"
+            from class_groups g
+            where g.homeroom_teacher_id = $1
+            order by g.academic_year desc, g.name;
+        "#,
+        teacher_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get homeroom groups from db".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+#[instrument(name = "Patching group", skip(connection), ret(Debug))]
+pub async fn db_patch_group(
+    group_id: Uuid,
+    data: PatchGroup,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<ClassGroup, Error> {
+    let before = db_get_group(group_id, connection).await?;
+
+    let query_span = tracing::info_span!("Updating group's data", %group_id);
+    sqlx::query!(
+        r#"
+            update class_groups set
+                name = coalesce($1, name),
+                academic_year = coalesce($2, academic_year),
+                homeroom_teacher_id = coalesce($3, homeroom_teacher_id)
+            where id = $4;
+        "#,
+        data.name,
+        data.academic_year,
+        data.homeroom_teacher_id,
+        group_id
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new group's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let result = db_get_group(group_id, connection).await?;
+    db_write_audit(
+        audit,
+        "update",
+        "group",
+        Some(group_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//members are removed with the group
+#[instrument(name = "Delete group from db", skip(connection))]
+pub async fn db_delete_group(
+    group_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = db_get_group(group_id, connection).await?;
+
+    let query_span = tracing::info_span!("Delete group",%group_id);
+    sqlx::query!("delete from class_groups where id = $1;", group_id)
+        .execute(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not delete the group".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    db_write_audit(
+        audit,
+        "delete",
+        "group",
+        Some(group_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    Ok(())
+}
+
+#[instrument(name = "Add students to group", skip(connection), ret(Debug))]
+pub async fn db_add_group_members(
+    group_id: Uuid,
+    students: &[Uuid],
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<ClassGroup, Error> {
+    db_get_group(group_id, connection).await?;
+
+    let query_span = tracing::info_span!("Add group members",%group_id,students=?students);
+    let added = sqlx::query!(
+        r#"
+            insert into group_members (group_id, student_id)
+            select $1, s.id from students s
+            where s.id = any($2) and s.deleted_at is null
+            on conflict do nothing
+            returning student_id;
+        "#,
+        group_id,
+        students
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not add students to the group".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "add_members",
+        "group",
+        Some(group_id.to_string()),
+        None,
+        serde_json::to_value(added.iter().map(|r| r.student_id).collect::<Vec<_>>()).ok(),
+        connection,
+    )
+    .await;
+
+    db_get_group(group_id, connection).await
+}
+
+#[instrument(name = "Remove student from group", skip(connection), ret(Debug))]
+pub async fn db_remove_group_member(
+    group_id: Uuid,
+    student_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<ClassGroup, Error> {
+    let query_span = tracing::info_span!("Remove group member",%group_id,%student_id);
+    let removed = sqlx::query!(
+        "delete from group_members where group_id = $1 and student_id = $2 returning student_id;",
+        group_id,
+        student_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not remove student from the group".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if removed.is_none() {
+        return Err(Error::new(
+            None,
+            Some("The student isn't a member of the group".into()),
+            ErrorTypes::NotFoundError,
+        ));
+    }
+
+    db_write_audit(
+        audit,
+        "remove_member",
+        "group",
+        Some(group_id.to_string()),
+        serde_json::to_value(student_id).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    db_get_group(group_id, connection).await
+}
+
+#[instrument(name = "Get group's students", skip(connection))]
+pub async fn db_get_group_students(
+    group_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<FullStudent>, Error> {
+    db_get_group(group_id, connection).await?;
+
+    let query_span = tracing::info_span!("Get students of group",%group_id);
+    let students = sqlx::query_as!(
+        Student,
+        r#"
+            select s.* from students s
+            join group_members gm on gm.student_id = s.id
+            where gm.group_id = $1 and s.deleted_at is null
+            order by s.full_name;
+        "#,
+        group_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get group's students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    load_courses(students, connection).await
+}
@@ -0,0 +1,496 @@
+use crate::{
+    db::{db_write_audit, load_courses},
+    errors::{Error, ErrorTypes},
+    schemas::{
+        AddGuardian, AuditContext, FullStudent, Guardian, LinkGuardian, PatchGuardian,
+        Relationship, Student, StudentGuardian,
+    },
+};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Adding a new guardian to db", skip(connection), ret(Debug))]
+pub async fn db_add_guardian(
+    data: AddGuardian,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Guardian, Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Saving new guardian in database");
+    let guardian = sqlx::query_as!(
+        Guardian,
+        r#"
+            insert into guardians (full_name, relationship, phone, email, user_id)
+            values ($1, $2, $3, $4, $5)
+            returning id, full_name, relationship as "relationship: Relationship",
+                phone, email, user_id, created_at;
+        "#,
+        data.full_name,
+        data.relationship as Relationship,
+        data.phone,
+        data.email,
+        data.user_id
+    )
+    .fetch_one(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the guardian to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if let Some(user_id) = guardian.user_id {
+        grant_parent_role(user_id, &mut transaction).await?;
+    }
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the guardian to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "create",
+        "guardian",
+        Some(guardian.id.to_string()),
+        None,
+        serde_json::to_value(&guardian).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(guardian)
+}
+
+#[instrument(name = "Get all guardians from db", skip(connection))]
+pub async fn db_get_all_guardians(connection: &PgPool) -> Result<Vec<Guardian>, Error> {
+    let query_span = tracing::info_span!("Get guardians from guardians table");
+    sqlx::query_as!(
+        Guardian,
+        r#"
+            select id, full_name, relationship as "relationship: Relationship",
+                phone, email, user_id, created_at
+            from guardians order by full_name;
+        "#
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get all guardians from db".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+#[instrument(name = "Get guardian from db", skip(connection))]
+pub async fn db_get_guardian(guardian_id: Uuid, connection: &PgPool) -> Result<Guardian, Error> {
+    let query_span = tracing::info_span!("Get guardian",%guardian_id);
+    sqlx::query_as!(
+        Guardian,
+        r#"
+            select id, full_name, relationship as "relationship: Relationship",
+                phone, email, user_id, created_at
+            from guardians where id = $1;
+        "#,
+        guardian_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the guardian from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find guardian with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })
+}
+
+#[instrument(name = "Patching guardian", skip(connection), ret(Debug))]
+pub async fn db_patch_guardian(
+    guardian_id: Uuid,
+    data: PatchGuardian,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Guardian, Error> {
+    let before = db_get_guardian(guardian_id, connection).await?;
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Updating guardian's data", %guardian_id);
+    let result = sqlx::query_as!(
+        Guardian,
+        r#"
+            update guardians set
+                full_name = coalesce($1, full_name),
+                relationship = coalesce($2, relationship),
+                phone = coalesce($3, phone),
+                email = coalesce($4, email),
+                user_id = coalesce($5, user_id)
+            where id = $6
+            returning id, full_name, relationship as "relationship: Relationship",
+                phone, email, user_id, created_at;
+        "#,
+        data.full_name,
+        data.relationship as Option<Relationship>,
+        data.phone,
+        data.email,
+        data.user_id,
+        guardian_id
+    )
+    .fetch_one(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new guardian's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if let Some(user_id) = data.user_id {
+        grant_parent_role(user_id, &mut transaction).await?;
+    }
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new guardian's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "update",
+        "guardian",
+        Some(guardian_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//the guardian's login account gets the parent role, so `require_student_access` limits it
+//to the guardian's children. Admins, teachers and accounts of students keep their role
+async fn grant_parent_role(
+    user_id: Uuid,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Give the parent role to the guardian's user", %user_id);
+    sqlx::query!(
+        r#"
+            update users set role = 'parent'
+            where id = $1 and role = 'student'
+                and not exists (select 1 from student_users where user_id = $1);
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not give the parent role to the guardian's user".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
+
+//links to the students are removed with the guardian
+#[instrument(name = "Delete guardian from db", skip(connection))]
+pub async fn db_delete_guardian(
+    guardian_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = db_get_guardian(guardian_id, connection).await?;
+
+    let query_span = tracing::info_span!("Delete guardian",%guardian_id);
+    sqlx::query!("delete from guardians where id = $1;", guardian_id)
+        .execute(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not delete the guardian".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    db_write_audit(
+        audit,
+        "delete",
+        "guardian",
+        Some(guardian_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    Ok(())
+}
+
+#[instrument(name = "Link guardian to student", skip(connection), ret(Debug))]
+pub async fn db_link_guardian(
+    student_id: Uuid,
+    data: LinkGuardian,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Vec<StudentGuardian>, Error> {
+    db_get_guardian(data.guardian_id, connection).await?;
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if data.is_primary {
+        let query_span = tracing::info_span!("Reset primary contact",%student_id);
+        sqlx::query!(
+            "update student_guardians set is_primary = false where student_id = $1;",
+            student_id
+        )
+        .execute(&mut transaction)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not change student's primary contact".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+    }
+
+    let query_span = tracing::info_span!("Link guardian",%student_id,guardian_id=%data.guardian_id);
+    sqlx::query!(
+        r#"
+            insert into student_guardians (student_id, guardian_id, is_primary)
+            select s.id, $2, $3 from students s
+            where s.id = $1 and s.deleted_at is null
+            on conflict (student_id, guardian_id) do update set is_primary = excluded.is_primary
+            returning student_id;
+        "#,
+        student_id,
+        data.guardian_id,
+        data.is_primary
+    )
+    .fetch_optional(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not link the guardian to the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find student with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not link the guardian to the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "link_guardian",
+        "student",
+        Some(student_id.to_string()),
+        None,
+        serde_json::to_value(&data).ok(),
+        connection,
+    )
+    .await;
+
+    db_get_student_guardians(student_id, connection).await
+}
+
+#[instrument(name = "Unlink guardian from student", skip(connection), ret(Debug))]
+pub async fn db_unlink_guardian(
+    student_id: Uuid,
+    guardian_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Vec<StudentGuardian>, Error> {
+    let query_span = tracing::info_span!("Unlink guardian",%student_id,%guardian_id);
+    let removed = sqlx::query!(
+        "delete from student_guardians where student_id = $1 and guardian_id = $2 returning guardian_id;",
+        student_id,
+        guardian_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not unlink the guardian from the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if removed.is_none() {
+        return Err(Error::new(
+            None,
+            Some("The guardian isn't linked to the student".into()),
+            ErrorTypes::NotFoundError,
+        ));
+    }
+
+    db_write_audit(
+        audit,
+        "unlink_guardian",
+        "student",
+        Some(student_id.to_string()),
+        serde_json::to_value(guardian_id).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    db_get_student_guardians(student_id, connection).await
+}
+
+//primary contact goes first
+#[instrument(name = "Get student's guardians", skip(connection))]
+pub async fn db_get_student_guardians(
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<StudentGuardian>, Error> {
+    let query_span = tracing::info_span!("Get guardians of student",%student_id);
+    sqlx::query_as!(
+        StudentGuardian,
+        r#"
+            select g.id, g.full_name, g.relationship as "relationship: Relationship",
+                g.phone, g.email, sg.is_primary
+            from guardians g
+            join student_guardians sg on sg.guardian_id = g.id
+            where sg.student_id = $1
+            order by sg.is_primary desc, g.full_name;
+        "#,
+        student_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's guardians".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//children of the guardian linked to the login account
+#[instrument(name = "Get guardian's children", skip(connection))]
+pub async fn db_get_guardian_children(
+    user_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<FullStudent>, Error> {
+    let query_span = tracing::info_span!("Get children of guardian",%user_id);
+    let students = sqlx::query_as!(
+        Student,
+        r#"
+            select s.* from students s
+            join student_guardians sg on sg.student_id = s.id
+            join guardians g on g.id = sg.guardian_id
+            where g.user_id = $1 and s.deleted_at is null
+            order by s.full_name;
+        "#,
+        user_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get guardian's children".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    load_courses(students, connection).await
+}
+
+#[instrument(name = "Check guardian of student", skip(connection))]
+pub async fn db_is_guardian_of(
+    user_id: Uuid,
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<bool, Error> {
+    let query_span = tracing::info_span!("Check guardian",%user_id,%student_id);
+    sqlx::query!(
+        r#"
+            select exists (
+                select 1 from student_guardians sg
+                join guardians g on g.id = sg.guardian_id
+                where g.user_id = $1 and sg.student_id = $2
+            ) as "exists!";
+        "#,
+        user_id,
+        student_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map(|rec| rec.exists)
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check student's guardians".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{
+    app::AvatarProvider,
+    db::{
+        course::{check_prerequisites, overfull_courses},
+        db_emit_webhook_event, db_notify_welcome, db_write_audit,
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{AuditContext, FullStudent, ImportReport, ImportRow, ImportRowError, WebhookEvent},
+};
+use futures_util::{stream, StreamExt};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+//avatars of imported students resolved at the same time
+const AVATAR_CONCURRENCY: usize = 16;
+
+//rows are checked like new students, valid ones are saved in one transaction
+#[instrument(name = "Import students", skip(rows, connection, avatar_client))]
+pub async fn db_import_students(
+    rows: Vec<ImportRow>,
+    mut errors: Vec<ImportRowError>,
+    dry_run: bool,
+    audit: &AuditContext,
+    connection: &PgPool,
+    avatar_client: Arc<dyn AvatarProvider>,
+) -> Result<ImportReport, Error> {
+    let total = rows.len() + errors.len();
+
+    //student's name is unique
+    let names: Vec<String> = rows.iter().map(|(_, s)| s.full_name.clone()).collect();
+    let query_span = tracing::info_span!("Check students' names");
+    let existing: HashSet<String> = sqlx::query!(
+        "select full_name from students where full_name = any($1);",
+        &names
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check students' names".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.full_name)
+    .collect();
+
+    //free seats are taken in the order of the rows, they are checked again when the students are saved
+    let courses: Vec<String> = rows
+        .iter()
+        .flat_map(|(_, s)| s.courses.iter().cloned())
+        .collect();
+    let query_span = tracing::info_span!("Get free seats of courses");
+    let mut seats: HashMap<String, i64> = sqlx::query!(
+        r#"
+            select cc.course_name, cc.max_seats - (
+                select count(*) from current_courses c
+                join students s on s.id = c.student_id
+                where c.course_name = cc.course_name and s.deleted_at is null
+            ) as "free!"
+            from course_capacities cc where cc.course_name = any($1);
+        "#,
+        &courses
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check courses' capacity".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| (rec.course_name, rec.free))
+    .collect();
+
+    let registration_date = OffsetDateTime::now_utc();
+    let mut seen = HashSet::new();
+    let mut students = Vec::new();
+    for (row, data) in rows {
+        if existing.contains(&data.full_name) || !seen.insert(data.full_name.clone()) {
+            errors.push(row_error(row, "Student with this name already exists"));
+            continue;
+        }
+
+        let id = Uuid::new_v4();
+        if let Err(e) = check_prerequisites(&data.courses, id, connection).await {
+            errors.push(ImportRowError {
+                row,
+                errors: e
+                    .cause
+                    .and_then(|cause| serde_json::from_str(&cause).ok())
+                    .unwrap_or_default(),
+            });
+            continue;
+        }
+
+        let full: Vec<&String> = data
+            .courses
+            .iter()
+            .filter(|c| seats.get(*c).map(|free| *free <= 0).unwrap_or(false))
+            .collect();
+        if !full.is_empty() {
+            let full: Vec<&str> = full.iter().map(|c| c.as_str()).collect();
+            errors.push(row_error(
+                row,
+                &format!("Courses are full: {}", full.join(", ")),
+            ));
+            continue;
+        }
+        for course in data.courses.iter() {
+            if let Some(free) = seats.get_mut(course) {
+                *free -= 1;
+            }
+        }
+
+        students.push(FullStudent {
+            id,
+            full_name: data.full_name,
+            email: data.email,
+            age: data.age,
+            //resolved below, only when the students are saved
+            img: String::new(),
+            registration_date,
+            courses: data.courses,
+            version: 1,
+            deleted_at: None,
+            created_by: audit.actor_id,
+            updated_by: audit.actor_id,
+            updated_at: registration_date,
+            grades: None,
+            waitlist: Vec::new(),
+        });
+    }
+    errors.sort_by_key(|e| e.row);
+
+    let valid = students.len();
+    if dry_run || students.is_empty() {
+        return Ok(ImportReport {
+            dry_run,
+            total,
+            valid,
+            imported: 0,
+            errors,
+        });
+    }
+
+    let imgs: Vec<String> = stream::iter(students.iter())
+        .map(|s| avatar_client.resolve(s.id, &s.full_name, &s.email))
+        .buffered(AVATAR_CONCURRENCY)
+        .collect()
+        .await;
+    for (student, img) in students.iter_mut().zip(imgs) {
+        student.img = img;
+    }
+
+    save_students(&students, audit.actor_id, registration_date, connection).await?;
+
+    for student in students.iter() {
+        db_write_audit(
+            audit,
+            "import",
+            "student",
+            Some(student.id.to_string()),
+            None,
+            serde_json::to_value(student).ok(),
+            connection,
+        )
+        .await;
+    }
+
+    Ok(ImportReport {
+        dry_run,
+        total,
+        valid,
+        imported: valid,
+        errors,
+    })
+}
+
+async fn save_students(
+    students: &[FullStudent],
+    actor_id: Option<Uuid>,
+    registration_date: OffsetDateTime,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let ids: Vec<Uuid> = students.iter().map(|s| s.id).collect();
+    let names: Vec<String> = students.iter().map(|s| s.full_name.clone()).collect();
+    let ages: Vec<i32> = students.iter().map(|s| s.age).collect();
+    let emails: Vec<String> = students.iter().map(|s| s.email.clone()).collect();
+    let imgs: Vec<String> = students.iter().map(|s| s.img.clone()).collect();
+    let query_span = tracing::info_span!("Saving imported students", count = students.len());
+    sqlx::query!(
+        r#"
+            insert into students
+                (id, full_name, age, registration_date, email, img, created_by, updated_by, updated_at)
+            select id, full_name, age, $6, email, img, $7, $7, $6
+            from unnest($1::uuid[], $2::varchar[], $3::int4[], $4::varchar[], $5::text[])
+                as s(id, full_name, age, email, img);
+        "#,
+        &ids,
+        &names,
+        &ages,
+        &emails,
+        &imgs,
+        registration_date,
+        actor_id
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the students to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let (student_ids, courses): (Vec<Uuid>, Vec<String>) = students
+        .iter()
+        .flat_map(|s| s.courses.iter().map(move |c| (s.id, c.clone())))
+        .unzip();
+    let query_span = tracing::info_span!("Saving courses of imported students");
+    sqlx::query!(
+        r#"
+            insert into courses (student_id, course_name, term_id)
+            select student_id, course_name, (select id from terms where is_current)
+            from unnest($1::uuid[], $2::text[]) as c(student_id, course_name);
+        "#,
+        &student_ids,
+        &courses
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert students' courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    //seats counted before the transaction could have been taken meanwhile
+    let full = overfull_courses(&courses, &mut transaction).await?;
+    if !full.is_empty() {
+        return Err(Error::new(
+            Some(format!("Courses are full: {}", full.join(", "))),
+            Some("Courses have been filled meanwhile, import the file again".into()),
+            ErrorTypes::Conflict,
+        ));
+    }
+
+    for student in students.iter() {
+        db_notify_welcome(student, &mut transaction).await?;
+        if let Ok(data) = serde_json::to_value(student) {
+            db_emit_webhook_event(WebhookEvent::StudentCreated, data, &mut transaction).await?;
+        }
+    }
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not import the students".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+fn row_error(row: u64, message: &str) -> ImportRowError {
+    ImportRowError {
+        row,
+        errors: serde_json::Value::String(message.into()),
+    }
+}
@@ -1,5 +1,42 @@
+pub mod assignment;
+pub mod attendance;
+pub mod audit;
+pub mod avatar;
+pub mod course;
+pub mod email;
+pub mod export;
 pub mod functionality;
+pub mod grade;
+pub mod group;
+pub mod guardian;
+pub mod import;
+pub mod outbox;
+pub mod report;
+pub mod schedule;
+pub mod search;
+pub mod stats;
+pub mod teacher;
+pub mod term;
 pub mod user;
+pub mod webhook;
 
+pub use assignment::*;
+pub use attendance::*;
+pub use audit::*;
+pub use avatar::*;
+pub use course::*;
+pub use email::*;
+pub use export::*;
 pub use functionality::*;
+pub use grade::*;
+pub use group::*;
+pub use guardian::*;
+pub use import::*;
+pub use report::*;
+pub use schedule::*;
+pub use search::*;
+pub use stats::*;
+pub use teacher::*;
+pub use term::*;
 pub use user::*;
+pub use webhook::*;
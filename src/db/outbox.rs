@@ -0,0 +1,14 @@
+//Emails and webhook deliveries are sent through outbox tables with the same columns:
+//`attempts`, `next_attempt_at`, `last_error` and `failed_at`. The sender claims due rows,
+//concurrent senders get different ones, and failed rows are retried with exponential backoff
+use crate::app::OutboxSettings;
+
+//claimed rows aren't claimed again for this time, e.g. when the sender crashes
+pub(crate) const LEASE_SECONDS: f64 = 300.0;
+
+//the next attempt is delayed twice as long as the previous one.
+//Returns the delay in seconds and whether all attempts are used, `attempts` includes the failed one
+pub(crate) fn retry_after(attempts: i32, settings: &OutboxSettings) -> (f64, bool) {
+    let delay = settings.backoff_seconds * 2f64.powi(attempts.max(1) - 1);
+    (delay, attempts >= settings.max_attempts)
+}
@@ -0,0 +1,190 @@
+use crate::{
+    db::{db_get_current_term, db_get_student, db_write_audit},
+    errors::{Error, ErrorTypes},
+    schemas::{
+        AddReportComment, AttendanceRate, AuditContext, Grade, GradeSummary, ReportCard,
+        ReportComment,
+    },
+};
+use sqlx::PgPool;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Set report card comment", skip(connection), ret(Debug))]
+pub async fn db_set_report_comment(
+    student_id: Uuid,
+    data: AddReportComment,
+    teacher_id: Option<Uuid>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Vec<ReportComment>, Error> {
+    let before = db_get_report_comments(student_id, connection).await?;
+
+    let query_span =
+        tracing::info_span!("Saving report comment",%student_id,course_name=%data.course_name);
+    sqlx::query!(
+        r#"
+            insert into report_comments (student_id, course_name, term_id, comment, teacher_id)
+            select s.id, $2, (select id from terms where is_current), $3, $4
+            from students s where s.id = $1 and s.deleted_at is null
+            on conflict (student_id, course_name, coalesce(term_id, '00000000-0000-0000-0000-000000000000'))
+            do update set
+                comment = excluded.comment,
+                teacher_id = excluded.teacher_id,
+                updated_at = now()
+            returning id;
+        "#,
+        student_id,
+        data.course_name,
+        data.comment,
+        teacher_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not save the comment".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find student with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    let result = db_get_report_comments(student_id, connection).await?;
+    db_write_audit(
+        audit,
+        "set_report_comment",
+        "student",
+        Some(student_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//comments of the current term
+#[instrument(name = "Get report card comments", skip(connection))]
+pub async fn db_get_report_comments(
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<ReportComment>, Error> {
+    let query_span = tracing::info_span!("Get report comments of student",%student_id);
+    sqlx::query_as!(
+        ReportComment,
+        r#"
+            select rc.course_name, rc.comment, t.full_name as "teacher_name?", rc.updated_at
+            from report_comments rc
+            left join teachers t on t.id = rc.teacher_id
+            where rc.student_id = $1
+                and rc.term_id is not distinct from (select id from terms where is_current)
+            order by rc.course_name;
+        "#,
+        student_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get report card comments".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//grades and attendance within the dates of the current term
+#[instrument(name = "Get student's report card", skip(connection))]
+pub async fn db_get_report_card(
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<ReportCard, Error> {
+    let student = db_get_student(student_id, connection).await?;
+    let term = db_get_current_term(connection).await.ok();
+    let from = term.as_ref().map(|t| t.starts_on);
+    let to = term.as_ref().map(|t| t.ends_on);
+
+    let query_span = tracing::info_span!("Get grades of the term",%student_id);
+    let grades = sqlx::query_as!(
+        Grade,
+        r#"
+            select * from grades
+            where student_id = $1
+                and ($2::date is null or graded_on >= $2)
+                and ($3::date is null or graded_on <= $3)
+            order by course_name, graded_on;
+        "#,
+        student_id,
+        from,
+        to
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's grades".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Aggregate attendance of the term",%student_id);
+    let attendance = sqlx::query_as!(
+        AttendanceRate,
+        r#"
+            select
+                course_name,
+                count(*) as "records!",
+                count(*) filter (where status = 'present') as "present!",
+                count(*) filter (where status = 'absent') as "absent!",
+                count(*) filter (where status = 'late') as "late!",
+                count(*) filter (where status = 'excused') as "excused!",
+                round(
+                    100.0 * count(*) filter (where status in ('present', 'late'))
+                    / nullif(count(*) filter (where status <> 'excused'), 0),
+                    2
+                )::float8 as rate
+            from attendance
+            where student_id = $1
+                and ($2::date is null or session_date >= $2)
+                and ($3::date is null or session_date <= $3)
+            group by course_name
+            order by course_name;
+        "#,
+        student_id,
+        from,
+        to
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's attendance".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let comments = db_get_report_comments(student_id, connection).await?;
+
+    Ok(ReportCard {
+        student,
+        term,
+        grades: GradeSummary::from_grades(&grades),
+        attendance_rate: ReportCard::total_attendance(&attendance),
+        attendance,
+        comments,
+    })
+}
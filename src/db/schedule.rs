@@ -0,0 +1,390 @@
+use crate::{
+    db::db_write_audit,
+    errors::{Error, ErrorTypes},
+    schemas::{AddSlot, AuditContext, PatchSlot, ScheduleSlot},
+};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(
+    name = "Adding a new schedule slot to db",
+    skip(connection),
+    ret(Debug)
+)]
+pub async fn db_add_slot(
+    data: AddSlot,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<ScheduleSlot, Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    lock_slots(&mut transaction).await?;
+    check_conflicts(&data, None, &mut transaction).await?;
+
+    let query_span = tracing::info_span!("Saving new slot in database");
+    let slot = sqlx::query_as!(
+        ScheduleSlot,
+        r#"
+            insert into schedule_slots
+                (course_name, term_id, day_of_week, starts_at, ends_at, room, teacher_id)
+            values ($1, (select id from terms where is_current), $2, $3, $4, $5, $6)
+            returning *;
+        "#,
+        data.course_name,
+        data.day_of_week,
+        data.starts_at,
+        data.ends_at,
+        data.room,
+        data.teacher_id
+    )
+    .fetch_one(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the slot to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the slot to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "create",
+        "schedule_slot",
+        Some(slot.id.to_string()),
+        None,
+        serde_json::to_value(&slot).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(slot)
+}
+
+#[instrument(name = "Get schedule slot from db", skip(connection))]
+pub async fn db_get_slot(
+    slot_id: Uuid,
+    connection: impl PgExecutor<'_>,
+) -> Result<ScheduleSlot, Error> {
+    let query_span = tracing::info_span!("Get slot",%slot_id);
+    sqlx::query_as!(
+        ScheduleSlot,
+        "select * from schedule_slots where id = $1",
+        slot_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the slot from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find slot with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })
+}
+
+#[instrument(name = "Patching schedule slot", skip(connection), ret(Debug))]
+pub async fn db_patch_slot(
+    slot_id: Uuid,
+    data: PatchSlot,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<ScheduleSlot, Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    lock_slots(&mut transaction).await?;
+
+    let before = db_get_slot(slot_id, &mut transaction).await?;
+    let before_json = serde_json::to_value(&before).ok();
+
+    let slot = AddSlot::patched(before, data).map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Invalid data".into()),
+            ErrorTypes::ValidationError,
+        )
+    })?;
+    check_conflicts(&slot, Some(slot_id), &mut transaction).await?;
+
+    let query_span = tracing::info_span!("Updating slot's data", %slot_id);
+    let result = sqlx::query_as!(
+        ScheduleSlot,
+        r#"
+            update schedule_slots set
+                day_of_week = $1, starts_at = $2, ends_at = $3, room = $4, teacher_id = $5
+            where id = $6
+            returning *;
+        "#,
+        slot.day_of_week,
+        slot.starts_at,
+        slot.ends_at,
+        slot.room,
+        slot.teacher_id,
+        slot_id
+    )
+    .fetch_one(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new slot's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new slot's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "update",
+        "schedule_slot",
+        Some(slot_id.to_string()),
+        before_json,
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+#[instrument(name = "Delete schedule slot from db", skip(connection))]
+pub async fn db_delete_slot(
+    slot_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = db_get_slot(slot_id, connection).await?;
+
+    let query_span = tracing::info_span!("Delete slot",%slot_id);
+    sqlx::query!("delete from schedule_slots where id = $1;", slot_id)
+        .execute(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not delete the slot".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    db_write_audit(
+        audit,
+        "delete",
+        "schedule_slot",
+        Some(slot_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    Ok(())
+}
+
+//weekly slots of the student's courses in the current term
+#[instrument(name = "Get student's timetable", skip(connection))]
+pub async fn db_get_student_timetable(
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<ScheduleSlot>, Error> {
+    let query_span = tracing::info_span!("Get slots of student's courses",%student_id);
+    sqlx::query_as!(
+        ScheduleSlot,
+        r#"
+            select sl.* from schedule_slots sl
+            where sl.term_id is not distinct from (select id from terms where is_current)
+                and sl.course_name in (
+                    select course_name from current_courses where student_id = $1
+                )
+            order by sl.day_of_week, sl.starts_at;
+        "#,
+        student_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get student's timetable".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//weekly slots taught by the teacher in the current term
+#[instrument(name = "Get teacher's timetable", skip(connection))]
+pub async fn db_get_teacher_timetable(
+    teacher_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<ScheduleSlot>, Error> {
+    let query_span = tracing::info_span!("Get slots of teacher",%teacher_id);
+    sqlx::query_as!(
+        ScheduleSlot,
+        r#"
+            select * from schedule_slots
+            where term_id is not distinct from (select id from terms where is_current)
+                and teacher_id = $1
+            order by day_of_week, starts_at;
+        "#,
+        teacher_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get teacher's timetable".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//slots are changed one at a time, otherwise concurrent changes could book the same time
+//without seeing each other
+async fn lock_slots(transaction: &mut Transaction<'_, Postgres>) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Lock schedule slots");
+    sqlx::query("lock table schedule_slots in share row exclusive mode;")
+        .execute(&mut *transaction)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not lock schedule slots".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+    Ok(())
+}
+
+//room or teacher double-booked, or students of the course busy with another course.
+//The slots must be locked by the transaction
+async fn check_conflicts(
+    slot: &AddSlot,
+    exclude: Option<Uuid>,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Check schedule conflicts");
+    let conflict = sqlx::query!(
+        r#"
+            select sl.course_name, sl.room, sl.teacher_id from schedule_slots sl
+            where sl.term_id is not distinct from (select id from terms where is_current)
+                and sl.id is distinct from $1
+                and sl.day_of_week = $2
+                and sl.starts_at < $4 and $3 < sl.ends_at
+                and (sl.room = $5 or sl.teacher_id = $6)
+            limit 1;
+        "#,
+        exclude,
+        slot.day_of_week,
+        slot.starts_at,
+        slot.ends_at,
+        slot.room,
+        slot.teacher_id
+    )
+    .fetch_optional(&mut *transaction)
+    .instrument(query_span.clone())
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check schedule conflicts".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if let Some(conflict) = conflict {
+        let message = if conflict.room == slot.room {
+            format!(
+                "Room {} is booked for {} at this time",
+                conflict.room, conflict.course_name
+            )
+        } else {
+            format!("Teacher is busy with {} at this time", conflict.course_name)
+        };
+        return Err(Error::new(None, Some(message), ErrorTypes::Conflict));
+    }
+
+    let busy_students = sqlx::query!(
+        r#"
+            select distinct c.student_id as "student_id!", sl.course_name as "course_name!" from current_courses c
+            join current_courses other on other.student_id = c.student_id
+                and other.course_name <> c.course_name
+            join students s on s.id = c.student_id
+            join schedule_slots sl on sl.course_name = other.course_name
+            where c.course_name = $1 and s.deleted_at is null
+                and sl.term_id is not distinct from (select id from terms where is_current)
+                and sl.id is distinct from $2
+                and sl.day_of_week = $3
+                and sl.starts_at < $5 and $4 < sl.ends_at;
+        "#,
+        slot.course_name,
+        exclude,
+        slot.day_of_week,
+        slot.starts_at,
+        slot.ends_at
+    )
+    .fetch_all(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check schedule conflicts".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if !busy_students.is_empty() {
+        let students: Vec<String> = busy_students
+            .iter()
+            .map(|rec| format!("{} ({})", rec.student_id, rec.course_name))
+            .collect();
+        return Err(Error::new(
+            Some(students.join(", ")),
+            Some("Students of the course have other courses at this time".into()),
+            ErrorTypes::Conflict,
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::{
+    errors::{Error, ErrorTypes},
+    schemas::{SearchHighlights, SearchQuery, SearchResult},
+};
+use sqlx::PgPool;
+use tracing::{instrument, Instrument};
+
+//minimal word similarity of a match, low enough to find words with typos
+const SIMILARITY_THRESHOLD: &str = "0.4";
+
+#[instrument(name = "Search students in db", skip(connection))]
+pub async fn db_search_students(
+    query: &SearchQuery,
+    connection: &PgPool,
+) -> Result<Vec<SearchResult>, Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    //the threshold of the `<%` operator, which can use the trigram indexes
+    sqlx::query("select set_config('pg_trgm.word_similarity_threshold', $1, true);")
+        .bind(SIMILARITY_THRESHOLD)
+        .execute(&mut transaction)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not set the search threshold".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    let query_span = tracing::info_span!("Search students");
+    let rows = sqlx::query!(
+        r#"
+            select s.id, s.full_name, s.email, s.age,
+                coalesce(c.courses, '{}') as "courses!",
+                greatest(
+                    word_similarity($1, s.full_name),
+                    word_similarity($1, s.email),
+                    coalesce(c.rank, 0)
+                ) as "rank!"
+            from students s
+            left join lateral (
+                select array_agg(cc.course_name order by cc.course_name) as courses,
+                    max(word_similarity($1, cc.course_name)) as rank
+                from current_courses cc where cc.student_id = s.id
+            ) c on true
+            where s.deleted_at is null and (
+                $1 <% s.full_name or $1 <% s.email or exists (
+                    select 1 from current_courses cc
+                    where cc.student_id = s.id and $1 <% cc.course_name
+                )
+            )
+            order by 6 desc, s.full_name
+            limit $2;
+        "#,
+        query.q,
+        query.limit
+    )
+    .fetch_all(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not search students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not search students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SearchResult {
+            highlights: SearchHighlights::new(&query.q, &row.full_name, &row.email, &row.courses),
+            id: row.id,
+            full_name: row.full_name,
+            email: row.email,
+            age: row.age,
+            courses: row.courses,
+            rank: row.rank,
+        })
+        .collect())
+}
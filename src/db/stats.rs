@@ -0,0 +1,98 @@
+use crate::{
+    errors::{Error, ErrorTypes},
+    schemas::{AgeGroup, CourseEnrollment, MonthRegistrations, Stats},
+};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::{instrument, Instrument};
+
+#[instrument(name = "Get statistics from db", skip(connection))]
+pub async fn db_get_stats(connection: &PgPool) -> Result<Stats, Error> {
+    let query_span = tracing::info_span!("Count students");
+    let total_students =
+        sqlx::query!(r#"select count(*) as "count!" from students where deleted_at is null;"#)
+            .fetch_one(connection)
+            .instrument(query_span)
+            .await
+            .map_err(stats_error)?
+            .count;
+
+    let query_span = tracing::info_span!("Count registrations per month");
+    let registrations_per_month = sqlx::query_as!(
+        MonthRegistrations,
+        r#"
+            select to_char(date_trunc('month', registration_date), 'YYYY-MM') as "month!",
+                count(*) as "count!"
+            from students where deleted_at is null
+            group by 1 order by 1;
+        "#
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(stats_error)?;
+
+    let query_span = tracing::info_span!("Count students by age");
+    let age_distribution = sqlx::query_as!(
+        AgeGroup,
+        r#"
+            select age / 5 * 5 as "from!", age / 5 * 5 + 4 as "to!", count(*) as "count!"
+            from students where deleted_at is null
+            group by 1 order by 1;
+        "#
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(stats_error)?;
+
+    let query_span = tracing::info_span!("Count enrollments per course");
+    let rows = sqlx::query!(
+        r#"
+            select c.course_name as "course_name!", count(*) as "students!",
+                count(*) = max(count(*)) over () as "most!",
+                count(*) = min(count(*)) over () as "least!"
+            from current_courses c
+            join students s on s.id = c.student_id
+            where s.deleted_at is null
+            group by c.course_name
+            order by 2 desc, 1;
+        "#
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(stats_error)?;
+
+    let mut stats = Stats {
+        total_students,
+        registrations_per_month,
+        age_distribution,
+        courses: Vec::with_capacity(rows.len()),
+        most_popular: Vec::new(),
+        least_popular: Vec::new(),
+        generated_at: OffsetDateTime::now_utc(),
+    };
+    for row in rows {
+        let course = CourseEnrollment {
+            course_name: row.course_name,
+            students: row.students,
+        };
+        if row.most {
+            stats.most_popular.push(course.clone());
+        }
+        if row.least {
+            stats.least_popular.push(course.clone());
+        }
+        stats.courses.push(course);
+    }
+    Ok(stats)
+}
+
+fn stats_error(e: sqlx::Error) -> Error {
+    Error::new(
+        Some(e.to_string()),
+        Some("Can not get statistics".into()),
+        ErrorTypes::DbError,
+    )
+}
@@ -0,0 +1,391 @@
+use crate::{
+    db::{db_write_audit, load_courses},
+    errors::{Error, ErrorTypes},
+    schemas::{AddTeacher, AuditContext, FullStudent, FullTeacher, PatchTeacher, Student, Teacher},
+};
+use sqlx::PgPool;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Adding a new teacher to db", skip(connection), ret(Debug))]
+pub async fn db_add_teacher(
+    data: AddTeacher,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<FullTeacher, Error> {
+    let query_span = tracing::info_span!("Saving new teacher in database");
+    let teacher = sqlx::query_as!(
+        Teacher,
+        r#"
+            insert into teachers (full_name, email, subjects, user_id)
+            values ($1, $2, $3, $4)
+            returning *;
+        "#,
+        data.full_name,
+        data.email,
+        &data.subjects,
+        data.user_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the teacher to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let teacher = teacher.with_courses(Vec::new());
+    db_write_audit(
+        audit,
+        "create",
+        "teacher",
+        Some(teacher.id.to_string()),
+        None,
+        serde_json::to_value(&teacher).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(teacher)
+}
+
+#[instrument(name = "Get all teachers from db", skip(connection))]
+pub async fn db_get_all_teachers(connection: &PgPool) -> Result<Vec<FullTeacher>, Error> {
+    let query_span = tracing::info_span!("Get teachers from teachers table");
+    let teachers = sqlx::query_as!(Teacher, "select * from teachers order by full_name")
+        .fetch_all(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not get all teachers from db".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    let mut full_teachers = Vec::with_capacity(teachers.len());
+    for teacher in teachers {
+        let courses = get_teacher_courses(teacher.id, connection).await?;
+        full_teachers.push(teacher.with_courses(courses));
+    }
+    Ok(full_teachers)
+}
+
+#[instrument(name = "Get teacher from db", skip(connection))]
+pub async fn db_get_teacher(teacher_id: Uuid, connection: &PgPool) -> Result<FullTeacher, Error> {
+    let query_span = tracing::info_span!("Get teacher",%teacher_id);
+    let teacher = sqlx::query_as!(Teacher, "select * from teachers where id=$1", teacher_id)
+        .fetch_optional(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not get the teacher from db".into()),
+                ErrorTypes::DbError,
+            )
+        })?
+        .ok_or_else(|| {
+            Error::new(
+                None,
+                Some("Can not find teacher with the provided id".into()),
+                ErrorTypes::NotFoundError,
+            )
+        })?;
+
+    let courses = get_teacher_courses(teacher_id, connection).await?;
+    Ok(teacher.with_courses(courses))
+}
+
+//teacher profile linked to the login account
+#[instrument(name = "Get teacher by user from db", skip(connection))]
+pub async fn db_get_teacher_by_user(
+    user_id: Uuid,
+    connection: &PgPool,
+) -> Result<FullTeacher, Error> {
+    let query_span = tracing::info_span!("Get teacher by user",%user_id);
+    let teacher = sqlx::query_as!(Teacher, "select * from teachers where user_id=$1", user_id)
+        .fetch_optional(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not get the teacher from db".into()),
+                ErrorTypes::DbError,
+            )
+        })?
+        .ok_or_else(|| {
+            Error::new(
+                None,
+                Some("Your account isn't linked to a teacher".into()),
+                ErrorTypes::NotFoundError,
+            )
+        })?;
+
+    let courses = get_teacher_courses(teacher.id, connection).await?;
+    Ok(teacher.with_courses(courses))
+}
+
+#[instrument(name = "Patching teacher", skip(connection), ret(Debug))]
+pub async fn db_patch_teacher(
+    teacher_id: Uuid,
+    data: PatchTeacher,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<FullTeacher, Error> {
+    let before = db_get_teacher(teacher_id, connection).await?;
+
+    let query_span = tracing::info_span!("Updating teacher's data", %teacher_id);
+    sqlx::query!(
+        r#"
+            update teachers set
+                full_name = coalesce($1, full_name),
+                email = coalesce($2, email),
+                subjects = coalesce($3, subjects),
+                user_id = coalesce($4, user_id)
+            where id = $5;
+        "#,
+        data.full_name,
+        data.email,
+        data.subjects.as_deref(),
+        data.user_id,
+        teacher_id
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new teacher's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let result = db_get_teacher(teacher_id, connection).await?;
+    db_write_audit(
+        audit,
+        "update",
+        "teacher",
+        Some(teacher_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+#[instrument(name = "Delete teacher from db", skip(connection))]
+pub async fn db_delete_teacher(
+    teacher_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = db_get_teacher(teacher_id, connection).await?;
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Delete teacher's courses",%teacher_id);
+    sqlx::query!(
+        "delete from course_teachers where teacher_id = $1;",
+        teacher_id
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not delete teacher's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Delete teacher",%teacher_id);
+    sqlx::query!("delete from teachers where id = $1;", teacher_id)
+        .execute(&mut transaction)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not delete the teacher".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not delete the teacher".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "delete",
+        "teacher",
+        Some(teacher_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    Ok(())
+}
+
+#[instrument(name = "Assign courses to teacher", skip(connection), ret(Debug))]
+pub async fn db_assign_courses(
+    teacher_id: Uuid,
+    courses: &[String],
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<FullTeacher, Error> {
+    let before = db_get_teacher(teacher_id, connection).await?;
+
+    let query_span = tracing::info_span!("Assign courses",%teacher_id,courses=?courses);
+    sqlx::query!(
+        r#"
+            insert into course_teachers (course_name, teacher_id)
+            select distinct c, $1::uuid from unnest($2::text[]) as c
+            on conflict do nothing;
+        "#,
+        teacher_id,
+        courses
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not assign courses to the teacher".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let result = db_get_teacher(teacher_id, connection).await?;
+    db_write_audit(
+        audit,
+        "assign_courses",
+        "teacher",
+        Some(teacher_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+#[instrument(name = "Unassign course from teacher", skip(connection), ret(Debug))]
+pub async fn db_unassign_course(
+    teacher_id: Uuid,
+    course_name: &str,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<FullTeacher, Error> {
+    let before = db_get_teacher(teacher_id, connection).await?;
+
+    let query_span = tracing::info_span!("Unassign course",%teacher_id,%course_name);
+    sqlx::query!(
+        "delete from course_teachers where teacher_id = $1 and course_name = $2;",
+        teacher_id,
+        course_name
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not unassign the course".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let result = db_get_teacher(teacher_id, connection).await?;
+    db_write_audit(
+        audit,
+        "unassign_course",
+        "teacher",
+        Some(teacher_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//students enrolled in any of the teacher's courses
+#[instrument(name = "Get teacher's students", skip(connection))]
+pub async fn db_get_teacher_students(
+    teacher_id: Uuid,
+    connection: &PgPool,
+) -> Result<Vec<FullStudent>, Error> {
+    let query_span = tracing::info_span!("Get students of teacher's courses",%teacher_id);
+    let students = sqlx::query_as!(
+        Student,
+        r#"
+            select * from students s
+            where s.deleted_at is null and exists (
+                select 1 from current_courses c
+                join course_teachers ct on ct.course_name = c.course_name
+                where c.student_id = s.id and ct.teacher_id = $1
+            )
+            order by s.full_name;
+        "#,
+        teacher_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get teacher's students".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    load_courses(students, connection).await
+}
+
+async fn get_teacher_courses(teacher_id: Uuid, connection: &PgPool) -> Result<Vec<String>, Error> {
+    let query_span = tracing::info_span!("Get teacher's courses",%teacher_id);
+    Ok(sqlx::query!(
+        "select course_name from course_teachers where teacher_id=$1 order by course_name",
+        teacher_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not find teacher's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.course_name)
+    .collect())
+}
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use crate::{
+    db::db_write_audit,
+    errors::{Error, ErrorTypes},
+    schemas::{
+        AcademicYear, AddAcademicYear, AddTerm, AuditContext, FullAcademicYear, Term,
+        TermCourses,
+    },
+};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+#[instrument(name = "Adding a new academic year to db", skip(connection), ret(Debug))]
+pub async fn db_add_academic_year(
+    data: AddAcademicYear,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<FullAcademicYear, Error> {
+    let query_span = tracing::info_span!("Saving new academic year in database");
+    let year = sqlx::query_as!(
+        AcademicYear,
+        r#"
+            insert into academic_years (name, starts_on, ends_on)
+            values ($1, $2, $3)
+            returning *;
+        "#,
+        data.name,
+        data.starts_on,
+        data.ends_on
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the academic year to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let year = year.with_terms(Vec::new());
+    db_write_audit(
+        audit,
+        "create",
+        "academic_year",
+        Some(year.id.to_string()),
+        None,
+        serde_json::to_value(&year).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(year)
+}
+
+#[instrument(name = "Get academic years from db", skip(connection))]
+pub async fn db_get_academic_years(connection: &PgPool) -> Result<Vec<FullAcademicYear>, Error> {
+    let query_span = tracing::info_span!("Get academic years");
+    let years = sqlx::query_as!(
+        AcademicYear,
+        "select * from academic_years order by starts_on desc"
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get academic years from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Get terms");
+    let terms = sqlx::query_as!(Term, "select * from terms order by starts_on")
+        .fetch_all(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not get terms from db".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    let mut by_year: HashMap<Uuid, Vec<Term>> = HashMap::new();
+    for term in terms {
+        by_year.entry(term.academic_year_id).or_default().push(term);
+    }
+
+    Ok(years
+        .into_iter()
+        .map(|year| {
+            let terms = by_year.remove(&year.id).unwrap_or_default();
+            year.with_terms(terms)
+        })
+        .collect())
+}
+
+//the term becomes current if its code is configured in the settings,
+//then it gets the enrollments made without a term
+#[instrument(name = "Adding a new term to db", skip(connection), ret(Debug))]
+pub async fn db_add_term(
+    academic_year_id: Uuid,
+    data: AddTerm,
+    current_term: Option<&str>,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Term, Error> {
+    let query_span = tracing::info_span!("Get academic year", %academic_year_id);
+    let year = sqlx::query_as!(
+        AcademicYear,
+        "select * from academic_years where id = $1",
+        academic_year_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the academic year from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find academic year with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    if data.starts_on < year.starts_on || data.ends_on > year.ends_on {
+        return Err(Error::new(
+            None,
+            Some(format!("Term must be within the academic year {}", year.name)),
+            ErrorTypes::ValidationError,
+        ));
+    }
+
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let is_current = current_term == Some(data.code.as_str());
+    let query_span = tracing::info_span!("Saving new term in database", %academic_year_id);
+    let term = sqlx::query_as!(
+        Term,
+        r#"
+            insert into terms (academic_year_id, code, name, starts_on, ends_on, is_current)
+            values ($1, $2, $3, $4, $5, $6)
+            returning *;
+        "#,
+        academic_year_id,
+        data.code,
+        data.name,
+        data.starts_on,
+        data.ends_on,
+        is_current
+    )
+    .fetch_one(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the term to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if is_current {
+        adopt_unscoped_enrollments(term.id, &mut transaction).await?;
+    }
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the term to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "create",
+        "term",
+        Some(term.id.to_string()),
+        None,
+        serde_json::to_value(&term).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(term)
+}
+
+#[instrument(name = "Get current term from db", skip(connection))]
+pub async fn db_get_current_term(connection: &PgPool) -> Result<Term, Error> {
+    let query_span = tracing::info_span!("Get current term");
+    sqlx::query_as!(Term, "select * from terms where is_current")
+        .fetch_optional(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not get the current term from db".into()),
+                ErrorTypes::DbError,
+            )
+        })?
+        .ok_or_else(|| {
+            Error::new(
+                None,
+                Some("Current term isn't configured".into()),
+                ErrorTypes::NotFoundError,
+            )
+        })
+}
+
+//mark the term configured in the settings as current, it gets the enrollments made without a term.
+//Returns false if there is no such term.
+//It's only called at startup, a change of `academic.current_term` needs a restart
+#[instrument(name = "Set current term", skip(connection))]
+pub async fn db_sync_current_term(code: Option<&str>, connection: &PgPool) -> Result<bool, Error> {
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Reset current term");
+    sqlx::query!(
+        "update terms set is_current = false where is_current and code is distinct from $1;",
+        code
+    )
+    .execute(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not reset the current term".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Mark current term", ?code);
+    let marked = sqlx::query!(
+        "update terms set is_current = true where code = $1 returning id;",
+        code
+    )
+    .fetch_optional(&mut transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set the current term".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if let Some(term) = marked.as_ref() {
+        adopt_unscoped_enrollments(term.id, &mut transaction).await?;
+    }
+
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set the current term".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    Ok(marked.is_some())
+}
+
+//enrollments and waitlists made while no term was current (or before terms were used) belong
+//to the current term, otherwise `current_courses` would hide them
+async fn adopt_unscoped_enrollments(
+    term_id: Uuid,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Move unscoped enrollments to the term", %term_id);
+    sqlx::query!(
+        "update courses set term_id = $1 where term_id is null;",
+        term_id
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span.clone())
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not move enrollments to the current term".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    sqlx::query!(
+        "update course_waitlist set term_id = $1 where term_id is null;",
+        term_id
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not move waitlists to the current term".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
+
+//student's courses of the term, the current term if not provided
+#[instrument(name = "Get student's courses of term", skip(connection))]
+pub async fn db_get_student_term_courses(
+    student_id: Uuid,
+    term: Option<&str>,
+    connection: &PgPool,
+) -> Result<TermCourses, Error> {
+    let term = match term {
+        Some(code) => Some(code.to_string()),
+        None => sqlx::query!("select code from terms where is_current")
+            .fetch_optional(connection)
+            .await
+            .map_err(|e| {
+                Error::new(
+                    Some(e.to_string()),
+                    Some("Can not get the current term from db".into()),
+                    ErrorTypes::DbError,
+                )
+            })?
+            .map(|rec| rec.code),
+    };
+
+    let term_id = match &term {
+        Some(code) => Some(
+            sqlx::query!("select id from terms where code = $1", code)
+                .fetch_optional(connection)
+                .await
+                .map_err(|e| {
+                    Error::new(
+                        Some(e.to_string()),
+                        Some("Can not get the term from db".into()),
+                        ErrorTypes::DbError,
+                    )
+                })?
+                .ok_or_else(|| {
+                    Error::new(
+                        None,
+                        Some(format!("Can not find term '{}'", code)),
+                        ErrorTypes::NotFoundError,
+                    )
+                })?
+                .id,
+        ),
+        None => None,
+    };
+
+    let query_span = tracing::info_span!("Get courses of term", %student_id, ?term);
+    let courses = sqlx::query!(
+        r#"
+            select course_name from courses
+            where student_id = $1 and term_id is not distinct from $2
+            order by course_name;
+        "#,
+        student_id,
+        term_id
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not find student's courses".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .into_iter()
+    .map(|rec| rec.course_name)
+    .collect();
+
+    Ok(TermCourses { term, courses })
+}
@@ -1,6 +1,7 @@
 use crate::{
+    db::{db_emit_webhook_event, db_write_audit},
     errors::{Auth, Error, ErrorTypes},
-    schemas::{LoginUser, RegisterUser, User},
+    schemas::{AuditContext, LoginUser, RegisterUser, Role, StudentAccount, User, WebhookEvent},
 };
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
@@ -8,9 +9,14 @@ use argon2::{
 };
 use sqlx::{PgPool, Row};
 use tracing::{instrument, Instrument};
+use uuid::Uuid;
 
 #[instrument(name = "Add new user", skip(connection), ret(Debug))]
-pub async fn db_add_user(data: RegisterUser, connection: &PgPool) -> Result<User, Error> {
+pub async fn db_add_user(
+    data: RegisterUser,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<User, Error> {
     let query_span =
         tracing::info_span!("Check if another users with provided email or username exist");
     let exists: bool =
@@ -50,15 +56,27 @@ pub async fn db_add_user(data: RegisterUser, connection: &PgPool) -> Result<User
         })?
         .to_string();
 
+    //the user and the event are saved together
+    let mut transaction = connection.begin().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not start transaction".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
     let query_span = tracing::info_span!("Inserting new user to db");
     let query_result = sqlx::query_as!(
         User,
-        "INSERT INTO users (username,email,password_hash) VALUES ($1, $2, $3) RETURNING *",
+        r#"
+            INSERT INTO users (username,email,password_hash) VALUES ($1, $2, $3)
+            RETURNING id, username, email, password_hash, created_at, role as "role: Role"
+        "#,
         data.username,
         data.email,
         hashed_password
     )
-    .fetch_one(connection)
+    .fetch_one(&mut transaction)
     .instrument(query_span)
     .await
     .map_err(|e| {
@@ -69,23 +87,125 @@ pub async fn db_add_user(data: RegisterUser, connection: &PgPool) -> Result<User
         )
     })?;
 
+    db_emit_webhook_event(
+        WebhookEvent::UserRegistered,
+        user_snapshot(&query_result),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await.map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert new user".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "register",
+        "user",
+        Some(query_result.id.to_string()),
+        None,
+        Some(user_snapshot(&query_result)),
+        connection,
+    )
+    .await;
+
     Ok(query_result)
 }
 
 #[instrument(name = "Find the user in db", skip(connection), ret(Debug))]
 pub async fn db_find_user(user_id: uuid::Uuid, connection: &PgPool) -> Result<User, Error> {
     let query_span = tracing::info_span!("Query user",%user_id);
-    let user = sqlx::query_as!(User, "select * from users where id=$1;", user_id)
-        .fetch_one(connection)
-        .instrument(query_span)
-        .await
-        .map_err(|e| {
-            Error::new(
-                Some(e.to_string()),
-                Some("User doesn't exist".into()),
-                ErrorTypes::NotFoundError,
-            )
-        })?;
+    let user = sqlx::query_as!(
+        User,
+        r#"
+            select id, username, email, password_hash, created_at, role as "role: Role"
+            from users where id=$1;
+        "#,
+        user_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("User doesn't exist".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    Ok(user)
+}
+
+//the role is read on every request, so changes take effect before the token expires
+#[instrument(name = "Get user's role", skip(connection))]
+pub async fn db_get_user_role(user_id: Uuid, connection: &PgPool) -> Result<Role, Error> {
+    let query_span = tracing::info_span!("Query user's role",%user_id);
+    sqlx::query!(
+        r#"select role as "role: Role" from users where id = $1;"#,
+        user_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check user's role".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .map(|rec| rec.role)
+    .ok_or_else(|| Error {
+        cause: None,
+        message: Some("User doesn't exist. Log in again!".into()),
+        error_type: ErrorTypes::Auth(Auth::Authorization),
+    })
+}
+
+//gives the user the admin role if there is no admin yet, returns the changed user
+#[instrument(name = "Bootstrap admin", skip(audit, connection), ret(Debug))]
+pub async fn db_bootstrap_admin(
+    user_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Option<User>, Error> {
+    let query_span = tracing::info_span!("Promoting the first admin",%user_id);
+    let user = sqlx::query_as!(
+        User,
+        r#"
+            update users set role = 'admin'
+            where id = $1 and not exists (select 1 from users where role = 'admin')
+            returning id, username, email, password_hash, created_at, role as "role: Role";
+        "#,
+        user_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set the admin".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    if let Some(user) = &user {
+        db_write_audit(
+            audit,
+            "change_role",
+            "user",
+            Some(user_id.to_string()),
+            None,
+            Some(user_snapshot(user)),
+            connection,
+        )
+        .await;
+    }
 
     Ok(user)
 }
@@ -93,15 +213,22 @@ pub async fn db_find_user(user_id: uuid::Uuid, connection: &PgPool) -> Result<Us
 #[instrument(name = "User login", skip(connection), ret(Debug))]
 pub async fn user_login(data: LoginUser, connection: &PgPool) -> Result<User, Error> {
     let query_span = tracing::info_span!("Find user with the email");
-    let user = sqlx::query_as!(User, "select * from users where email = $1", data.email)
-        .fetch_one(connection)
-        .instrument(query_span)
-        .await
-        .map_err(|e| Error {
-            cause: Some(e.to_string()),
-            message: Some("Invalid password or email".into()),
-            error_type: ErrorTypes::Auth(Auth::Authentication),
-        })?;
+    let user = sqlx::query_as!(
+        User,
+        r#"
+            select id, username, email, password_hash, created_at, role as "role: Role"
+            from users where email = $1
+        "#,
+        data.email
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| Error {
+        cause: Some(e.to_string()),
+        message: Some("Invalid password or email".into()),
+        error_type: ErrorTypes::Auth(Auth::Authentication),
+    })?;
 
     let parsed_hash = PasswordHash::new(&user.password_hash).map_err(|e| Error {
         cause: Some(e.to_string()),
@@ -119,3 +246,138 @@ pub async fn user_login(data: LoginUser, connection: &PgPool) -> Result<User, Er
 
     Ok(user)
 }
+
+#[instrument(name = "Change user's role", skip(connection), ret(Debug))]
+pub async fn db_change_user_role(
+    user_id: uuid::Uuid,
+    role: Role,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<User, Error> {
+    let before = db_find_user(user_id, connection).await?;
+
+    let query_span = tracing::info_span!("Updating user's role",%user_id);
+    let user = sqlx::query_as!(
+        User,
+        r#"
+            update users set role=$1 where id=$2
+            returning id, username, email, password_hash, created_at, role as "role: Role";
+        "#,
+        role as Role,
+        user_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("User doesn't exist".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "change_role",
+        "user",
+        Some(user_id.to_string()),
+        Some(user_snapshot(&before)),
+        Some(user_snapshot(&user)),
+        connection,
+    )
+    .await;
+
+    Ok(user)
+}
+
+//the user logs in as the student, an account can belong to one student only
+#[instrument(name = "Link student's account", skip(connection), ret(Debug))]
+pub async fn db_link_student_user(
+    data: StudentAccount,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<StudentAccount, Error> {
+    let query_span = tracing::info_span!("Saving student's account",student_id=%data.student_id,user_id=%data.user_id);
+    let account = sqlx::query_as!(
+        StudentAccount,
+        r#"
+            insert into student_users (student_id, user_id)
+            select s.id, $2 from students s
+            where s.id = $1 and s.deleted_at is null
+            on conflict (student_id) do update set user_id = excluded.user_id
+            returning student_id, user_id;
+        "#,
+        data.student_id,
+        data.user_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not link the account to the student".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find student with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "link_account",
+        "student",
+        Some(account.student_id.to_string()),
+        None,
+        serde_json::to_value(&account).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(account)
+}
+
+#[instrument(name = "Check student's account", skip(connection))]
+pub async fn db_is_student_user(
+    user_id: Uuid,
+    student_id: Uuid,
+    connection: &PgPool,
+) -> Result<bool, Error> {
+    let query_span = tracing::info_span!("Check student's account",%user_id,%student_id);
+    sqlx::query!(
+        r#"
+            select exists (
+                select 1 from student_users where user_id = $1 and student_id = $2
+            ) as "exists!";
+        "#,
+        user_id,
+        student_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map(|rec| rec.exists)
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not check student's account".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//user's data for the audit log without password hash
+fn user_snapshot(user: &User) -> serde_json::Value {
+    serde_json::json!({
+        "id": user.id,
+        "username": user.username,
+        "email": user.email,
+        "role": user.role,
+    })
+}
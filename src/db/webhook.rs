@@ -0,0 +1,403 @@
+use crate::{
+    app::OutboxSettings,
+    db::{
+        db_write_audit,
+        outbox::{retry_after, LEASE_SECONDS},
+    },
+    errors::{Error, ErrorTypes},
+    schemas::{
+        AddWebhook, AuditContext, DeliveryFilter, NewWebhook, PatchWebhook, QueuedDelivery,
+        Webhook, WebhookDelivery, WebhookEvent, WebhookPayload,
+    },
+};
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+use time::OffsetDateTime;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+fn event_names(events: &[WebhookEvent]) -> Vec<String> {
+    events.iter().map(|e| e.as_str().to_string()).collect()
+}
+
+#[instrument(name = "Add webhook to db", skip(data, connection))]
+pub async fn db_add_webhook(
+    data: AddWebhook,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<NewWebhook, Error> {
+    let secret = data
+        .secret
+        .unwrap_or_else(|| format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()));
+
+    let query_span = tracing::info_span!("Inserting webhook", url = %data.url);
+    let webhook = sqlx::query_as!(
+        Webhook,
+        r#"
+            insert into webhooks (url, secret, events, created_by)
+            values ($1, $2, $3, $4)
+            returning id, url, events, active, created_by, created_at;
+        "#,
+        data.url,
+        secret,
+        &event_names(&data.events),
+        audit.actor_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not insert the webhook to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "create",
+        "webhook",
+        Some(webhook.id.to_string()),
+        None,
+        serde_json::to_value(&webhook).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(NewWebhook { webhook, secret })
+}
+
+#[instrument(name = "Get all webhooks from db", skip(connection))]
+pub async fn db_get_all_webhooks(connection: &PgPool) -> Result<Vec<Webhook>, Error> {
+    let query_span = tracing::info_span!("Get all webhooks");
+    sqlx::query_as!(
+        Webhook,
+        r#"
+            select id, url, events, active, created_by, created_at
+            from webhooks order by created_at;
+        "#
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get webhooks".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+#[instrument(name = "Get webhook from db", skip(connection))]
+pub async fn db_get_webhook(webhook_id: Uuid, connection: &PgPool) -> Result<Webhook, Error> {
+    let query_span = tracing::info_span!("Get webhook", %webhook_id);
+    sqlx::query_as!(
+        Webhook,
+        r#"
+            select id, url, events, active, created_by, created_at
+            from webhooks where id = $1;
+        "#,
+        webhook_id
+    )
+    .fetch_optional(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get the webhook from db".into()),
+            ErrorTypes::DbError,
+        )
+    })?
+    .ok_or_else(|| {
+        Error::new(
+            None,
+            Some("Can not find webhook with the provided id".into()),
+            ErrorTypes::NotFoundError,
+        )
+    })
+}
+
+#[instrument(name = "Patching webhook", skip(data, connection))]
+pub async fn db_patch_webhook(
+    webhook_id: Uuid,
+    data: PatchWebhook,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<Webhook, Error> {
+    let before = db_get_webhook(webhook_id, connection).await?;
+
+    let events = data.events.as_deref().map(event_names);
+    let query_span = tracing::info_span!("Updating webhook", %webhook_id);
+    let result = sqlx::query_as!(
+        Webhook,
+        r#"
+            update webhooks set
+                url = coalesce($1, url),
+                events = coalesce($2, events),
+                active = coalesce($3, active),
+                secret = coalesce($4, secret)
+            where id = $5
+            returning id, url, events, active, created_by, created_at;
+        "#,
+        data.url,
+        events.as_deref(),
+        data.active,
+        data.secret,
+        webhook_id
+    )
+    .fetch_one(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not set new webhook's data to db".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    db_write_audit(
+        audit,
+        "update",
+        "webhook",
+        Some(webhook_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        serde_json::to_value(&result).ok(),
+        connection,
+    )
+    .await;
+
+    Ok(result)
+}
+
+//deliveries of the webhook are removed with it
+#[instrument(name = "Delete webhook from db", skip(connection))]
+pub async fn db_delete_webhook(
+    webhook_id: Uuid,
+    audit: &AuditContext,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let before = db_get_webhook(webhook_id, connection).await?;
+
+    let query_span = tracing::info_span!("Delete webhook", %webhook_id);
+    sqlx::query!("delete from webhooks where id = $1;", webhook_id)
+        .execute(connection)
+        .instrument(query_span)
+        .await
+        .map_err(|e| {
+            Error::new(
+                Some(e.to_string()),
+                Some("Can not delete the webhook".into()),
+                ErrorTypes::DbError,
+            )
+        })?;
+
+    db_write_audit(
+        audit,
+        "delete",
+        "webhook",
+        Some(webhook_id.to_string()),
+        serde_json::to_value(&before).ok(),
+        None,
+        connection,
+    )
+    .await;
+
+    Ok(())
+}
+
+//delivery log of the webhook, the newest first
+#[instrument(name = "Get webhook deliveries", skip(connection))]
+pub async fn db_get_webhook_deliveries(
+    webhook_id: Uuid,
+    filter: &DeliveryFilter,
+    connection: &PgPool,
+) -> Result<Vec<WebhookDelivery>, Error> {
+    db_get_webhook(webhook_id, connection).await?;
+
+    let query_span = tracing::info_span!("Get deliveries of webhook", %webhook_id);
+    sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+            select id, webhook_id, event, payload, attempts, last_status, last_error,
+                next_attempt_at, delivered_at, failed_at, created_at
+            from webhook_deliveries
+            where webhook_id = $1
+                and ($2::text is null
+                    or ($2 = 'delivered' and delivered_at is not null)
+                    or ($2 = 'failed' and failed_at is not null)
+                    or ($2 = 'pending' and delivered_at is null and failed_at is null))
+            order by created_at desc, id desc
+            limit $3 offset $4;
+        "#,
+        webhook_id,
+        filter.status.map(|s| s.as_str()),
+        filter.limit.unwrap_or(100).clamp(1, 1000),
+        filter.offset.unwrap_or(0).max(0)
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get webhook deliveries".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+//queue the event for active webhooks subscribed to it. It's queued in the transaction of the
+//change, so the event is delivered only if the change is committed and never lost after it
+#[instrument(name = "Emit webhook event", skip(data, transaction))]
+pub async fn db_emit_webhook_event(
+    event: WebhookEvent,
+    data: Value,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), Error> {
+    let payload = WebhookPayload {
+        id: Uuid::new_v4(),
+        event,
+        occurred_at: OffsetDateTime::now_utc(),
+        data,
+    };
+    let payload = serde_json::to_value(&payload).map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not serialize webhook payload".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+
+    let query_span = tracing::info_span!("Inserting webhook deliveries", event = event.as_str());
+    sqlx::query!(
+        r#"
+            insert into webhook_deliveries (webhook_id, event, payload)
+            select id, $1, $2 from webhooks where active and $1 = any(events);
+        "#,
+        event.as_str(),
+        payload
+    )
+    .execute(&mut *transaction)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not queue webhook deliveries".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
+
+//take deliveries due to be sent, concurrent senders get different ones.
+//Deliveries of inactive webhooks wait until they are activated again
+#[instrument(name = "Claim webhook deliveries", skip(connection))]
+pub async fn db_claim_deliveries(
+    batch_size: i64,
+    connection: &PgPool,
+) -> Result<Vec<QueuedDelivery>, Error> {
+    let query_span = tracing::info_span!("Claim deliveries");
+    sqlx::query_as!(
+        QueuedDelivery,
+        r#"
+            update webhook_deliveries d set
+                attempts = d.attempts + 1,
+                next_attempt_at = now() + make_interval(secs => $2)
+            from webhooks w
+            where w.id = d.webhook_id and d.id in (
+                select wd.id from webhook_deliveries wd
+                join webhooks wh on wh.id = wd.webhook_id
+                where wh.active and wd.delivered_at is null and wd.failed_at is null
+                    and wd.next_attempt_at <= now()
+                order by wd.next_attempt_at
+                limit $1
+                for update of wd skip locked
+            )
+            returning d.id, w.url, w.secret, d.event, d.payload, d.attempts;
+        "#,
+        batch_size,
+        LEASE_SECONDS
+    )
+    .fetch_all(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not get queued webhook deliveries".into()),
+            ErrorTypes::DbError,
+        )
+    })
+}
+
+#[instrument(name = "Mark delivery as delivered", skip(connection))]
+pub async fn db_delivery_succeeded(
+    delivery_id: Uuid,
+    status: i32,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let query_span = tracing::info_span!("Update delivered webhook", %delivery_id);
+    sqlx::query!(
+        r#"
+            update webhook_deliveries set delivered_at = now(), last_status = $2, last_error = null
+            where id = $1;
+        "#,
+        delivery_id,
+        status
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not update the webhook delivery".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
+
+//the delivery is retried later, or failed when all attempts are used
+#[instrument(name = "Mark delivery as failed", skip(delivery, settings, connection))]
+pub async fn db_delivery_failed(
+    delivery: &QueuedDelivery,
+    status: Option<i32>,
+    error: &str,
+    settings: &OutboxSettings,
+    connection: &PgPool,
+) -> Result<(), Error> {
+    let (delay, failed) = retry_after(delivery.attempts, settings);
+    let query_span = tracing::info_span!("Update failed webhook", delivery_id=%delivery.id);
+    sqlx::query!(
+        r#"
+            update webhook_deliveries set
+                last_status = $2,
+                last_error = $3,
+                next_attempt_at = now() + make_interval(secs => $4),
+                failed_at = case when $5 then now() end
+            where id = $1;
+        "#,
+        delivery.id,
+        status,
+        error,
+        delay,
+        failed
+    )
+    .execute(connection)
+    .instrument(query_span)
+    .await
+    .map_err(|e| {
+        Error::new(
+            Some(e.to_string()),
+            Some("Can not update the webhook delivery".into()),
+            ErrorTypes::DbError,
+        )
+    })?;
+    Ok(())
+}
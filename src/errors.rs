@@ -15,6 +15,10 @@ pub enum ErrorTypes {
     NotFoundError,
     Auth(Auth),
     JwtError,
+    PreconditionFailed,
+    PreconditionRequired,
+    Conflict,
+    MailerError,
 }
 
 #[derive(Debug, Serialize)]
@@ -52,6 +56,10 @@ impl ResponseError for Error {
                 Auth::Authorization => StatusCode::FORBIDDEN,
             },
             ErrorTypes::JwtError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorTypes::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            ErrorTypes::PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+            ErrorTypes::Conflict => StatusCode::CONFLICT,
+            ErrorTypes::MailerError => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
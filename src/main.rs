@@ -5,7 +5,10 @@ pub mod errors;
 pub mod logging;
 pub mod schemas;
 
-use app::{run_app, AvatarClient, Settings};
+use app::{
+    deliver_webhooks_job, purge_deleted_students_job, run_app, send_emails_job, Settings,
+};
+use db::db_sync_current_term;
 use std::net::TcpListener;
 
 #[tokio::main]
@@ -26,8 +29,40 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Can not run migrations");
 
+    //mark the configured term as current
+    match db_sync_current_term(config.academic.current_term.as_deref(), &app_state.connection).await
+    {
+        Ok(false) if config.academic.current_term.is_some() => {
+            tracing::warn!("Current term from the settings doesn't exist yet")
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Can not set current term: {}", e),
+    }
+
+    //hard delete students after the retention period
+    tokio::spawn(purge_deleted_students_job(
+        app_state.connection.clone(),
+        app_state.storage.clone(),
+        config.purge.clone(),
+    ));
+
+    //queued emails are sent in the background
+    let mailer = config.email.build().expect("Invalid email settings");
+    tokio::spawn(send_emails_job(
+        app_state.connection.clone(),
+        mailer,
+        config.email.clone(),
+    ));
+
+    //events are delivered to the webhooks in the background
+    tokio::spawn(deliver_webhooks_job(
+        app_state.connection.clone(),
+        reqwest::Client::new(),
+        config.webhooks.clone(),
+    ));
+
     //creating new client for gravatar API
-    let avatar = AvatarClient::new(config.avatar.base_url, config.avatar.default_img);
+    let avatar = config.avatar.build();
 
     let listener = TcpListener::bind(format!("{}:{}", config.app.host, config.app.port))?;
 
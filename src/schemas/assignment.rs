@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::grade::validate_scores;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Assignment {
+    pub id: Uuid,
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[serde(rename = "termId")]
+    pub term_id: Option<Uuid>,
+    pub title: String,
+    pub description: String,
+    #[serde(rename = "dueAt", with = "time::serde::rfc3339")]
+    pub due_at: OffsetDateTime,
+    #[serde(rename = "maxScore")]
+    pub max_score: f64,
+    pub weight: f64,
+    #[serde(rename = "teacherId")]
+    pub teacher_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+}
+
+//Assignment from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "add_assignment_validation"))]
+pub struct AddAssignment {
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "dueAt", with = "time::serde::rfc3339")]
+    pub due_at: OffsetDateTime,
+    //100 if not provided
+    #[serde(rename = "maxScore")]
+    pub max_score: Option<f64>,
+    //1 if not provided
+    #[validate(range(min = 0.0))]
+    pub weight: Option<f64>,
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "patch_assignment_validation"))]
+pub struct PatchAssignment {
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "dueAt", with = "time::serde::rfc3339::option", default)]
+    pub due_at: Option<OffsetDateTime>,
+    #[serde(rename = "maxScore")]
+    pub max_score: Option<f64>,
+    #[validate(range(min = 0.0))]
+    pub weight: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SubmissionFile {
+    pub id: Uuid,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub size: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Submission {
+    pub id: Uuid,
+    #[serde(rename = "assignmentId")]
+    pub assignment_id: Uuid,
+    #[serde(rename = "studentId")]
+    pub student_id: Uuid,
+    pub content: Option<String>,
+    #[serde(rename = "submittedAt")]
+    pub submitted_at: OffsetDateTime,
+    //submitted after the due date
+    pub late: bool,
+    //gradebook entry of the submission
+    #[serde(rename = "gradeId")]
+    pub grade_id: Option<Uuid>,
+    pub files: Vec<SubmissionFile>,
+}
+
+//file from the multipart form
+pub struct UploadedFile {
+    pub file_name: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl std::fmt::Debug for UploadedFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadedFile")
+            .field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct GradeSubmission {
+    #[validate(range(min = 0.0))]
+    pub score: f64,
+}
+
+fn add_assignment_validation(assignment: &AddAssignment) -> Result<(), ValidationError> {
+    validate_scores(
+        0.0,
+        assignment.max_score.unwrap_or(100.0),
+        assignment.weight,
+    )
+}
+
+fn patch_assignment_validation(assignment: &PatchAssignment) -> Result<(), ValidationError> {
+    validate_scores(
+        0.0,
+        assignment.max_score.unwrap_or(100.0),
+        assignment.weight,
+    )
+}
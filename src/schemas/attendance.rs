@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "attendance_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AttendanceStatus {
+    Present,
+    Absent,
+    Late,
+    Excused,
+}
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct Attendance {
+    pub id: Uuid,
+    #[serde(rename = "studentId")]
+    pub student_id: Uuid,
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[serde(rename = "sessionDate", with = "super::dates")]
+    pub session_date: Date,
+    pub status: AttendanceStatus,
+    pub note: Option<String>,
+    #[serde(rename = "recordedBy")]
+    pub recorded_by: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: OffsetDateTime,
+}
+
+//attendance of the whole class for one session
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct MarkAttendance {
+    #[serde(with = "super::dates")]
+    pub date: Date,
+    #[validate(length(min = 1))]
+    #[validate]
+    pub records: Vec<AttendanceMark>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "note_validation", skip_on_field_errors = true))]
+pub struct AttendanceMark {
+    #[serde(rename = "studentId")]
+    pub student_id: Uuid,
+    pub status: AttendanceStatus,
+    #[validate(length(min = 1, max = 500))]
+    pub note: Option<String>,
+}
+
+//Query parameters of attendance history and rates
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct AttendanceFilter {
+    pub course: Option<String>,
+    #[serde(with = "super::dates::option", default)]
+    pub from: Option<Date>,
+    #[serde(with = "super::dates::option", default)]
+    pub to: Option<Date>,
+}
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct AttendanceRate {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    pub records: i64,
+    pub present: i64,
+    pub absent: i64,
+    pub late: i64,
+    pub excused: i64,
+    //percent of attended (present or late) sessions, excused ones aren't counted
+    pub rate: Option<f64>,
+}
+
+//excused absence must be explained
+fn note_validation(mark: &AttendanceMark) -> Result<(), ValidationError> {
+    if mark.status == AttendanceStatus::Excused && mark.note.is_none() {
+        let mut error = ValidationError::new("Excused absence requires a note");
+        error.add_param("studentId".into(), &mark.student_id);
+        return Err(error);
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use actix_web::HttpRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+//who made the change and in which request
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub actor_id: Option<Uuid>,
+    pub request_id: String,
+}
+
+impl AuditContext {
+    //use request id from X-Request-Id header or generate a new one
+    pub fn new(actor_id: Option<Uuid>, req: &HttpRequest) -> Self {
+        let request_id = req
+            .headers()
+            .get("x-request-id")
+            .and_then(|h| h.to_str().ok())
+            .map(String::from)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        AuditContext {
+            actor_id,
+            request_id,
+        }
+    }
+
+    //changes made by background jobs
+    pub fn system() -> Self {
+        AuditContext {
+            actor_id: None,
+            request_id: "system".into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    #[serde(rename = "actorId")]
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub entity: String,
+    #[serde(rename = "entityId")]
+    pub entity_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub diff: Option<Value>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+}
+
+//Query parameters of the audit log
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct AuditFilter {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
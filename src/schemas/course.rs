@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::student::courses_validation;
+
+//seats of the course in the current term
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CourseCapacity {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    //no limit if empty
+    #[serde(rename = "maxSeats")]
+    pub max_seats: Option<i32>,
+    pub enrolled: i64,
+    //students in the order they get free seats
+    pub waitlist: Vec<Uuid>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct SetCapacity {
+    #[validate(range(min = 1))]
+    #[serde(rename = "maxSeats")]
+    pub max_seats: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "prerequisite_validation", skip_on_field_errors = true))]
+pub struct Prerequisite {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    //weighted average of the course in percents
+    #[validate(range(min = 0.0, max = 100.0))]
+    #[serde(rename = "minGrade")]
+    pub min_grade: f64,
+}
+
+//replaces all prerequisites of the course
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "prerequisites_validation", skip_on_field_errors = true))]
+pub struct SetPrerequisites {
+    #[validate]
+    pub prerequisites: Vec<Prerequisite>,
+}
+
+//prerequisite the student hasn't passed, returned with the validation error
+#[derive(Deserialize, Serialize, Debug)]
+pub struct UnmetPrerequisite {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    pub prerequisite: String,
+    #[serde(rename = "minGrade")]
+    pub min_grade: f64,
+    //none if the student has no grades of the prerequisite
+    pub grade: Option<f64>,
+}
+
+fn prerequisite_validation(prerequisite: &Prerequisite) -> Result<(), ValidationError> {
+    courses_validation(&vec![prerequisite.course_name.clone()])
+}
+
+fn prerequisites_validation(data: &SetPrerequisites) -> Result<(), ValidationError> {
+    let mut names = HashSet::new();
+    if data
+        .prerequisites
+        .iter()
+        .all(|p| names.insert(p.course_name.as_str()))
+    {
+        return Ok(());
+    }
+    Err(ValidationError::new("Prerequisites must be unique"))
+}
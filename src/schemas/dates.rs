@@ -0,0 +1,21 @@
+//dates are sent as "2023-04-26", use with `#[serde(with = "dates")]`
+time::serde::format_description!(date_format, Date, "[year]-[month]-[day]");
+
+//times of day are sent as "08:30"
+time::serde::format_description!(hours_format, Time, "[hour]:[minute]");
+
+pub use date_format::{deserialize, serialize};
+
+//for optional dates, use with `#[serde(with = "dates::option", default)]`
+pub mod option {
+    pub use super::date_format::option::{deserialize, serialize};
+}
+
+//use with `#[serde(with = "dates::hours")]`
+pub mod hours {
+    pub use super::hours_format::{deserialize, serialize};
+
+    pub mod option {
+        pub use super::super::hours_format::option::{deserialize, serialize};
+    }
+}
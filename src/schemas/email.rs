@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+//templates of the outbound emails, the first line of a template is the subject
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTemplate {
+    Welcome,
+    GradePosted,
+    AbsenceAlert,
+}
+
+impl EmailTemplate {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::Welcome => "welcome",
+            EmailTemplate::GradePosted => "grade_posted",
+            EmailTemplate::AbsenceAlert => "absence_alert",
+        }
+    }
+
+    fn source(&self) -> &'static str {
+        match self {
+            EmailTemplate::Welcome => include_str!("../../templates/emails/welcome.txt"),
+            EmailTemplate::GradePosted => include_str!("../../templates/emails/grade_posted.txt"),
+            EmailTemplate::AbsenceAlert => {
+                include_str!("../../templates/emails/absence_alert.txt")
+            }
+        }
+    }
+
+    //replaces `{{name}}` placeholders in one pass, so placeholders in the values stay as they are.
+    //Unknown placeholders are kept. Returns the subject and the body
+    pub fn render(&self, vars: &[(&str, &str)]) -> (String, String) {
+        let mut text = String::new();
+        let mut rest = self.source();
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            text.push_str(&rest[..start]);
+            let name = &rest[start + 2..start + len];
+            match vars.iter().find(|(n, _)| *n == name) {
+                Some((_, value)) => text.push_str(value),
+                None => text.push_str(&rest[start..start + len + 2]),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        text.push_str(rest);
+        let (subject, body) = text.split_once('\n').unwrap_or((text.as_str(), ""));
+        (subject.trim().to_string(), body.trim().to_string())
+    }
+}
+
+//email of the outbox waiting to be sent
+#[derive(Deserialize, Serialize, FromRow, Debug, Clone)]
+pub struct QueuedEmail {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    //including the current one
+    pub attempts: i32,
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::student::courses_validation;
+
+#[derive(Deserialize, Serialize, FromRow, Debug, Clone)]
+pub struct Grade {
+    pub id: Uuid,
+    #[serde(rename = "studentId")]
+    pub student_id: Uuid,
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    pub assessment: String,
+    pub score: f64,
+    #[serde(rename = "maxScore")]
+    pub max_score: f64,
+    pub weight: f64,
+    #[serde(rename = "gradedOn", with = "super::dates")]
+    pub graded_on: Date,
+    #[serde(rename = "teacherId")]
+    pub teacher_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: OffsetDateTime,
+}
+
+//Grade from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "add_grade_validation", skip_on_field_errors = true))]
+pub struct AddGrade {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[validate(length(min = 1, max = 255))]
+    pub assessment: String,
+    #[validate(range(min = 0.0))]
+    pub score: f64,
+    #[serde(rename = "maxScore")]
+    pub max_score: f64,
+    #[validate(range(min = 0.0))]
+    pub weight: Option<f64>,
+    //today if not provided
+    #[serde(rename = "gradedOn", with = "super::dates::option", default)]
+    pub graded_on: Option<Date>,
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct PatchGrade {
+    #[validate(length(min = 1, max = 255))]
+    pub assessment: Option<String>,
+    #[validate(range(min = 0.0))]
+    pub score: Option<f64>,
+    #[serde(rename = "maxScore")]
+    pub max_score: Option<f64>,
+    #[validate(range(min = 0.0))]
+    pub weight: Option<f64>,
+    #[serde(rename = "gradedOn", with = "super::dates::option", default)]
+    pub graded_on: Option<Date>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct CourseAverage {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    //weighted average in percents
+    pub average: f64,
+    #[serde(rename = "gradesCount")]
+    pub grades_count: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default)]
+pub struct GradeSummary {
+    pub courses: Vec<CourseAverage>,
+    //4.0 scale, none if the student has no grades
+    pub gpa: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GradeBook {
+    pub grades: Vec<Grade>,
+    pub summary: GradeSummary,
+}
+
+impl GradeSummary {
+    pub fn from_grades(grades: &[Grade]) -> Self {
+        //course -> (sum of weighted percents, sum of weights, grades count)
+        let mut courses: BTreeMap<&str, (f64, f64, usize)> = BTreeMap::new();
+        for grade in grades {
+            let course = courses.entry(&grade.course_name).or_default();
+            course.0 += grade.score / grade.max_score * grade.weight;
+            course.1 += grade.weight;
+            course.2 += 1;
+        }
+
+        let courses: Vec<CourseAverage> = courses
+            .into_iter()
+            .map(|(name, (weighted, weights, count))| CourseAverage {
+                course_name: name.to_string(),
+                average: round(weighted / weights * 100.0),
+                grades_count: count,
+            })
+            .collect();
+
+        let gpa = if courses.is_empty() {
+            None
+        } else {
+            let points: f64 = courses.iter().map(|c| gpa_points(c.average)).sum();
+            Some(round(points / courses.len() as f64))
+        };
+
+        GradeSummary { courses, gpa }
+    }
+}
+
+//grade points of the course average on the 4.0 scale
+fn gpa_points(average: f64) -> f64 {
+    match average {
+        a if a >= 90.0 => 4.0,
+        a if a >= 80.0 => 3.0,
+        a if a >= 70.0 => 2.0,
+        a if a >= 60.0 => 1.0,
+        _ => 0.0,
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn add_grade_validation(grade: &AddGrade) -> Result<(), ValidationError> {
+    courses_validation(&vec![grade.course_name.clone()])?;
+    validate_scores(grade.score, grade.max_score, grade.weight)
+}
+
+pub(crate) fn validate_scores(
+    score: f64,
+    max_score: f64,
+    weight: Option<f64>,
+) -> Result<(), ValidationError> {
+    if max_score <= 0.0 || score > max_score {
+        let mut error = ValidationError::new("Invalid score");
+        error.add_param("score".into(), &score);
+        error.add_param("maxScore".into(), &max_score);
+        return Err(error);
+    }
+    if weight == Some(0.0) {
+        return Err(ValidationError::new("Weight must be positive"));
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+//regex for academic year, e.g. "2023/2024"
+lazy_static! {
+    pub(crate) static ref ACADEMIC_YEAR_REGEX: Regex =
+        Regex::new(r"^\d{4}/\d{4}$").expect("Ivalid regular expression");
+}
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct ClassGroup {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "academicYear")]
+    pub academic_year: String,
+    #[serde(rename = "homeroomTeacherId")]
+    pub homeroom_teacher_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+    #[serde(rename = "studentsCount")]
+    pub students_count: i64,
+}
+
+//Group from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct AddGroup {
+    #[validate(length(min = 1, max = 32))]
+    pub name: String,
+    #[validate(regex(path = "ACADEMIC_YEAR_REGEX", message = "Must be like 2023/2024"))]
+    #[serde(rename = "academicYear")]
+    pub academic_year: String,
+    #[serde(rename = "homeroomTeacherId")]
+    pub homeroom_teacher_id: Option<Uuid>,
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct PatchGroup {
+    #[validate(length(min = 1, max = 32))]
+    pub name: Option<String>,
+    #[validate(regex(path = "ACADEMIC_YEAR_REGEX", message = "Must be like 2023/2024"))]
+    #[serde(rename = "academicYear")]
+    pub academic_year: Option<String>,
+    #[serde(rename = "homeroomTeacherId")]
+    pub homeroom_teacher_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct GroupMembers {
+    #[validate(length(min = 1))]
+    pub students: Vec<Uuid>,
+}
+
+//Query parameters of the groups list
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct GroupFilter {
+    pub academic_year: Option<String>,
+}
@@ -0,0 +1,101 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::student::FULLNAME_REGEX;
+
+//regex for phone numbers, e.g. "+380 (67) 123-45-67"
+lazy_static! {
+    pub(crate) static ref PHONE_REGEX: Regex =
+        Regex::new(r"^\+?[0-9][0-9 ()-]{4,18}[0-9]$").expect("Ivalid regular expression");
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "guardian_relationship", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Relationship {
+    Mother,
+    Father,
+    Grandparent,
+    Sibling,
+    Guardian,
+    Other,
+}
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct Guardian {
+    pub id: Uuid,
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    pub relationship: Relationship,
+    pub phone: Option<String>,
+    pub email: String,
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+}
+
+//guardian of a particular student
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct StudentGuardian {
+    pub id: Uuid,
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    pub relationship: Relationship,
+    pub phone: Option<String>,
+    pub email: String,
+    #[serde(rename = "isPrimary")]
+    pub is_primary: bool,
+}
+
+//Guardian from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct AddGuardian {
+    #[validate(regex(
+        path = "FULLNAME_REGEX",
+        message = "Must contais only letters and space!"
+    ))]
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    pub relationship: Relationship,
+    #[validate(regex(path = "PHONE_REGEX", message = "Invalid phone number"))]
+    pub phone: Option<String>,
+    #[validate(email)]
+    pub email: String,
+    //link to the parent's login account
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct PatchGuardian {
+    #[validate(regex(
+        path = "FULLNAME_REGEX",
+        message = "Must contais only letters and space!"
+    ))]
+    #[serde(rename = "fullName")]
+    pub full_name: Option<String>,
+    pub relationship: Option<Relationship>,
+    #[validate(regex(path = "PHONE_REGEX", message = "Invalid phone number"))]
+    pub phone: Option<String>,
+    #[validate(email)]
+    pub email: Option<String>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+}
+
+//link a guardian to the student
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LinkGuardian {
+    #[serde(rename = "guardianId")]
+    pub guardian_id: Uuid,
+    //the previous primary contact of the student is replaced
+    #[serde(rename = "isPrimary", default)]
+    pub is_primary: bool,
+}
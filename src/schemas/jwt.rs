@@ -1,6 +1,7 @@
 use crate::{
     app::TokenConfig,
     errors::{Auth, Error, ErrorTypes},
+    schemas::Role,
 };
 use actix_web::{HttpMessage, HttpRequest};
 
@@ -15,14 +16,15 @@ use tracing::instrument;
 pub struct TokenClaims {
     pub sub: String,
     pub exp: usize,
+    pub role: Role,
 }
 
 impl TokenClaims {
-    pub fn new(sub: String, time: Duration) -> Self {
+    pub fn new(sub: String, role: Role, time: Duration) -> Self {
         //create token
         let now = OffsetDateTime::now_utc();
         let exp = (now + time).unix_timestamp() as usize;
-        TokenClaims { sub, exp }
+        TokenClaims { sub, exp, role }
     }
 }
 pub struct TokenSettings {
@@ -161,6 +163,6 @@ impl Jwt {
             error_type: ErrorTypes::Auth(Auth::Authentication),
         })?;
 
-        Ok(user_id.clone())
+        Ok(*user_id)
     }
 }
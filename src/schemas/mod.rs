@@ -1,7 +1,38 @@
+pub mod assignment;
+pub mod attendance;
+pub mod audit;
+pub mod course;
+pub mod dates;
+pub mod email;
+pub mod grade;
+pub mod group;
+pub mod guardian;
 pub mod jwt;
+pub mod report;
+pub mod schedule;
+pub mod stats;
+pub mod search;
 pub mod student;
+pub mod teacher;
+pub mod term;
 pub mod user;
+pub mod webhook;
 
+pub use assignment::*;
+pub use attendance::*;
+pub use audit::*;
+pub use course::*;
+pub use email::*;
+pub use grade::*;
+pub use group::*;
+pub use guardian::*;
 pub use jwt::*;
+pub use report::*;
+pub use schedule::*;
+pub use search::*;
+pub use stats::*;
 pub use student::*;
+pub use teacher::*;
+pub use term::*;
 pub use user::*;
+pub use webhook::*;
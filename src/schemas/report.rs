@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use validator::{Validate, ValidationError};
+
+use super::{student::courses_validation, AttendanceRate, FullStudent, GradeSummary, Term};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ReportComment {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    pub comment: String,
+    #[serde(rename = "teacherName")]
+    pub teacher_name: Option<String>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: OffsetDateTime,
+}
+
+//comment of the course for the current term, replaces the previous one
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "comment_validation", skip_on_field_errors = true))]
+pub struct AddReportComment {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[validate(length(min = 1, max = 2000))]
+    pub comment: String,
+}
+
+//grades and attendance of the current term, everything without a term
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ReportCard {
+    pub student: FullStudent,
+    pub term: Option<Term>,
+    pub grades: GradeSummary,
+    pub attendance: Vec<AttendanceRate>,
+    //of all courses, none without attendance records
+    #[serde(rename = "attendanceRate")]
+    pub attendance_rate: Option<f64>,
+    pub comments: Vec<ReportComment>,
+}
+
+impl ReportCard {
+    //percent of attended sessions, excused ones aren't counted
+    pub fn total_attendance(rates: &[AttendanceRate]) -> Option<f64> {
+        let attended: i64 = rates.iter().map(|r| r.present + r.late).sum();
+        let counted: i64 = rates.iter().map(|r| r.records - r.excused).sum();
+        if counted == 0 {
+            return None;
+        }
+        Some((attended as f64 / counted as f64 * 10000.0).round() / 100.0)
+    }
+}
+
+fn comment_validation(comment: &AddReportComment) -> Result<(), ValidationError> {
+    courses_validation(&vec![comment.course_name.clone()])
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::{OffsetDateTime, Time};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::student::courses_validation;
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct ScheduleSlot {
+    pub id: Uuid,
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[serde(rename = "termId")]
+    pub term_id: Option<Uuid>,
+    //ISO day of week, 1 is Monday
+    #[serde(rename = "dayOfWeek")]
+    pub day_of_week: i16,
+    #[serde(rename = "startsAt", with = "super::dates::hours")]
+    pub starts_at: Time,
+    #[serde(rename = "endsAt", with = "super::dates::hours")]
+    pub ends_at: Time,
+    pub room: String,
+    #[serde(rename = "teacherId")]
+    pub teacher_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+}
+
+//Slot from Json with validation, it's added to the current term
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "slot_validation", skip_on_field_errors = true))]
+pub struct AddSlot {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[validate(range(min = 1, max = 7))]
+    #[serde(rename = "dayOfWeek")]
+    pub day_of_week: i16,
+    #[serde(rename = "startsAt", with = "super::dates::hours")]
+    pub starts_at: Time,
+    #[serde(rename = "endsAt", with = "super::dates::hours")]
+    pub ends_at: Time,
+    #[validate(length(min = 1, max = 32))]
+    pub room: String,
+    #[serde(rename = "teacherId")]
+    pub teacher_id: Option<Uuid>,
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct PatchSlot {
+    #[validate(range(min = 1, max = 7))]
+    #[serde(rename = "dayOfWeek")]
+    pub day_of_week: Option<i16>,
+    #[serde(rename = "startsAt", with = "super::dates::hours::option", default)]
+    pub starts_at: Option<Time>,
+    #[serde(rename = "endsAt", with = "super::dates::hours::option", default)]
+    pub ends_at: Option<Time>,
+    #[validate(length(min = 1, max = 32))]
+    pub room: Option<String>,
+    #[serde(rename = "teacherId")]
+    pub teacher_id: Option<Uuid>,
+}
+
+impl AddSlot {
+    //slot after applying the patch
+    pub fn patched(slot: ScheduleSlot, data: PatchSlot) -> Result<Self, ValidationError> {
+        let slot = AddSlot {
+            course_name: slot.course_name,
+            day_of_week: data.day_of_week.unwrap_or(slot.day_of_week),
+            starts_at: data.starts_at.unwrap_or(slot.starts_at),
+            ends_at: data.ends_at.unwrap_or(slot.ends_at),
+            room: data.room.unwrap_or(slot.room),
+            teacher_id: data.teacher_id.or(slot.teacher_id),
+        };
+        slot_validation(&slot)?;
+        Ok(slot)
+    }
+}
+
+fn slot_validation(slot: &AddSlot) -> Result<(), ValidationError> {
+    courses_validation(&vec![slot.course_name.clone()])?;
+    if slot.starts_at >= slot.ends_at {
+        return Err(ValidationError::new("Slot must start before the end"));
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+//Query parameters of the students search
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct SearchQuery {
+    #[validate(length(min = 2, max = 100))]
+    pub q: String,
+    #[validate(range(min = 1, max = 100))]
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SearchResult {
+    pub id: Uuid,
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    pub email: String,
+    pub age: i32,
+    pub courses: Vec<String>,
+    //similarity of the best matching field, from 0 to 1
+    pub rank: f32,
+    pub highlights: SearchHighlights,
+}
+
+//matched fields with the matched words wrapped in `<mark>`
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SearchHighlights {
+    #[serde(rename = "fullName", skip_serializing_if = "Option::is_none", default)]
+    pub full_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub courses: Vec<String>,
+}
+
+impl SearchHighlights {
+    pub fn new(query: &str, full_name: &str, email: &str, courses: &[String]) -> Self {
+        let terms: Vec<String> = query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        SearchHighlights {
+            full_name: highlight(full_name, &terms),
+            email: highlight(email, &terms),
+            courses: courses
+                .iter()
+                .filter_map(|course| highlight(course, &terms))
+                .collect(),
+        }
+    }
+}
+
+//wraps words of the text matching any of the terms, None if nothing matches.
+//The text is escaped, only the marks are HTML
+fn highlight(text: &str, terms: &[String]) -> Option<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut matched = false;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let start = rest.find(char::is_alphanumeric).unwrap_or(rest.len());
+        escape_html(&rest[..start], &mut result);
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        if !word.is_empty() && terms.iter().any(|t| word_matches(word, t)) {
+            matched = true;
+            result.push_str("<mark>");
+            escape_html(word, &mut result);
+            result.push_str("</mark>");
+        } else {
+            escape_html(word, &mut result);
+        }
+        rest = &rest[end..];
+    }
+    matched.then_some(result)
+}
+
+fn escape_html(text: &str, result: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+}
+
+//the word contains the term or its beginning differs by a typo
+fn word_matches(word: &str, term: &str) -> bool {
+    let word = word.to_lowercase();
+    if word.contains(term) {
+        return true;
+    }
+    let term_len = term.chars().count();
+    if term_len < 4 {
+        return false;
+    }
+    let typos = if term_len < 8 { 1 } else { 2 };
+    //the term can be the beginning of the word, with a typo it may be a char longer or shorter
+    (term_len.saturating_sub(typos)..=term_len + typos).any(|len| {
+        let prefix: String = word.chars().take(len).collect();
+        prefix.chars().count() == len && levenshtein(&prefix, term) <= typos
+    })
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(row[j + 1])
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+//aggregates of not deleted students and their courses of the current term
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Stats {
+    #[serde(rename = "totalStudents")]
+    pub total_students: i64,
+    #[serde(rename = "registrationsPerMonth")]
+    pub registrations_per_month: Vec<MonthRegistrations>,
+    #[serde(rename = "ageDistribution")]
+    pub age_distribution: Vec<AgeGroup>,
+    //sorted by the number of students, the most popular first
+    pub courses: Vec<CourseEnrollment>,
+    //courses with the most students, several on a tie
+    #[serde(rename = "mostPopular")]
+    pub most_popular: Vec<CourseEnrollment>,
+    #[serde(rename = "leastPopular")]
+    pub least_popular: Vec<CourseEnrollment>,
+    #[serde(rename = "generatedAt", with = "time::serde::rfc3339")]
+    pub generated_at: OffsetDateTime,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MonthRegistrations {
+    //e.g. `2023-05`
+    pub month: String,
+    pub count: i64,
+}
+
+//five years wide groups
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AgeGroup {
+    pub from: i32,
+    pub to: i32,
+    pub count: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CourseEnrollment {
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    pub students: i64,
+}
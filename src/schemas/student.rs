@@ -1,12 +1,14 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use time::OffsetDateTime;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 use uuid::Uuid;
 
 use lazy_static::lazy_static;
 use regex::Regex;
 use validator::{Validate, ValidationError};
 
+use super::grade::GradeSummary;
+
 #[derive(Deserialize, Serialize, FromRow, Debug)]
 pub struct Student {
     pub id: Uuid,
@@ -16,14 +18,40 @@ pub struct Student {
     pub age: i32,
     pub img: String,
     pub registration_date: OffsetDateTime,
+    pub version: i32,
+    pub deleted_at: Option<OffsetDateTime>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: OffsetDateTime,
+}
+
+impl Student {
+    pub fn with_courses(self, courses: Vec<String>) -> FullStudent {
+        FullStudent {
+            id: self.id,
+            full_name: self.full_name,
+            email: self.email,
+            age: self.age,
+            img: self.img,
+            registration_date: self.registration_date,
+            courses,
+            version: self.version,
+            deleted_at: self.deleted_at,
+            created_by: self.created_by,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+            grades: None,
+            waitlist: Vec::new(),
+        }
+    }
 }
 
 //regex for name falidation. Must contains letters and space
 lazy_static! {
-    static ref FULLNAME_REGEX: Regex =
+    pub(crate) static ref FULLNAME_REGEX: Regex =
         Regex::new(r"\b\w{3,}\D\b\s{1}\b\w{3,}\D\b$").expect("Ivalid regular expression");
     static ref COURSES_REGEX: Regex =
-        Regex::new(r"\b[a-zA-Z]{2,}[.\\\d]{0,}\b").expect("Ivalid regular expression");
+        Regex::new(r"^[a-zA-Z]{2,}[.\\\d]{0,}$").expect("Ivalid regular expression");
 }
 
 //User from Json with validation
@@ -41,6 +69,9 @@ pub struct AddStudent {
     pub age: i32,
     #[validate(custom = "courses_validation")]
     pub courses: Vec<String>,
+    //join the waitlist of full courses instead of failing
+    #[serde(default)]
+    pub waitlist: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -54,6 +85,21 @@ pub struct FullStudent {
     #[serde(rename = "registrationDate")]
     pub registration_date: OffsetDateTime,
     pub courses: Vec<String>,
+    pub version: i32,
+    #[serde(rename = "deletedAt", skip_serializing_if = "Option::is_none", default)]
+    pub deleted_at: Option<OffsetDateTime>,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<Uuid>,
+    #[serde(rename = "updatedBy")]
+    pub updated_by: Option<Uuid>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: OffsetDateTime,
+    //only with `?include=grades`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub grades: Option<GradeSummary>,
+    //full courses the student waits for
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub waitlist: Vec<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Validate)]
@@ -64,9 +110,292 @@ pub struct EditStudent {
     pub age: i32,
     #[validate(custom = "courses_validation")]
     pub courses: Vec<String>,
+    #[serde(default)]
+    pub waitlist: bool,
+}
+
+//Query parameters of the students list
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct StudentFilter {
+    #[serde(default)]
+    pub include_deleted: bool,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
+    //only students registered by the logged user
+    #[serde(default)]
+    pub mine: bool,
+    //name of the class group, e.g. `10-B`. Names repeat every academic year, the group of
+    //the current academic year is used, or the latest one when no term is current
+    pub group: Option<String>,
+    pub include: Option<String>,
+}
+
+impl StudentFilter {
+    pub fn include_grades(&self) -> bool {
+        includes(&self.include, "grades")
+    }
+}
+
+//Query parameters of a single student
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct StudentQuery {
+    //comma separated list of additional data, e.g. `grades`
+    pub include: Option<String>,
+}
+
+impl StudentQuery {
+    pub fn include_grades(&self) -> bool {
+        includes(&self.include, "grades")
+    }
+}
+
+fn includes(include: &Option<String>, part: &str) -> bool {
+    include
+        .as_deref()
+        .map(|i| i.split(',').any(|p| p.trim() == part))
+        .unwrap_or(false)
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct PatchStudent {
+    #[validate(regex(
+        path = "FULLNAME_REGEX",
+        message = "Must contais only letters and space!"
+    ))]
+    #[serde(rename = "fullName")]
+    pub full_name: Option<String>,
+    #[validate(email)]
+    pub email: Option<String>,
+    #[validate(range(min = 16, max = 120))]
+    pub age: Option<i32>,
+    #[validate(custom = "courses_validation")]
+    #[serde(rename = "addCourses")]
+    pub add_courses: Option<Vec<String>>,
+    #[serde(rename = "removeCourses")]
+    pub remove_courses: Option<Vec<String>>,
+    #[serde(default)]
+    pub waitlist: bool,
+}
+
+//uploaded avatar, served instead of the Gravatar image
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct StudentAvatar {
+    #[serde(rename = "studentId")]
+    pub student_id: Uuid,
+    pub etag: String,
+    #[serde(rename = "updatedAt", with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    //sizes of the thumbnails in pixels
+    #[serde(default)]
+    pub sizes: Vec<u32>,
+}
+
+//widths and heights of the avatar thumbnails
+pub const AVATAR_SIZES: [u32; 3] = [64, 128, 256];
+
+//Query parameters of the avatar
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct AvatarQuery {
+    #[validate(custom = "avatar_size_validation")]
+    #[serde(default = "default_avatar_size")]
+    pub size: u32,
+}
+
+fn default_avatar_size() -> u32 {
+    128
+}
+
+fn avatar_size_validation(size: u32) -> Result<(), ValidationError> {
+    if !AVATAR_SIZES.contains(&size) {
+        let mut error = ValidationError::new("Invalid avatar size");
+        error.add_param("sizes".into(), &AVATAR_SIZES);
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Xlsx,
+}
+
+//Query parameters of the students export, filters are the same as of the list
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+}
+
+//student's row of the export
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportedStudent {
+    pub id: Uuid,
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    pub email: String,
+    pub age: i32,
+    //courses of the current term
+    pub courses: Vec<String>,
+    #[serde(rename = "registrationDate", with = "time::serde::rfc3339")]
+    pub registration_date: OffsetDateTime,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<Uuid>,
+    #[serde(rename = "updatedBy")]
+    pub updated_by: Option<Uuid>,
+    #[serde(rename = "updatedAt", with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    #[serde(
+        rename = "deletedAt",
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+impl ExportedStudent {
+    //columns of the CSV and XLSX files, the first ones can be imported back
+    pub const HEADERS: [&'static str; 10] = [
+        "fullName",
+        "email",
+        "age",
+        "courses",
+        "id",
+        "registrationDate",
+        "createdBy",
+        "updatedBy",
+        "updatedAt",
+        "deletedAt",
+    ];
+
+    pub fn to_record(&self) -> Vec<String> {
+        let date = |d: &OffsetDateTime| d.format(&Rfc3339).unwrap_or_default();
+        let id = |id: &Option<Uuid>| id.map(|id| id.to_string()).unwrap_or_default();
+        vec![
+            self.full_name.clone(),
+            self.email.clone(),
+            self.age.to_string(),
+            self.courses.join(";"),
+            self.id.to_string(),
+            date(&self.registration_date),
+            id(&self.created_by),
+            id(&self.updated_by),
+            date(&self.updated_at),
+            self.deleted_at.as_ref().map(date).unwrap_or_default(),
+        ]
+    }
+}
+
+//Query parameters of the students import
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ImportQuery {
+    //only validate the rows, nothing is saved
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+//row of the imported CSV, courses are separated by `;`
+#[derive(Deserialize, Debug)]
+pub struct StudentRecord {
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    pub email: String,
+    pub age: i32,
+    #[serde(default)]
+    pub courses: String,
+}
+
+impl From<StudentRecord> for AddStudent {
+    fn from(record: StudentRecord) -> Self {
+        let mut courses: Vec<String> = Vec::new();
+        for course in record.courses.split(';').map(str::trim) {
+            if !course.is_empty() && !courses.iter().any(|c| c == course) {
+                courses.push(course.to_string());
+            }
+        }
+        AddStudent {
+            full_name: record.full_name,
+            email: record.email,
+            age: record.age,
+            courses,
+            waitlist: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ImportRowError {
+    //line of the CSV file, the header is the first line
+    pub row: u64,
+    //validation errors of the fields or the error message
+    pub errors: serde_json::Value,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ImportReport {
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    pub total: usize,
+    pub valid: usize,
+    //always 0 for the dry run
+    pub imported: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+//valid student with its line in the CSV file
+pub type ImportRow = (u64, AddStudent);
+
+//returns valid rows with their line numbers and errors of the other rows
+pub fn parse_students_csv(
+    data: &[u8],
+) -> Result<(Vec<ImportRow>, Vec<ImportRowError>), csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader.headers()?.clone();
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(ImportRowError {
+                    row: e.position().map(|p| p.line()).unwrap_or_default(),
+                    errors: serde_json::Value::String(e.to_string()),
+                });
+                continue;
+            }
+        };
+        let row = record.position().map(|p| p.line()).unwrap_or_default();
+
+        let student = match record.deserialize::<StudentRecord>(Some(&headers)) {
+            Ok(student) => AddStudent::from(student),
+            Err(e) => {
+                errors.push(ImportRowError {
+                    row,
+                    errors: serde_json::Value::String(e.to_string()),
+                });
+                continue;
+            }
+        };
+        if let Err(e) = student.validate() {
+            errors.push(ImportRowError {
+                row,
+                errors: serde_json::to_value(&e).unwrap_or_default(),
+            });
+            continue;
+        }
+        rows.push((row, student));
+    }
+
+    Ok((rows, errors))
 }
 
-fn courses_validation(courses: &Vec<String>) -> Result<(), ValidationError> {
+pub(crate) fn courses_validation(courses: &Vec<String>) -> Result<(), ValidationError> {
     for c in courses {
         if !COURSES_REGEX.is_match(c) {
             let mut error = ValidationError::new("Invalid courses data");
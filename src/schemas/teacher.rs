@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::student::{courses_validation, FULLNAME_REGEX};
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct Teacher {
+    pub id: Uuid,
+    pub full_name: String,
+    pub email: String,
+    pub subjects: Vec<String>,
+    pub user_id: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+}
+
+impl Teacher {
+    pub fn with_courses(self, courses: Vec<String>) -> FullTeacher {
+        FullTeacher {
+            id: self.id,
+            full_name: self.full_name,
+            email: self.email,
+            subjects: self.subjects,
+            user_id: self.user_id,
+            created_at: self.created_at,
+            courses,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FullTeacher {
+    pub id: Uuid,
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    pub email: String,
+    pub subjects: Vec<String>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+    pub courses: Vec<String>,
+}
+
+//Teacher from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct AddTeacher {
+    #[validate(regex(
+        path = "FULLNAME_REGEX",
+        message = "Must contais only letters and space!"
+    ))]
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+    #[validate(email)]
+    pub email: String,
+    #[validate(custom = "courses_validation")]
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    //link to the teacher's login account
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct PatchTeacher {
+    #[validate(regex(
+        path = "FULLNAME_REGEX",
+        message = "Must contais only letters and space!"
+    ))]
+    #[serde(rename = "fullName")]
+    pub full_name: Option<String>,
+    #[validate(email)]
+    pub email: Option<String>,
+    #[validate(custom = "courses_validation")]
+    pub subjects: Option<Vec<String>>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct TeacherCourses {
+    #[validate(custom = "courses_validation")]
+    pub courses: Vec<String>,
+}
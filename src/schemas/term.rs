@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::Date;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::group::ACADEMIC_YEAR_REGEX;
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct AcademicYear {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "startsOn", with = "super::dates")]
+    pub starts_on: Date,
+    #[serde(rename = "endsOn", with = "super::dates")]
+    pub ends_on: Date,
+}
+
+impl AcademicYear {
+    pub fn with_terms(self, terms: Vec<Term>) -> FullAcademicYear {
+        FullAcademicYear {
+            id: self.id,
+            name: self.name,
+            starts_on: self.starts_on,
+            ends_on: self.ends_on,
+            terms,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct FullAcademicYear {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "startsOn", with = "super::dates")]
+    pub starts_on: Date,
+    #[serde(rename = "endsOn", with = "super::dates")]
+    pub ends_on: Date,
+    pub terms: Vec<Term>,
+}
+
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct Term {
+    pub id: Uuid,
+    #[serde(rename = "academicYearId")]
+    pub academic_year_id: Uuid,
+    pub code: String,
+    pub name: String,
+    #[serde(rename = "startsOn", with = "super::dates")]
+    pub starts_on: Date,
+    #[serde(rename = "endsOn", with = "super::dates")]
+    pub ends_on: Date,
+    #[serde(rename = "isCurrent")]
+    pub is_current: bool,
+}
+
+//Academic year from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "year_dates_validation", skip_on_field_errors = true))]
+pub struct AddAcademicYear {
+    #[validate(regex(path = "ACADEMIC_YEAR_REGEX", message = "Must be like 2023/2024"))]
+    pub name: String,
+    #[serde(rename = "startsOn", with = "super::dates")]
+    pub starts_on: Date,
+    #[serde(rename = "endsOn", with = "super::dates")]
+    pub ends_on: Date,
+}
+
+//Term from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[validate(schema(function = "term_dates_validation", skip_on_field_errors = true))]
+pub struct AddTerm {
+    //unique code used in the settings and queries, e.g. "2023-fall"
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    #[serde(rename = "startsOn", with = "super::dates")]
+    pub starts_on: Date,
+    #[serde(rename = "endsOn", with = "super::dates")]
+    pub ends_on: Date,
+}
+
+//Query parameters of student's courses
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct TermQuery {
+    //term code, the current term if not provided
+    pub term: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TermCourses {
+    //none for enrollments without a term
+    pub term: Option<String>,
+    pub courses: Vec<String>,
+}
+
+fn year_dates_validation(year: &AddAcademicYear) -> Result<(), ValidationError> {
+    dates_validation(year.starts_on, year.ends_on)
+}
+
+fn term_dates_validation(term: &AddTerm) -> Result<(), ValidationError> {
+    dates_validation(term.starts_on, term.ends_on)
+}
+
+fn dates_validation(starts_on: Date, ends_on: Date) -> Result<(), ValidationError> {
+    if starts_on >= ends_on {
+        return Err(ValidationError::new("Must start before the end"));
+    }
+    Ok(())
+}
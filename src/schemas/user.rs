@@ -11,6 +11,17 @@ pub struct User {
     pub password_hash: String,
     #[serde(rename = "createdAt")]
     pub created_at: Option<OffsetDateTime>,
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Teacher,
+    Parent,
+    Student,
 }
 
 #[derive(Debug, Deserialize, Serialize, Validate)]
@@ -30,3 +41,23 @@ pub struct LoginUser {
     #[validate(length(min = 8))]
     pub password: String,
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangeRole {
+    pub role: Role,
+}
+
+//login account of the student
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StudentAccount {
+    #[serde(rename = "studentId")]
+    pub student_id: uuid::Uuid,
+    #[serde(rename = "userId")]
+    pub user_id: uuid::Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LinkStudent {
+    #[serde(rename = "studentId")]
+    pub student_id: uuid::Uuid,
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    #[serde(rename = "student.created")]
+    StudentCreated,
+    #[serde(rename = "student.updated")]
+    StudentUpdated,
+    #[serde(rename = "student.deleted")]
+    StudentDeleted,
+    #[serde(rename = "user.registered")]
+    UserRegistered,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::StudentCreated => "student.created",
+            WebhookEvent::StudentUpdated => "student.updated",
+            WebhookEvent::StudentDeleted => "student.deleted",
+            WebhookEvent::UserRegistered => "user.registered",
+        }
+    }
+}
+
+//registered endpoint, the secret is only returned when the webhook is created
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NewWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+//Webhook from Json with validation
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct AddWebhook {
+    #[validate(url)]
+    pub url: String,
+    #[validate(length(min = 1))]
+    pub events: Vec<WebhookEvent>,
+    //generated if empty
+    #[validate(length(min = 16, max = 255))]
+    pub secret: Option<String>,
+}
+
+//Partial update, only provided fields are changed
+#[derive(Deserialize, Serialize, Debug, Validate)]
+pub struct PatchWebhook {
+    #[validate(url)]
+    pub url: Option<String>,
+    #[validate(length(min = 1))]
+    pub events: Option<Vec<WebhookEvent>>,
+    pub active: Option<bool>,
+    #[validate(length(min = 16, max = 255))]
+    pub secret: Option<String>,
+}
+
+//body of the webhook requests
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WebhookPayload {
+    //the same for all webhooks of the event
+    pub id: Uuid,
+    pub event: WebhookEvent,
+    #[serde(rename = "occurredAt", with = "time::serde::rfc3339")]
+    pub occurred_at: OffsetDateTime,
+    pub data: Value,
+}
+
+//attempts to deliver an event to the webhook
+#[derive(Deserialize, Serialize, FromRow, Debug)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    #[serde(rename = "webhookId")]
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: Value,
+    pub attempts: i32,
+    //HTTP status of the last attempt, empty when the endpoint couldn't be reached
+    #[serde(rename = "lastStatus")]
+    pub last_status: Option<i32>,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    #[serde(rename = "nextAttemptAt")]
+    pub next_attempt_at: OffsetDateTime,
+    #[serde(rename = "deliveredAt")]
+    pub delivered_at: Option<OffsetDateTime>,
+    #[serde(rename = "failedAt")]
+    pub failed_at: Option<OffsetDateTime>,
+    #[serde(rename = "createdAt")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+//Query parameters of the delivery log
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct DeliveryFilter {
+    pub status: Option<DeliveryStatus>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+//delivery claimed by the sender
+#[derive(Deserialize, Serialize, FromRow, Debug, Clone)]
+pub struct QueuedDelivery {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub event: String,
+    pub payload: Value,
+    //including the current one
+    pub attempts: i32,
+}
@@ -0,0 +1,213 @@
+use fake::{Fake, Faker};
+use reqwest::multipart::{Form, Part};
+use sqlx::PgPool;
+use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime};
+use zero2prod::schemas::{Assignment, FullStudent, GradeBook, Submission};
+
+use crate::{
+    client_with_role, client_with_role_and_id,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+fn submission_form() -> Result<Form, reqwest::Error> {
+    Ok(Form::new()
+        .text("content", "My answers")
+        .part(
+            "files",
+            Part::bytes(b"essay text".to_vec())
+                .file_name("essay.txt")
+                .mime_str("text/plain")?,
+        )
+        .part(
+            "files",
+            Part::bytes(b"<script>alert(1)</script>".to_vec())
+                .file_name("page.html")
+                .mime_str("text/html")?,
+        ))
+}
+
+#[sqlx::test]
+async fn assignment_submission_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let teacher = client_with_role(&address, &pool, "teacher").await;
+    let (student_client, student_user_id) =
+        client_with_role_and_id(&address, &pool, "student").await;
+
+    let mut students = Vec::new();
+    for _ in 0..2 {
+        let mut student: FakeStudent = Faker.fake();
+        student.courses = vec!["Math".into()];
+        let student = send_post_request(&admin, &student, format!("{}/students", address))
+            .await?
+            .json::<FullStudent>()
+            .await?;
+        students.push(student);
+    }
+
+    let response = admin
+        .put(format!("{}/users/{}/student", address, student_user_id))
+        .json(&serde_json::json!({"studentId": students[0].id}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let mut assignments = Vec::new();
+    for days in [7, -1] {
+        let due_at = (OffsetDateTime::now_utc() + Duration::days(days))
+            .format(&Rfc3339)
+            .unwrap();
+        let response = send_post_request(
+            &teacher,
+            &serde_json::json!({"title": format!("Homework {}", days), "dueAt": due_at, "maxScore": 10.0}),
+            format!("{}/courses/Math/assignments", address),
+        )
+        .await?;
+        assert!(response.status().is_success());
+        assignments.push(response.json::<Assignment>().await?);
+    }
+    let (open, overdue) = (&assignments[0], &assignments[1]);
+
+    //students can't publish assignments
+    let response = send_post_request(
+        &student_client,
+        &serde_json::json!({"title": "Homework", "dueAt": "2023-06-01T10:00:00Z"}),
+        format!("{}/courses/Math/assignments", address),
+    )
+    .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    //the student can submit only own work
+    let response = student_client
+        .post(format!(
+            "{}/assignments/{}/submissions/{}",
+            address, open.id, students[1].id
+        ))
+        .multipart(submission_form()?)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let submission_uri = format!(
+        "{}/assignments/{}/submissions/{}",
+        address, open.id, students[0].id
+    );
+
+    //the number of files is limited
+    let mut form = Form::new();
+    for i in 0..11 {
+        form = form.part(
+            "files",
+            Part::bytes(b"page".to_vec()).file_name(format!("page{}.txt", i)),
+        );
+    }
+    let response = student_client
+        .post(submission_uri.clone())
+        .multipart(form)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = student_client
+        .post(submission_uri.clone())
+        .multipart(submission_form()?)
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let submission = response.json::<Submission>().await?;
+    assert!(!submission.late);
+    assert_eq!(submission.content.as_deref(), Some("My answers"));
+    assert_eq!(submission.files.len(), 2);
+
+    //only known content types are sent as they were uploaded
+    for (file_name, content_type) in [
+        ("essay.txt", "text/plain"),
+        ("page.html", "application/octet-stream"),
+    ] {
+        let file = submission
+            .files
+            .iter()
+            .find(|f| f.file_name == file_name)
+            .unwrap();
+        let response = teacher
+            .get(format!("{}/submissions/files/{}", address, file.id))
+            .send()
+            .await?;
+        assert!(response.status().is_success());
+        assert_eq!(response.headers()["content-type"], content_type);
+    }
+    let essay = submission
+        .files
+        .iter()
+        .find(|f| f.file_name == "essay.txt")
+        .unwrap();
+    let response = teacher
+        .get(format!("{}/submissions/files/{}", address, essay.id))
+        .send()
+        .await?;
+    assert_eq!(response.bytes().await?.as_ref(), b"essay text");
+
+    let response = student_client
+        .post(format!(
+            "{}/assignments/{}/submissions/{}",
+            address, overdue.id, students[0].id
+        ))
+        .multipart(Form::new().text("content", "Sorry, I'm late"))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    assert!(response.json::<Submission>().await?.late);
+
+    //grade goes to the gradebook
+    let response = teacher
+        .put(format!("{}/grade", submission_uri))
+        .json(&serde_json::json!({"score": 8.0}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let graded = response.json::<Submission>().await?;
+    assert!(graded.grade_id.is_some());
+
+    let gradebook = student_client
+        .get(format!("{}/students/{}/grades", address, students[0].id))
+        .send()
+        .await?
+        .json::<GradeBook>()
+        .await?;
+    assert_eq!(gradebook.grades.len(), 1);
+    assert_eq!(gradebook.grades[0].assessment, open.title);
+    assert_eq!(gradebook.summary.courses[0].average, 80.0);
+
+    //graded submissions can't be replaced
+    let response = student_client
+        .post(submission_uri)
+        .multipart(submission_form()?)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 409);
+
+    //concurrent gradings record one grade
+    let overdue_uri = format!(
+        "{}/assignments/{}/submissions/{}/grade",
+        address, overdue.id, students[0].id
+    );
+    let requests = (0..3).map(|_| {
+        teacher
+            .put(overdue_uri.clone())
+            .json(&serde_json::json!({"score": 5.0}))
+            .send()
+    });
+    for response in futures_util::future::join_all(requests).await {
+        assert!([200, 409].contains(&response?.status().as_u16()));
+    }
+    let gradebook = student_client
+        .get(format!("{}/students/{}/grades", address, students[0].id))
+        .send()
+        .await?
+        .json::<GradeBook>()
+        .await?;
+    assert_eq!(gradebook.grades.len(), 2);
+
+    Ok(())
+}
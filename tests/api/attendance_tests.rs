@@ -0,0 +1,100 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{Attendance, AttendanceRate, AttendanceStatus, FullStudent};
+
+use crate::{
+    authorized_client,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn attendance_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+    let attendance_uri = format!("{}/courses/Chemistry/attendance", address);
+
+    let mut students = Vec::new();
+    for courses in [
+        vec!["Chemistry"],
+        vec!["Chemistry", "Physics"],
+        vec!["Physics"],
+    ] {
+        let mut student: FakeStudent = Faker.fake();
+        student.courses = courses.into_iter().map(String::from).collect();
+        let student = send_post_request(&client, &student, format!("{}/students", address))
+            .await?
+            .json::<FullStudent>()
+            .await?;
+        students.push(student.id);
+    }
+
+    let sessions = [
+        serde_json::json!({"date": "2023-04-24", "records": [
+            {"studentId": students[0], "status": "present"},
+            {"studentId": students[1], "status": "present"},
+        ]}),
+        //the second mark of the same session overwrites the first one
+        serde_json::json!({"date": "2023-04-24", "records": [
+            {"studentId": students[1], "status": "absent"},
+        ]}),
+        serde_json::json!({"date": "2023-04-25", "records": [
+            {"studentId": students[0], "status": "late"},
+            {"studentId": students[1], "status": "excused", "note": "Sick"},
+        ]}),
+    ];
+    for session in sessions.iter() {
+        let response = send_post_request(&client, session, attendance_uri.clone()).await?;
+        assert!(response.status().is_success());
+    }
+
+    //excused without a note and students of other courses are rejected
+    let invalid_sessions = [
+        serde_json::json!({"date": "2023-04-26", "records": [
+            {"studentId": students[0], "status": "excused"},
+        ]}),
+        serde_json::json!({"date": "2023-04-26", "records": [
+            {"studentId": students[2], "status": "present"},
+        ]}),
+    ];
+    for session in invalid_sessions.iter() {
+        let response = send_post_request(&client, session, attendance_uri.clone()).await?;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    let history = client
+        .get(format!("{}/students/{}/attendance", address, students[1]))
+        .send()
+        .await?
+        .json::<Vec<Attendance>>()
+        .await?;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].status, AttendanceStatus::Excused);
+    assert_eq!(history[1].status, AttendanceStatus::Absent);
+
+    //present and late of not excused records: 2 of 3
+    let rates = client
+        .get(format!("{}/attendance/rates?course=Chemistry", address))
+        .send()
+        .await?
+        .json::<Vec<AttendanceRate>>()
+        .await?;
+    assert_eq!(rates.len(), 1);
+    assert_eq!(rates[0].records, 4);
+    assert_eq!(rates[0].excused, 1);
+    assert_eq!(rates[0].rate, Some(66.67));
+
+    let rates = client
+        .get(format!(
+            "{}/attendance/rates?course=Chemistry&from=2023-04-25",
+            address
+        ))
+        .send()
+        .await?
+        .json::<Vec<AttendanceRate>>()
+        .await?;
+    assert_eq!(rates[0].records, 2);
+    assert_eq!(rates[0].rate, Some(100.0));
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{AuditEntry, FullStudent};
+
+use crate::{
+    authorized_client, client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn audit_log_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let new_student: FakeStudent = Faker.fake();
+    let response =
+        send_post_request(&client, &new_student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let student = response.json::<FullStudent>().await?;
+
+    let new_age = if student.age == 30 { 31 } else { 30 };
+    let response = client
+        .patch(format!("{}/students/{}", address, student.id))
+        .header("If-Match", "*")
+        .header("X-Request-Id", "test-request")
+        .json(&serde_json::json!({ "age": new_age }))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    //only admins can read the audit log
+    let audit_address = format!("{}/audit?entity=student&entity_id={}", address, student.id);
+    let response = client.get(&audit_address).send().await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = admin.get(&audit_address).send().await?;
+    assert!(response.status().is_success());
+    let entries = response.json::<Vec<AuditEntry>>().await?;
+
+    assert_eq!(entries.len(), 2);
+    let update = &entries[0];
+    assert_eq!(update.action, "update");
+    assert_eq!(update.request_id.as_deref(), Some("test-request"));
+    assert!(update.actor_id.is_some());
+    assert_eq!(update.diff.as_ref().unwrap()["age"]["after"], new_age);
+    assert_eq!(entries[1].action, "create");
+
+    Ok(())
+}
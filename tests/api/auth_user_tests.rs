@@ -2,9 +2,16 @@ use fake::{Dummy, Faker, Fake};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::PgPool;
-use zero2prod::schemas::User;
-
-use crate::{post_students_tests::send_post_request, start_app};
+use zero2prod::{
+    db::db_bootstrap_admin,
+    schemas::{AuditContext, Role, User},
+};
+
+use crate::{
+    client_with_role, client_with_role_and_id,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
 use fake::faker::internet::en::{Username,Password,SafeEmail};
 
 #[derive(Debug, Serialize, Deserialize, Dummy)]
@@ -22,16 +29,19 @@ pub struct FakeRegisterUser {
 #[sqlx::test]
 async fn register_user(pool: PgPool) -> Result<(), reqwest::Error> {
     let address = start_app(pool).await;
+    let client = reqwest::Client::new();
 
     let new_user: FakeRegisterUser = Faker.fake();
-    let register_user_add = format!("{}/auth/register", address);
-    let response = send_post_request(&new_user, register_user_add).await?;
+    let register_user_add = format!("{}/auth/signup", address);
+    let response = send_post_request(&client, &new_user, register_user_add).await?;
 
     assert!(response.status().is_success());
     let res_data = response.json::<User>().await?;
 
     assert_eq!(res_data.username, new_user.username);
     assert_eq!(res_data.email, new_user.email);
+    //new users get the least privileged role
+    assert_eq!(res_data.role, Role::Student);
 
     Ok(())
 }
@@ -39,10 +49,11 @@ async fn register_user(pool: PgPool) -> Result<(), reqwest::Error> {
 #[sqlx::test]
 async fn user_log_in(pool: PgPool) -> Result<(), reqwest::Error> {
     let address = start_app(pool).await;
+    let client = reqwest::Client::new();
 
     let new_user: FakeRegisterUser = Faker.fake();
-    let register_user_add = format!("{}/auth/register", address);
-    let response = send_post_request(&new_user, register_user_add).await?;
+    let register_user_add = format!("{}/auth/signup", address);
+    let response = send_post_request(&client, &new_user, register_user_add).await?;
 
     assert!(response.status().is_success());
     let res_data = response.json::<User>().await?;
@@ -56,10 +67,50 @@ async fn user_log_in(pool: PgPool) -> Result<(), reqwest::Error> {
     });
 
     let login_user_add = format!("{}/auth/login", address);
-    let response = send_post_request(&login_data, login_user_add).await?;
+    let response = send_post_request(&client, &login_data, login_user_add).await?;
 
     assert!(response.status().is_success());
 
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[sqlx::test]
+async fn bootstrap_admin_check(pool: PgPool) {
+    let address = start_app(pool.clone()).await;
+    let (_, first_id) = crate::register_user(&address).await;
+    let (_, second_id) = crate::register_user(&address).await;
+
+    let audit = AuditContext::system();
+    let admin = db_bootstrap_admin(first_id, &audit, &pool).await.unwrap();
+    assert_eq!(admin.unwrap().role, Role::Admin);
+
+    //only the first admin is set
+    let admin = db_bootstrap_admin(second_id, &audit, &pool).await.unwrap();
+    assert!(admin.is_none());
+}
+
+#[sqlx::test]
+async fn changed_role_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let (teacher, teacher_id) = client_with_role_and_id(&address, &pool, "teacher").await;
+
+    let student: FakeStudent = Faker.fake();
+    let response = send_post_request(&teacher, &student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+
+    let response = admin
+        .put(format!("{}/users/{}/role", address, teacher_id))
+        .json(&json!({"role": "student"}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    //the token isn't refreshed, the new role applies at once
+    let student: FakeStudent = Faker.fake();
+    let response = send_post_request(&teacher, &student, format!("{}/students", address)).await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    Ok(())
+}
@@ -1,40 +1,233 @@
 use crate::post_students_tests::{send_post_request, FakeStudent};
-use crate::{mock_avatar_client, start_app};
+use crate::{
+    authorized_client, log_in_with, mock_avatar_client, register_user, set_role, start_app,
+    start_app_with_avatar,
+};
+use reqwest::multipart::{Form, Part};
 use sqlx::PgPool;
+use std::{io::Cursor, sync::Arc, time::Duration};
+use uuid::Uuid;
+use wiremock::matchers::{method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use fake::faker::internet::en::SafeEmail;
 use fake::{Fake, Faker};
-use zero2prod::schemas::FullStudent;
+use zero2prod::app::{AvatarClient, AvatarProvider, DefaultAvatar, LocalAvatar, LocalAvatarStyle};
+use zero2prod::schemas::{FullStudent, StudentAvatar};
 
 #[tokio::test]
 async fn avatar_mock_api_test() -> Result<(), reqwest::Error> {
     let avatar_client = mock_avatar_client().await;
     let img = avatar_client
-        .send_request(&SafeEmail().fake::<String>())
-        .await?;
-    assert!(img.len() > 0);
-    assert!(img.contains(&format!("{}", avatar_client.base_url)));
+        .resolve(Uuid::new_v4(), "Anna Smith", &SafeEmail().fake::<String>())
+        .await;
+    assert!(!img.is_empty());
+    assert!(img.contains(&avatar_client.base_url));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn avatar_probe_check() {
+    let mock_server = MockServer::start().await;
+    let id = Uuid::new_v4();
+    let name = "Anna Smith";
+    let email = "anna@example.com";
+    let hash = format!("/{:x}", md5::compute(email));
+    let default_img = "https://example.com/default.png".to_string();
+
+    //the first request fails
+    Mock::given(method("GET"))
+        .and(path(hash.clone()))
+        .and(query_param("d", "404"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(hash.clone()))
+        .and(query_param("d", "404"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex("^/slow/"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+        .mount(&mock_server)
+        .await;
+    //emails without an image
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    let client = AvatarClient::new(mock_server.uri(), default_img.clone())
+        .with_probe(Duration::from_millis(200), 1);
+    let img = client.resolve(id, name, email).await;
+    assert_eq!(
+        img,
+        format!("{}{}?d={}", mock_server.uri(), hash, default_img)
+    );
+
+    let img = client.resolve(id, name, "nobody@example.com").await;
+    assert_eq!(img, default_img);
+
+    //timeout
+    let client = AvatarClient::new(format!("{}/slow", mock_server.uri()), default_img.clone())
+        .with_probe(Duration::from_millis(200), 0);
+    assert_eq!(client.resolve(id, name, email).await, default_img);
+
+    //no retries left
+    let client = AvatarClient::new(mock_server.uri(), default_img.clone())
+        .with_probe(Duration::from_millis(200), 0);
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+    assert_eq!(client.resolve(id, name, email).await, default_img);
+
+    let offline = DefaultAvatar {
+        default_img: default_img.clone(),
+    };
+    assert_eq!(offline.resolve(id, name, email).await, default_img);
+}
+
+#[sqlx::test]
+async fn local_avatar_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let identicon = LocalAvatar {
+        style: LocalAvatarStyle::Identicon,
+    };
+    let (content_type, png) = identicon
+        .render("Anna Smith", "anna@example.com", 64)
+        .unwrap();
+    assert_eq!(content_type, "image/png");
+    let image = image::load_from_memory(&png).unwrap();
+    assert_eq!((image.width(), image.height()), (64, 64));
+    //the same email gets the same image
+    assert_eq!(
+        identicon
+            .render("Anna Smith", "anna@example.com", 64)
+            .unwrap()
+            .1,
+        png
+    );
+
+    let address = start_app_with_avatar(
+        pool.clone(),
+        Arc::new(LocalAvatar {
+            style: LocalAvatarStyle::Initials,
+        }),
+    )
+    .await;
+    let client = authorized_client(&address, &pool).await;
+
+    let mut new_student: FakeStudent = Faker.fake();
+    new_student.full_name = "Anna Smith".into();
+    let response =
+        send_post_request(&client, &new_student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let student = response.json::<FullStudent>().await?;
+    let avatar_uri = format!("{}/students/{}/avatar", address, student.id);
+    assert_eq!(format!("{}{}", address, student.img), avatar_uri);
+
+    //no redirect, the image is generated
+    let response = client.get(avatar_uri).send().await?;
+    assert!(response.status().is_success());
+    assert_eq!(response.headers()["Content-Type"], "image/svg+xml");
+    assert!(response.headers().contains_key("ETag"));
+    let svg = response.text().await?;
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains(">AS</text>"));
 
     Ok(())
 }
 
 #[sqlx::test]
 async fn student_avatar_check(pool: PgPool) -> Result<(), reqwest::Error> {
-    let address = start_app(pool).await;
+    let address = start_app(pool.clone()).await;
+    //the Gravatar redirect isn't followed
+    let (user, user_id) = register_user(&address).await;
+    set_role(&pool, user_id, "teacher").await;
+    let client = log_in_with(
+        &address,
+        &user,
+        reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()),
+    )
+    .await;
 
     let new_student: FakeStudent = Faker.fake();
     let post_student_address = format!("{}/students", address);
-    let response = send_post_request(&new_student, post_student_address).await?;
+    let response = send_post_request(&client, &new_student, post_student_address).await?;
 
     assert!(response.status().is_success());
 
     let user = response.json::<FullStudent>().await?;
+    let avatar_uri = format!("{}/students/{}/avatar", address, user.id);
+
+    let avatar_response = client.get(avatar_uri.clone()).send().await?;
+
+    assert_eq!(avatar_response.status().as_u16(), 302);
+    let location = avatar_response.headers()["Location"].to_str().unwrap();
+    assert!(location.starts_with(&user.img));
+    assert!(location.ends_with("&s=128"));
+
+    let response = client
+        .get(format!("{}?size=100", avatar_uri))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
 
-    let avatar_response = reqwest::get(format!("{}/students/{}/avatar", address, user.id)).await?;
+    //uploaded avatar
+    let mut png = Vec::new();
+    image::DynamicImage::new_rgb8(300, 200)
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+    let form = Form::new().part(
+        "avatar",
+        Part::bytes(png).file_name("me.png").mime_str("image/png")?,
+    );
+    let response = client
+        .put(avatar_uri.clone())
+        .multipart(form)
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let avatar = response.json::<StudentAvatar>().await?;
+    assert_eq!(avatar.sizes, vec![64, 128, 256]);
+
+    let response = client.get(format!("{}?size=64", avatar_uri)).send().await?;
+    assert!(response.status().is_success());
+    assert_eq!(response.headers()["Content-Type"], "image/png");
+    let etag = response.headers()["ETag"].clone();
+    let thumbnail = image::load_from_memory(&response.bytes().await?).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (64, 64));
+
+    let response = client
+        .get(format!("{}?size=64", avatar_uri))
+        .header("If-None-Match", etag)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 304);
+
+    //not an image
+    let form = Form::new().part(
+        "avatar",
+        Part::bytes(b"hello".to_vec())
+            .file_name("me.png")
+            .mime_str("image/png")?,
+    );
+    let response = client
+        .put(avatar_uri.clone())
+        .multipart(form)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
 
-    assert!(avatar_response.status().is_success());
-    let avatar_data = avatar_response.json::<String>().await?;
-    assert!(avatar_data.len() > 0);
+    let response = client.delete(avatar_uri.clone()).send().await?;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = client.get(avatar_uri).send().await?;
+    assert_eq!(response.status().as_u16(), 302);
 
     Ok(())
 }
@@ -0,0 +1,154 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{CourseCapacity, FullStudent};
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn course_waitlist_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let capacity_uri = format!("{}/courses/Chess/capacity", address);
+
+    let response = admin
+        .put(capacity_uri.clone())
+        .json(&serde_json::json!({"maxSeats": 1}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let mut first: FakeStudent = Faker.fake();
+    first.courses = vec!["Chess".into()];
+    let response = send_post_request(&admin, &first, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let first = response.json::<FullStudent>().await?;
+
+    //the course is full
+    let mut second: FakeStudent = Faker.fake();
+    second.courses = vec!["Chess".into(), "Art".into()];
+    let response = send_post_request(&admin, &second, format!("{}/students", address)).await?;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let mut second = serde_json::to_value(&second).unwrap();
+    second["waitlist"] = serde_json::Value::Bool(true);
+    let response = send_post_request(&admin, &second, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let second = response.json::<FullStudent>().await?;
+    assert_eq!(second.courses, vec!["Art".to_string()]);
+    assert_eq!(second.waitlist, vec!["Chess".to_string()]);
+
+    let capacity = admin
+        .get(capacity_uri.clone())
+        .send()
+        .await?
+        .json::<CourseCapacity>()
+        .await?;
+    assert_eq!(capacity.enrolled, 1);
+    assert_eq!(capacity.waitlist, vec![second.id]);
+
+    //a course with a free seat only in the database, its waitlist isn't promoted
+    //when a seat of another course frees up
+    let response = admin
+        .put(format!("{}/courses/Go/capacity", address))
+        .json(&serde_json::json!({"maxSeats": 1}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let mut waiting = Vec::new();
+    for _ in 0..2 {
+        let mut student = serde_json::to_value(Faker.fake::<FakeStudent>()).unwrap();
+        student["courses"] = serde_json::json!(["Go"]);
+        student["waitlist"] = serde_json::Value::Bool(true);
+        let student = send_post_request(&admin, &student, format!("{}/students", address))
+            .await?
+            .json::<FullStudent>()
+            .await?;
+        waiting.push(student);
+    }
+    assert_eq!(waiting[1].waitlist, vec!["Go".to_string()]);
+    sqlx::query("update course_capacities set max_seats = 2 where course_name = 'Go'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    //the seat goes to the waitlist
+    let response = admin
+        .delete(format!("{}/delete/{}", address, first.id))
+        .header("If-Match", "*")
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let mut second = admin
+        .get(format!("{}/students/{}", address, second.id))
+        .send()
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    second.courses.sort();
+    assert_eq!(second.courses, vec!["Art".to_string(), "Chess".to_string()]);
+    assert!(second.waitlist.is_empty());
+
+    let go = admin
+        .get(format!("{}/students/{}", address, waiting[1].id))
+        .send()
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    assert_eq!(go.waitlist, vec!["Go".to_string()]);
+
+    //the restored student's seat is taken, the student waits for it
+    let response = admin
+        .post(format!("{}/students/{}/restore", address, first.id))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let first = response.json::<FullStudent>().await?;
+    assert!(first.courses.is_empty());
+    assert_eq!(first.waitlist, vec!["Chess".to_string()]);
+
+    let capacity = admin
+        .get(capacity_uri)
+        .send()
+        .await?
+        .json::<CourseCapacity>()
+        .await?;
+    assert_eq!(capacity.enrolled, 1);
+    assert_eq!(capacity.waitlist, vec![first.id]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn concurrent_enrollment_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let response = admin
+        .put(format!("{}/courses/Chess/capacity", address))
+        .json(&serde_json::json!({"maxSeats": 1}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    //only one of the students enrolling at the same time gets the last seat
+    let requests = (0..5).map(|_| {
+        let mut student: FakeStudent = Faker.fake();
+        student.courses = vec!["Chess".into()];
+        let admin = admin.clone();
+        let uri = format!("{}/students", address);
+        async move { send_post_request(&admin, &student, uri).await }
+    });
+    let mut statuses = Vec::new();
+    for response in futures_util::future::join_all(requests).await {
+        statuses.push(response?.status().as_u16());
+    }
+    statuses.sort();
+    assert_eq!(statuses, vec![200, 409, 409, 409, 409]);
+
+    Ok(())
+}
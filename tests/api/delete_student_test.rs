@@ -4,17 +4,18 @@ use zero2prod::schemas::FullStudent;
 
 use crate::{
     post_students_tests::{send_post_request, FakeStudent},
-    start_app,
+    authorized_client, start_app,
 };
 
 #[sqlx::test]
 async fn delete_student_check(pool: PgPool) -> Result<(), reqwest::Error> {
-    let address = start_app(pool).await;
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
 
     //add new student
     let new_student: FakeStudent = Faker.fake();
     let post_student_address = format!("{}/students", address);
-    let response = send_post_request(&new_student, post_student_address).await?;
+    let response = send_post_request(&client, &new_student, post_student_address).await?;
 
     assert!(response.status().is_success());
     let res_data = response.json::<FullStudent>().await?;
@@ -25,14 +26,15 @@ async fn delete_student_check(pool: PgPool) -> Result<(), reqwest::Error> {
     assert_eq!(res_data.courses, new_student.courses);
 
     //delete added student
-    let response = reqwest::Client::new()
+    let response = client
         .delete(format!("{}/delete/{}", address, res_data.id))
+        .header("If-Match", format!("\"{}\"", res_data.version))
         .send()
         .await?;
 
     assert!(response.status().is_success());
 
     let res_data = response.json::<String>().await?;
-    assert!(res_data.contains("Deleted user"));
+    assert!(res_data.contains("Deleted student"));
     Ok(())
 }
@@ -0,0 +1,220 @@
+use async_trait::async_trait;
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::{
+    app::{send_queued_emails, EmailBackend, Mailer, OutboxSettings, Settings},
+    errors::{Error, ErrorTypes},
+    schemas::{EmailTemplate, FullStudent, Guardian, QueuedEmail},
+};
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn email_notifications_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let mut student: FakeStudent = Faker.fake();
+    student.courses = vec!["Chemistry".into()];
+    let student = send_post_request(&admin, &student, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+
+    let guardian = send_post_request(
+        &admin,
+        &serde_json::json!({
+            "fullName": "Mary Smith",
+            "relationship": "mother",
+            "email": "mother@family.com",
+        }),
+        format!("{}/guardians", address),
+    )
+    .await?
+    .json::<Guardian>()
+    .await?;
+    let response = send_post_request(
+        &admin,
+        &serde_json::json!({"guardianId": guardian.id, "isPrimary": true}),
+        format!("{}/students/{}/guardians", address, student.id),
+    )
+    .await?;
+    assert!(response.status().is_success());
+
+    let response = send_post_request(
+        &admin,
+        &serde_json::json!({"courseName": "Chemistry", "assessment": "Exam", "score": 45.0, "maxScore": 50.0, "gradedOn": "2023-04-20"}),
+        format!("{}/students/{}/grades", address, student.id),
+    )
+    .await?;
+    assert!(response.status().is_success());
+
+    let response = send_post_request(
+        &admin,
+        &serde_json::json!({"date": "2023-04-24", "records": [
+            {"studentId": student.id, "status": "absent"},
+        ]}),
+        format!("{}/courses/Chemistry/attendance", address),
+    )
+    .await?;
+    assert!(response.status().is_success());
+
+    //handlers only queue the emails
+    let queued = sqlx::query_as::<_, (String, String, String)>(
+        "select template, recipient, body from email_outbox where sent_at is null order by created_at",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    let templates: Vec<&str> = queued.iter().map(|(t, _, _)| t.as_str()).collect();
+    assert_eq!(templates, ["welcome", "grade_posted", "absence_alert"]);
+    assert_eq!(queued[0].1, student.email);
+    assert!(queued[1].2.contains("45 of 50 for \"Exam\" in Chemistry"));
+    assert_eq!(queued[2].1, "mother@family.com");
+    assert!(queued[2].2.starts_with("Hello Mary Smith"));
+
+    //the file backend writes a file per email
+    let dir = std::env::temp_dir().join(format!("zero2prod-test-emails-{}", uuid::Uuid::new_v4()));
+    let mut settings = Settings::get_configuration().unwrap().email;
+    settings.backend = EmailBackend::File;
+    settings.path = dir.to_string_lossy().into();
+    let mailer = settings.build().unwrap();
+
+    let sent = send_queued_emails(&pool, mailer.as_ref(), &settings)
+        .await
+        .unwrap();
+    assert_eq!(sent, 3);
+    let sent = send_queued_emails(&pool, mailer.as_ref(), &settings)
+        .await
+        .unwrap();
+    assert_eq!(sent, 0);
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        files.push(std::fs::read_to_string(entry.unwrap().path()).unwrap());
+    }
+    assert_eq!(files.len(), 3);
+    assert!(files
+        .iter()
+        .any(|f| f.contains("To: mother@family.com") && f.contains("was absent from Chemistry")));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    Ok(())
+}
+
+#[test]
+fn email_template_check() {
+    //values aren't expanded again
+    let (subject, body) =
+        EmailTemplate::Welcome.render(&[("student_name", "{{courses}}"), ("courses", "Math, Art")]);
+    assert_eq!(subject, "Welcome to the school, {{courses}}!");
+    assert!(body.starts_with("Hello {{courses}},"));
+    assert!(body.contains("Your courses: Math, Art."));
+
+    //unknown placeholders are kept
+    let (subject, _) = EmailTemplate::Welcome.render(&[]);
+    assert_eq!(subject, "Welcome to the school, {{student_name}}!");
+}
+
+#[test]
+fn email_poll_interval_check() {
+    //a zero interval would panic the sending job
+    let settings = serde_json::json!({
+        "poll_interval_seconds": 0,
+        "batch_size": 10,
+        "max_attempts": 3,
+        "backoff_seconds": 1.0
+    });
+    assert!(serde_json::from_value::<OutboxSettings>(settings).is_err());
+}
+
+#[derive(Debug)]
+struct FailingMailer;
+
+#[async_trait]
+impl Mailer for FailingMailer {
+    async fn send(&self, _email: &QueuedEmail) -> Result<(), Error> {
+        Err(Error::new(
+            Some("Connection refused".into()),
+            None,
+            ErrorTypes::MailerError,
+        ))
+    }
+}
+
+#[sqlx::test]
+async fn email_retries_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let student: FakeStudent = Faker.fake();
+    let response = send_post_request(&admin, &student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+
+    let mut settings = Settings::get_configuration().unwrap().email;
+    settings.outbox.max_attempts = 3;
+    settings.outbox.backoff_seconds = 60.0;
+
+    //the failed email waits for the backoff
+    send_queued_emails(&pool, &FailingMailer, &settings)
+        .await
+        .unwrap();
+    let (attempts, delay, error) = sqlx::query_as::<_, (i32, f64, Option<String>)>(
+        "select attempts, extract(epoch from next_attempt_at - now())::float8, last_error from email_outbox",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(attempts, 1);
+    assert!(delay > 50.0 && delay <= 60.0);
+    assert!(error.unwrap().contains("Connection refused"));
+
+    //no waiting for the test, the delay doubles after every attempt
+    for (attempts, backoff) in [(2, 120.0), (3, 240.0)] {
+        sqlx::query("update email_outbox set next_attempt_at = now()")
+            .execute(&pool)
+            .await
+            .unwrap();
+        send_queued_emails(&pool, &FailingMailer, &settings)
+            .await
+            .unwrap();
+        let (recorded, delay, failed) = sqlx::query_as::<_, (i32, f64, bool)>(
+            "select attempts, extract(epoch from next_attempt_at - now())::float8, failed_at is not null from email_outbox",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(recorded, attempts);
+        assert!(delay > backoff - 10.0 && delay <= backoff);
+        //all attempts are used
+        assert_eq!(failed, attempts == 3);
+    }
+
+    //failed emails aren't sent anymore
+    sqlx::query("update email_outbox set next_attempt_at = now()")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let count = sqlx::query_scalar::<_, i64>("select count(*) from email_outbox")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(
+        send_queued_emails(&pool, &FailingMailer, &settings)
+            .await
+            .unwrap(),
+        0
+    );
+    let attempts = sqlx::query_scalar::<_, i32>("select attempts from email_outbox")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(attempts, 3);
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::ExportedStudent;
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn export_students_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let teacher = client_with_role(&address, &pool, "teacher").await;
+    let parent = client_with_role(&address, &pool, "parent").await;
+    let export_uri = format!("{}/students/export", address);
+
+    let response = parent
+        .get(format!("{}?format=csv", export_uri))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = admin
+        .get(format!("{}?format=pdf", export_uri))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let mut student: FakeStudent = Faker.fake();
+    student.courses = vec!["Math".into(), "Art".into()];
+    let response = send_post_request(&admin, &student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let other: FakeStudent = Faker.fake();
+    let response = send_post_request(&teacher, &other, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+
+    let response = admin
+        .get(format!("{}?format=csv", export_uri))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers()["Content-Type"].to_str().unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let csv = response.text().await?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("fullName,email,age,courses,id"));
+    assert!(csv.contains(&format!(
+        "{},{},{},Art;Math",
+        student.full_name, student.email, student.age
+    )));
+
+    //filters of the list work for the export
+    let response = teacher
+        .get(format!("{}?format=jsonl&mine=true", export_uri))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let jsonl = response.text().await?;
+    let exported: Vec<ExportedStudent> = jsonl
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].full_name, other.full_name);
+
+    let response = teacher
+        .get(format!("{}?format=csv&include_deleted=true", export_uri))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    //XLSX is a ZIP archive
+    let response = admin
+        .get(format!("{}?format=xlsx", export_uri))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let xlsx = response.bytes().await?;
+    assert_eq!(&xlsx[..2], b"PK");
+    Ok(())
+}
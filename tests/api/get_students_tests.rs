@@ -1,6 +1,6 @@
 use crate::{
     post_students_tests::{send_post_request, FakeStudent},
-    start_app,
+    authorized_client, start_app,
 };
 use fake::{Fake, Faker};
 use sqlx::PgPool;
@@ -8,8 +8,9 @@ use zero2prod::schemas::FullStudent;
 
 #[sqlx::test]
 async fn get_students_check(pool: PgPool) -> Result<(), reqwest::Error> {
-    let address = start_app(pool).await;
-    let response = reqwest::get(format!("{}/students", address)).await?;
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+    let response = client.get(format!("{}/students", address)).send().await?;
     assert!(response.status().is_success());
 
     let res_data = response.json::<Vec<FullStudent>>().await?;
@@ -19,15 +20,20 @@ async fn get_students_check(pool: PgPool) -> Result<(), reqwest::Error> {
 
 #[sqlx::test]
 async fn get_student_check(pool: PgPool) -> Result<(), reqwest::Error> {
-    let address = start_app(pool).await;
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
 
     let new_student: FakeStudent = Faker.fake();
-    let post_student_address = format!("{}/users", address);
-    let response = send_post_request(&new_student, post_student_address).await?;
+    let post_student_address = format!("{}/students", address);
+    let response = send_post_request(&client, &new_student, post_student_address).await?;
 
     assert!(response.status().is_success());
+    let created = response.json::<FullStudent>().await?;
 
-    let response = reqwest::get(format!("{}/users/{}", address, new_student.id)).await?;
+    let response = client
+        .get(format!("{}/students/{}", address, created.id))
+        .send()
+        .await?;
     assert!(response.status().is_success());
 
     let res_data = response.json::<FullStudent>().await?;
@@ -37,3 +43,49 @@ async fn get_student_check(pool: PgPool) -> Result<(), reqwest::Error> {
     assert_eq!(res_data.courses, new_student.courses);
     Ok(())
 }
+
+#[sqlx::test]
+async fn get_my_students_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let first_client = authorized_client(&address, &pool).await;
+    let second_client = authorized_client(&address, &pool).await;
+
+    let post_student_address = format!("{}/students", address);
+    let first_student: FakeStudent = Faker.fake();
+    let response =
+        send_post_request(&first_client, &first_student, post_student_address.clone()).await?;
+    assert!(response.status().is_success());
+    let first_student = response.json::<FullStudent>().await?;
+    assert!(first_student.created_by.is_some());
+    assert_eq!(first_student.created_by, first_student.updated_by);
+
+    let second_student: FakeStudent = Faker.fake();
+    let response =
+        send_post_request(&second_client, &second_student, post_student_address).await?;
+    assert!(response.status().is_success());
+
+    //only students registered by the first user
+    let students = first_client
+        .get(format!("{}/students?mine=true", address))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?;
+    assert_eq!(students.len(), 1);
+    assert_eq!(students[0].id, first_student.id);
+
+    //second user changes the first student
+    let response = second_client
+        .patch(format!("{}/students/{}", address, first_student.id))
+        .header("If-Match", "*")
+        .json(&serde_json::json!({"addCourses": ["History"]}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let changed = response.json::<FullStudent>().await?;
+    assert_eq!(changed.created_by, first_student.created_by);
+    assert_ne!(changed.updated_by, first_student.updated_by);
+    assert!(changed.updated_at > first_student.updated_at);
+
+    Ok(())
+}
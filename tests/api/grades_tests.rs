@@ -0,0 +1,94 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{FullStudent, Grade, GradeBook};
+
+use crate::{
+    authorized_client,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn gradebook_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+
+    let mut student: FakeStudent = Faker.fake();
+    student.courses = vec!["Math".into(), "Biology".into()];
+    let student = send_post_request(&client, &student, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    let grades_uri = format!("{}/students/{}/grades", address, student.id);
+
+    let grades = [
+        serde_json::json!({"courseName": "Math", "assessment": "Exam", "score": 90.0, "maxScore": 100.0, "weight": 2.0, "gradedOn": "2023-04-20"}),
+        serde_json::json!({"courseName": "Math", "assessment": "Quiz", "score": 60.0, "maxScore": 100.0}),
+        serde_json::json!({"courseName": "Biology", "assessment": "Lab", "score": 45.0, "maxScore": 50.0}),
+    ];
+    let mut recorded = Vec::new();
+    for grade in grades.iter() {
+        let response = send_post_request(&client, grade, grades_uri.clone()).await?;
+        assert!(response.status().is_success());
+        recorded.push(response.json::<Grade>().await?);
+    }
+
+    //not enrolled course and score above max are rejected
+    let invalid_grades = [
+        serde_json::json!({"courseName": "History", "assessment": "Exam", "score": 5.0, "maxScore": 10.0}),
+        serde_json::json!({"courseName": "Math", "assessment": "Exam", "score": 15.0, "maxScore": 10.0}),
+    ];
+    for grade in invalid_grades.iter() {
+        let response = send_post_request(&client, grade, grades_uri.clone()).await?;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    let gradebook = client
+        .get(&grades_uri)
+        .send()
+        .await?
+        .json::<GradeBook>()
+        .await?;
+    assert_eq!(gradebook.grades.len(), 3);
+    //Biology 90% (4.0), Math (0.9*2 + 0.6)/3 = 80% (3.0)
+    assert_eq!(gradebook.summary.courses[0].course_name, "Biology");
+    assert_eq!(gradebook.summary.courses[0].average, 90.0);
+    assert_eq!(gradebook.summary.courses[1].average, 80.0);
+    assert_eq!(gradebook.summary.gpa, Some(3.5));
+
+    //edit the quiz, Math becomes (0.9*2 + 0.9)/3 = 90%
+    let response = client
+        .patch(format!("{}/grades/{}", address, recorded[1].id))
+        .json(&serde_json::json!({"score": 90.0}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let response = client
+        .patch(format!("{}/grades/{}", address, recorded[1].id))
+        .json(&serde_json::json!({"score": 120.0}))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let with_grades = client
+        .get(format!(
+            "{}/students/{}?include=grades",
+            address, student.id
+        ))
+        .send()
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    assert_eq!(with_grades.grades.unwrap().gpa, Some(4.0));
+
+    let without_grades = client
+        .get(format!("{}/students/{}", address, student.id))
+        .send()
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    assert!(without_grades.grades.is_none());
+
+    Ok(())
+}
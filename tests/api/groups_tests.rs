@@ -0,0 +1,129 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{ClassGroup, FullStudent, FullTeacher};
+
+use crate::{
+    client_with_role, client_with_role_and_id,
+    post_students_tests::{send_post_request, FakeStudent, ValidFullName},
+    start_app,
+};
+
+#[sqlx::test]
+async fn class_groups_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let (teacher_client, teacher_user_id) =
+        client_with_role_and_id(&address, &pool, "teacher").await;
+
+    let teacher = send_post_request(
+        &admin,
+        &serde_json::json!({
+            "fullName": ValidFullName.fake::<String>(),
+            "email": "homeroom@school.com",
+            "userId": teacher_user_id,
+        }),
+        format!("{}/teachers", address),
+    )
+    .await?
+    .json::<FullTeacher>()
+    .await?;
+
+    let new_group = serde_json::json!({
+        "name": "10-B",
+        "academicYear": "2023/2024",
+        "homeroomTeacherId": teacher.id,
+    });
+
+    //only admins can manage groups
+    let response =
+        send_post_request(&teacher_client, &new_group, format!("{}/groups", address)).await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = send_post_request(&admin, &new_group, format!("{}/groups", address)).await?;
+    assert!(response.status().is_success());
+    let group = response.json::<ClassGroup>().await?;
+
+    let mut students = Vec::new();
+    for _ in 0..3 {
+        let student: FakeStudent = Faker.fake();
+        let student = send_post_request(&admin, &student, format!("{}/students", address))
+            .await?
+            .json::<FullStudent>()
+            .await?;
+        students.push(student.id);
+    }
+
+    let group = send_post_request(
+        &admin,
+        &serde_json::json!({"students": [students[0], students[1]]}),
+        format!("{}/groups/{}/students", address, group.id),
+    )
+    .await?
+    .json::<ClassGroup>()
+    .await?;
+    assert_eq!(group.students_count, 2);
+
+    let group_students = admin
+        .get(format!("{}/students?group=10-B", address))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?;
+    assert_eq!(group_students.len(), 2);
+    assert!(group_students.iter().all(|s| s.id != students[2]));
+
+    //the group of the last year with the same name isn't included
+    let old_group = send_post_request(
+        &admin,
+        &serde_json::json!({"name": "10-B", "academicYear": "2022/2023"}),
+        format!("{}/groups", address),
+    )
+    .await?
+    .json::<ClassGroup>()
+    .await?;
+    let response = send_post_request(
+        &admin,
+        &serde_json::json!({"students": [students[2]]}),
+        format!("{}/groups/{}/students", address, old_group.id),
+    )
+    .await?;
+    assert!(response.status().is_success());
+    let group_students = admin
+        .get(format!("{}/students?group=10-B", address))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?;
+    assert_eq!(group_students.len(), 2);
+    assert!(group_students.iter().all(|s| s.id != students[2]));
+
+    //homeroom teacher sees the class
+    let my_groups = teacher_client
+        .get(format!("{}/groups/mine", address))
+        .send()
+        .await?
+        .json::<Vec<ClassGroup>>()
+        .await?;
+    assert_eq!(my_groups.len(), 1);
+    assert_eq!(my_groups[0].id, group.id);
+
+    let response = admin
+        .delete(format!(
+            "{}/groups/{}/students/{}",
+            address, group.id, students[0]
+        ))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let group_students = teacher_client
+        .get(format!("{}/groups/{}/students", address, group.id))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?;
+    assert_eq!(group_students.len(), 1);
+    assert_eq!(group_students[0].id, students[1]);
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{FullStudent, Guardian, StudentGuardian};
+
+use crate::{
+    client_with_role, log_in,
+    post_students_tests::{send_post_request, FakeStudent, ValidFullName},
+    register_user, start_app,
+};
+
+#[sqlx::test]
+async fn parent_children_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    //the parent signs up and gets the role when the guardian is linked
+    let (parent_user, parent_user_id) = register_user(&address).await;
+    let parent = log_in(&address, &parent_user).await;
+
+    let new_guardian = serde_json::json!({
+        "fullName": ValidFullName.fake::<String>(),
+        "relationship": "mother",
+        "phone": "+380 (67) 123-45-67",
+        "email": "mother@family.com",
+        "userId": parent_user_id,
+    });
+
+    //only admins can manage guardians
+    let response =
+        send_post_request(&parent, &new_guardian, format!("{}/guardians", address)).await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response =
+        send_post_request(&admin, &new_guardian, format!("{}/guardians", address)).await?;
+    assert!(response.status().is_success());
+    let guardian = response.json::<Guardian>().await?;
+
+    //two siblings and one more student
+    let mut students = Vec::new();
+    for _ in 0..3 {
+        let student: FakeStudent = Faker.fake();
+        let student = send_post_request(&admin, &student, format!("{}/students", address))
+            .await?
+            .json::<FullStudent>()
+            .await?;
+        students.push(student);
+    }
+
+    //freshly signed up users can't access students
+    let (stranger, _) = register_user(&address).await;
+    let stranger = log_in(&address, &stranger).await;
+    for uri in [
+        format!("{}/students", address),
+        format!("{}/students/{}", address, students[0].id),
+        format!("{}/students/{}/grades", address, students[0].id),
+    ] {
+        let response = stranger.get(uri).send().await?;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+    let student: FakeStudent = Faker.fake();
+    let response = send_post_request(&stranger, &student, format!("{}/students", address)).await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    for (student, is_primary) in students[..2].iter().zip([true, false]) {
+        let response = send_post_request(
+            &admin,
+            &serde_json::json!({"guardianId": guardian.id, "isPrimary": is_primary}),
+            format!("{}/students/{}/guardians", address, student.id),
+        )
+        .await?;
+        assert!(response.status().is_success());
+        let guardians = response.json::<Vec<StudentGuardian>>().await?;
+        assert_eq!(guardians.len(), 1);
+        assert_eq!(guardians[0].is_primary, is_primary);
+    }
+
+    let mut children = parent
+        .get(format!("{}/guardians/me/students", address))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?
+        .into_iter()
+        .map(|s| s.id)
+        .collect::<Vec<_>>();
+    children.sort();
+    let mut siblings = vec![students[0].id, students[1].id];
+    siblings.sort();
+    assert_eq!(children, siblings);
+
+    //parent can see the own child only
+    let response = parent
+        .get(format!("{}/students/{}", address, students[0].id))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let response = parent
+        .get(format!("{}/students/{}", address, students[2].id))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = parent
+        .get(format!("{}/students/{}/grades", address, students[2].id))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = parent.get(format!("{}/students", address)).send().await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    Ok(())
+}
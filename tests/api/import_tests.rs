@@ -0,0 +1,118 @@
+use sqlx::PgPool;
+use zero2prod::schemas::ImportReport;
+
+use crate::{client_with_role, start_app};
+
+const CSV: &str = "fullName,email,age,courses
+Anna Smith,anna@example.com,17,Math;Art
+Bob Brown,not-an-email,17,Math
+Carl Jones,carl@example.com,12,
+Anna Smith,anna2@example.com,18,Art
+Dora White,dora@example.com,19,Chess
+";
+
+#[sqlx::test]
+async fn import_students_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let parent = client_with_role(&address, &pool, "parent").await;
+    let import_uri = format!("{}/students/import", address);
+
+    let response = parent.post(import_uri.clone()).body(CSV).send().await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = admin
+        .post(import_uri.clone())
+        .body("fullName,email,age\nAnna Smith,anna@example.com,17,Math,Art")
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let report = response.json::<ImportReport>().await?;
+    assert_eq!(report.valid, 0);
+    assert_eq!(report.errors[0].row, 2);
+
+    //dry run only validates the file
+    let report = admin
+        .post(format!("{}?dry_run=true", import_uri))
+        .body(CSV)
+        .send()
+        .await?
+        .json::<ImportReport>()
+        .await?;
+    assert!(report.dry_run);
+    assert_eq!(report.total, 5);
+    assert_eq!(report.valid, 2);
+    assert_eq!(report.imported, 0);
+    let rows: Vec<u64> = report.errors.iter().map(|e| e.row).collect();
+    //invalid email, too young, duplicated name
+    assert_eq!(rows, vec![3, 4, 5]);
+    assert!(report.errors[0].errors.get("email").is_some());
+
+    let count = sqlx::query_scalar::<_, i64>("select count(*) from students")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    let report = admin
+        .post(import_uri.clone())
+        .body(CSV)
+        .send()
+        .await?
+        .json::<ImportReport>()
+        .await?;
+    assert!(!report.dry_run);
+    assert_eq!(report.imported, 2);
+
+    let courses = sqlx::query_scalar::<_, String>(
+        "select c.course_name from courses c join students s on s.id = c.student_id \
+         where s.full_name = 'Anna Smith' order by c.course_name",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(courses, vec!["Art".to_string(), "Math".to_string()]);
+
+    //imported students already exist
+    let report = admin
+        .post(import_uri)
+        .body(CSV)
+        .send()
+        .await?
+        .json::<ImportReport>()
+        .await?;
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.errors.len(), 5);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn import_students_capacity_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let import_uri = format!("{}/students/import", address);
+    sqlx::query("insert into course_capacities (course_name, max_seats) values ('Chess', 1)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    //concurrent imports can't take the same seat
+    let files = [
+        "fullName,email,age,courses\nAnna Smith,anna@example.com,17,Chess\n",
+        "fullName,email,age,courses\nDora White,dora@example.com,19,Chess\n",
+    ];
+    let requests = files
+        .iter()
+        .map(|file| admin.post(import_uri.clone()).body(*file).send());
+    for response in futures_util::future::join_all(requests).await {
+        assert!([200, 409].contains(&response?.status().as_u16()));
+    }
+
+    let enrolled =
+        sqlx::query_scalar::<_, i64>("select count(*) from courses where course_name = 'Chess'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(enrolled, 1);
+    Ok(())
+}
@@ -1,21 +1,45 @@
+pub mod assignments_tests;
 pub mod avatar_tests;
+pub mod capacity_tests;
 pub mod delete_student_test;
+pub mod email_tests;
+pub mod export_tests;
 pub mod get_students_tests;
+pub mod grades_tests;
+pub mod groups_tests;
+pub mod guardians_tests;
 pub mod health_check;
+pub mod import_tests;
 pub mod post_students_tests;
+pub mod prerequisites_tests;
+pub mod report_cards_tests;
+pub mod attendance_tests;
+pub mod audit_tests;
 pub mod auth_user_tests;
+pub mod restore_student_tests;
+pub mod search_tests;
+pub mod stats_tests;
+pub mod teachers_tests;
+pub mod terms_tests;
+pub mod timetable_tests;
+pub mod webhooks_tests;
 
 use wiremock::{Match, Request};
 
 use once_cell::sync::Lazy;
-use sqlx::PgPool;
+use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::net::TcpListener;
 use zero2prod::{
-    schemas::FullStudent,
     logging::{get_tracing_subscriber, init_tracing_subscriber}, app::Settings,
 };
 
-use zero2prod::app::{run_app, AvatarClient};
+use std::sync::Arc;
+use zero2prod::app::{run_app, AvatarClient, AvatarProvider, LocalStorage, Storage};
+use zero2prod::schemas::User;
+
+use auth_user_tests::FakeRegisterUser;
+use fake::{Fake, Faker};
+use post_students_tests::send_post_request;
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     if std::env::var("TEST_LOG").is_ok() {
@@ -33,8 +57,8 @@ impl Match for HasEmailHash {
     fn matches(&self, request: &Request) -> bool {
         if let Some(hash) = request
             .url
-            .to_string()
-            .split("/")
+            .as_str()
+            .split('/')
             .nth(3)
             .unwrap()
             .split('?')
@@ -68,12 +92,31 @@ async fn mock_avatar_client() -> AvatarClient {
     )
 }
 
+//uploaded files don't end up in the working directory
+pub fn test_storage() -> Arc<dyn Storage> {
+    Arc::new(LocalStorage::new(
+        std::env::temp_dir().join("zero2prod-test-uploads"),
+    ))
+}
+
 pub async fn start_app(pool: PgPool) -> String {
+    let avatar = Arc::new(mock_avatar_client().await);
+    start_app_with_avatar(pool, avatar).await
+}
+
+//start the app with another avatar provider
+pub async fn start_app_with_avatar(pool: PgPool, avatar: Arc<dyn AvatarProvider>) -> String {
     Lazy::force(&TRACING);
 
     let settings=Settings::get_configuration().unwrap();
     let mut app_state=settings.create_app_state().await.unwrap();
-    app_state.connection=pool;
+    //the server outlives the test, so it gets its own pool instead of the test pool.
+    //Idle connections are closed quickly to let sqlx drop the test database
+    app_state.connection = PgPoolOptions::new()
+        .max_connections(5)
+        .idle_timeout(std::time::Duration::from_millis(500))
+        .connect_lazy_with(pool.connect_options().clone());
+    app_state.storage = test_storage();
     //run migrations for mock database
     sqlx::migrate!("./migrations")
         .run(&app_state.connection)
@@ -86,8 +129,91 @@ pub async fn start_app(pool: PgPool) -> String {
     let port = listener.local_addr().unwrap().port();
 
     println!("Server started at: 127.0.0.1:{}", port);
-    let avatar = mock_avatar_client().await;
-
     let _s = tokio::spawn(run_app(listener, app_state, avatar).expect("Error bind server"));
     format!("http://127.0.0.1:{}", port)
 }
+
+//register a new user with fake data, returns the data and user's id
+pub async fn register_user(address: &str) -> (FakeRegisterUser, uuid::Uuid) {
+    let new_user: FakeRegisterUser = Faker.fake();
+    let response = send_post_request(
+        &reqwest::Client::new(),
+        &new_user,
+        format!("{}/auth/signup", address),
+    )
+    .await
+    .expect("Can not register user");
+    assert!(response.status().is_success());
+
+    let user = response.json::<User>().await.expect("Invalid user data");
+    (new_user, user.id)
+}
+
+//log in the user, returns a client which sends the access token with every request
+pub async fn log_in(address: &str, user: &FakeRegisterUser) -> reqwest::Client {
+    log_in_with(address, user, reqwest::Client::builder()).await
+}
+
+//log in the user, the client is built with the builder's settings
+pub async fn log_in_with(
+    address: &str,
+    user: &FakeRegisterUser,
+    builder: reqwest::ClientBuilder,
+) -> reqwest::Client {
+    let login_data = serde_json::json!({
+        "email": user.email,
+        "password": user.password
+    });
+    let response = send_post_request(
+        &reqwest::Client::new(),
+        &login_data,
+        format!("{}/auth/login", address),
+    )
+    .await
+    .expect("Can not log in");
+    assert!(response.status().is_success());
+
+    let tokens = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Invalid login response");
+    let cookie = format!("access_token={}", tokens["access"].as_str().unwrap());
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(reqwest::header::COOKIE, cookie.parse().unwrap());
+    builder
+        .default_headers(headers)
+        .build()
+        .unwrap()
+}
+
+//new users can't change students, the client logs in as a teacher
+pub async fn authorized_client(address: &str, pool: &PgPool) -> reqwest::Client {
+    client_with_role(address, pool, "teacher").await
+}
+
+//register a user with the role and log in
+pub async fn client_with_role(address: &str, pool: &PgPool, role: &str) -> reqwest::Client {
+    client_with_role_and_id(address, pool, role).await.0
+}
+
+//register a user with the role and log in, returns the client and user's id
+pub async fn client_with_role_and_id(
+    address: &str,
+    pool: &PgPool,
+    role: &str,
+) -> (reqwest::Client, uuid::Uuid) {
+    let (user, user_id) = register_user(address).await;
+    set_role(pool, user_id, role).await;
+    (log_in(address, &user).await, user_id)
+}
+
+//change the user's role directly in the database
+pub async fn set_role(pool: &PgPool, user_id: uuid::Uuid, role: &str) {
+    sqlx::query("update users set role = $1::user_role where id = $2")
+        .bind(role)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .expect("Can not change user's role");
+}
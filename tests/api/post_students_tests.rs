@@ -1,18 +1,39 @@
-use fake::faker::{internet::en::SafeEmail, lorem::en::Words, name::en::Name};
-use reqwest::Response;
+use fake::faker::{
+    internet::en::SafeEmail,
+    lorem::en::Words,
+    name::en::{FirstName, LastName},
+};
+use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
 use zero2prod::schemas::FullStudent;
 
-use crate::start_app;
+use crate::{authorized_client, start_app};
 
 use fake::{Dummy, Fake, Faker};
+
+//generates names accepted by the full name validation (two words, at least 4 letters each)
+pub struct ValidFullName;
+
+impl Dummy<ValidFullName> for String {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(_: &ValidFullName, rng: &mut R) -> String {
+        let valid = |s: &String| s.len() >= 4 && s.chars().all(|c| c.is_ascii_alphabetic());
+        loop {
+            let first: String = FirstName().fake_with_rng(rng);
+            let last: String = LastName().fake_with_rng(rng);
+            if valid(&first) && valid(&last) {
+                return format!("{} {}", first, last);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Dummy)]
 pub struct FakeStudent {
     pub id:uuid::Uuid,
     #[serde(rename = "fullName")]
-    #[dummy(faker = "Name()")]
+    #[dummy(faker = "ValidFullName")]
     pub full_name: String,
     #[dummy(faker = "SafeEmail()")]
     pub email: String,
@@ -33,21 +54,21 @@ pub struct FakeEditStudent {
 }
 
 pub async fn send_post_request<T: Serialize>(
+    client: &Client,
     new_user: &T,
     address: String,
 ) -> Result<Response, reqwest::Error> {
-    let client = reqwest::Client::new();
-
     client.post(address).json(new_user).send().await
 }
 
 #[sqlx::test]
 async fn post_student_check(pool: PgPool) -> Result<(), reqwest::Error> {
-    let address = start_app(pool).await;
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
 
     let new_student: FakeStudent = Faker.fake();
     let post_students_address = format!("{}/students", address);
-    let response = send_post_request(&new_student, post_students_address).await?;
+    let response = send_post_request(&client, &new_student, post_students_address).await?;
 
     assert!(response.status().is_success());
     let res_data = response.json::<FullStudent>().await?;
@@ -63,7 +84,8 @@ async fn post_student_check(pool: PgPool) -> Result<(), reqwest::Error> {
 #[should_panic]
 #[sqlx::test]
 async fn post_student_check_panic_validation(pool: PgPool) {
-    let address = start_app(pool).await;
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
 
     //invalid email, age and course
     let new_student: FakeStudent = FakeStudent {
@@ -74,7 +96,7 @@ async fn post_student_check_panic_validation(pool: PgPool) {
         courses: vec!["123course".to_owned()],
     };
     let post_student_address = format!("{}/students", address);
-    let response = send_post_request(&new_student, post_student_address)
+    let response = send_post_request(&client, &new_student, post_student_address)
         .await
         .expect("Send request error");
 
@@ -91,13 +113,29 @@ async fn post_student_check_panic_validation(pool: PgPool) {
     assert_eq!(res_data.courses, new_student.courses);
 }
 
+#[sqlx::test]
+async fn post_student_invalid_course_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+
+    //the whole course name must match, not only its beginning
+    let mut new_student: FakeStudent = Faker.fake();
+    new_student.courses = vec!["ab'),('x".to_owned()];
+    let response =
+        send_post_request(&client, &new_student, format!("{}/students", address)).await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    Ok(())
+}
+
 #[sqlx::test]
 async fn change_student(pool: PgPool) -> Result<(), reqwest::Error> {
-    let address = start_app(pool).await;
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
 
     let new_student: FakeStudent = Faker.fake();
     let post_student_address = format!("{}/students", &address);
-    let response = send_post_request(&new_student, post_student_address).await?;
+    let response = send_post_request(&client, &new_student, post_student_address).await?;
 
     assert!(response.status().is_success());
     let res_data = response.json::<FullStudent>().await?;
@@ -110,7 +148,12 @@ async fn change_student(pool: PgPool) -> Result<(), reqwest::Error> {
     let new_student_data: FakeEditStudent = Faker.fake();
 
     let change_student_address = format!("{}/students/change/{}", &address, res_data.id);
-    let response = send_post_request(&new_student_data, change_student_address).await?;
+    let response = client
+        .post(change_student_address)
+        .header("If-Match", format!("\"{}\"", res_data.version))
+        .json(&new_student_data)
+        .send()
+        .await?;
 
     assert!(response.status().is_success());
 
@@ -123,3 +166,111 @@ async fn change_student(pool: PgPool) -> Result<(), reqwest::Error> {
 
     Ok(())
 }
+
+#[sqlx::test]
+async fn patch_student(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+
+    let new_student: FakeStudent = Faker.fake();
+    let post_student_address = format!("{}/students", &address);
+    let response = send_post_request(&client, &new_student, post_student_address).await?;
+
+    assert!(response.status().is_success());
+    let res_data = response.json::<FullStudent>().await?;
+
+    //change only the name and one course
+    let new_name: String = ValidFullName.fake();
+    let removed = res_data.courses[0].clone();
+    let patch_data = serde_json::json!({
+        "fullName": new_name,
+        "addCourses": ["Physics"],
+        "removeCourses": [removed],
+    });
+
+    let response = client
+        .patch(format!("{}/students/{}", &address, res_data.id))
+        .header("If-Match", format!("\"{}\"", res_data.version))
+        .json(&patch_data)
+        .send()
+        .await?;
+
+    assert!(response.status().is_success());
+    let patched = response.json::<FullStudent>().await?;
+
+    assert_eq!(patched.full_name, new_name);
+    assert_eq!(patched.email, res_data.email);
+    assert_eq!(patched.age, res_data.age);
+    assert!(patched.courses.contains(&"Physics".to_string()));
+    assert!(!patched.courses.contains(&removed) || removed == "Physics");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn change_student_version_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+
+    let new_student: FakeStudent = Faker.fake();
+    let post_student_address = format!("{}/students", &address);
+    let response = send_post_request(&client, &new_student, post_student_address).await?;
+    assert!(response.status().is_success());
+    let res_data = response.json::<FullStudent>().await?;
+
+    let response = client
+        .get(format!("{}/students/{}", &address, res_data.id))
+        .send()
+        .await?;
+    let etag = response.headers().get("etag").unwrap().to_str().unwrap().to_owned();
+    assert_eq!(etag, format!("\"{}\"", res_data.version));
+
+    let student_address = format!("{}/students/{}", &address, res_data.id);
+    //If-Match is required
+    let response = client
+        .patch(&student_address)
+        .json(&serde_json::json!({"age": 30}))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 428);
+
+    //first update wins
+    let response = client
+        .patch(&student_address)
+        .header("If-Match", &etag)
+        .json(&serde_json::json!({"age": 30}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    assert_ne!(response.headers().get("etag").unwrap(), etag.as_str());
+
+    //second update with the old ETag is rejected
+    let response = client
+        .patch(&student_address)
+        .header("If-Match", &etag)
+        .json(&serde_json::json!({"age": 40}))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 412);
+
+    //weak ETags never match
+    let current = format!("\"{}\"", res_data.version + 1);
+    let response = client
+        .patch(&student_address)
+        .header("If-Match", format!("W/{}", current))
+        .json(&serde_json::json!({"age": 40}))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 412);
+
+    //any of the listed ETags can match
+    let response = client
+        .patch(&student_address)
+        .header("If-Match", format!("{}, {}", etag, current))
+        .json(&serde_json::json!({"age": 40}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    Ok(())
+}
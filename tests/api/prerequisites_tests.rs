@@ -0,0 +1,135 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{FullStudent, Prerequisite, UnmetPrerequisite};
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn course_prerequisites_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let teacher = client_with_role(&address, &pool, "teacher").await;
+    let prerequisites_uri = format!("{}/courses/Physics/prerequisites", address);
+    let prerequisites = serde_json::json!({
+        "prerequisites": [{"courseName": "Math", "minGrade": 75.0}]
+    });
+
+    //only admins can change prerequisites
+    let response = teacher
+        .put(prerequisites_uri.clone())
+        .json(&prerequisites)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = admin
+        .put(prerequisites_uri.clone())
+        .json(&serde_json::json!({
+            "prerequisites": [{"courseName": "Math", "minGrade": 120.0}]
+        }))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    //duplicated, own and cyclic prerequisites are rejected
+    let invalid = [
+        serde_json::json!({"prerequisites": [
+            {"courseName": "Math", "minGrade": 75.0},
+            {"courseName": "Math", "minGrade": 50.0}
+        ]}),
+        serde_json::json!({"prerequisites": [{"courseName": "Physics", "minGrade": 75.0}]}),
+    ];
+    for body in invalid {
+        let response = admin
+            .put(prerequisites_uri.clone())
+            .json(&body)
+            .send()
+            .await?;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    let response = admin
+        .put(prerequisites_uri.clone())
+        .json(&prerequisites)
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let response = admin
+        .put(format!("{}/courses/Math/prerequisites", address))
+        .json(&serde_json::json!({
+            "prerequisites": [{"courseName": "Physics", "minGrade": 50.0}]
+        }))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let saved = teacher
+        .get(prerequisites_uri.clone())
+        .send()
+        .await?
+        .json::<Vec<Prerequisite>>()
+        .await?;
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].course_name, "Math");
+    assert_eq!(saved[0].min_grade, 75.0);
+
+    //no Math grades yet
+    let mut student: FakeStudent = Faker.fake();
+    student.courses = vec!["Math".into(), "Physics".into()];
+    let response = send_post_request(&admin, &student, format!("{}/students", address)).await?;
+    assert_eq!(response.status().as_u16(), 400);
+    let error = response.json::<serde_json::Value>().await?;
+    let unmet: Vec<UnmetPrerequisite> =
+        serde_json::from_str(error["cause"].as_str().unwrap()).unwrap();
+    assert_eq!(unmet.len(), 1);
+    assert_eq!(unmet[0].course_name, "Physics");
+    assert_eq!(unmet[0].prerequisite, "Math");
+    assert_eq!(unmet[0].grade, None);
+
+    student.courses = vec!["Math".into()];
+    let student = send_post_request(&admin, &student, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    let grades_uri = format!("{}/students/{}/grades", address, student.id);
+
+    //60% is below the minimum grade
+    let grade = serde_json::json!({"courseName": "Math", "assessment": "Quiz", "score": 60.0, "maxScore": 100.0});
+    let response = send_post_request(&admin, &grade, grades_uri.clone()).await?;
+    assert!(response.status().is_success());
+
+    let patch_uri = format!("{}/students/{}", address, student.id);
+    let response = admin
+        .patch(patch_uri.clone())
+        .header("If-Match", "*")
+        .json(&serde_json::json!({"addCourses": ["Physics"]}))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+    let error = response.json::<serde_json::Value>().await?;
+    let unmet: Vec<UnmetPrerequisite> =
+        serde_json::from_str(error["cause"].as_str().unwrap()).unwrap();
+    assert_eq!(unmet[0].grade, Some(60.0));
+
+    //(0.6 + 1.0)/2 = 80%
+    let grade = serde_json::json!({"courseName": "Math", "assessment": "Exam", "score": 100.0, "maxScore": 100.0});
+    let response = send_post_request(&admin, &grade, grades_uri).await?;
+    assert!(response.status().is_success());
+
+    let response = admin
+        .patch(patch_uri)
+        .header("If-Match", "*")
+        .json(&serde_json::json!({"addCourses": ["Physics"]}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let student = response.json::<FullStudent>().await?;
+    assert!(student.courses.contains(&"Physics".to_string()));
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::io::Read;
+
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{ClassGroup, FullStudent, ReportCard};
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn report_cards_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let teacher = client_with_role(&address, &pool, "teacher").await;
+
+    let mut students = Vec::new();
+    //names aren't only in Latin letters
+    for name in ["Иван Петров", "Zoey Clark"] {
+        let mut student: FakeStudent = Faker.fake();
+        student.full_name = name.into();
+        student.courses = vec!["Chemistry".into()];
+        let student = send_post_request(&admin, &student, format!("{}/students", address))
+            .await?
+            .json::<FullStudent>()
+            .await?;
+        students.push(student.id);
+    }
+
+    let group = send_post_request(
+        &admin,
+        &serde_json::json!({"name": "11-A", "academicYear": "2023/2024"}),
+        format!("{}/groups", address),
+    )
+    .await?
+    .json::<ClassGroup>()
+    .await?;
+    let response = send_post_request(
+        &admin,
+        &serde_json::json!({"students": students}),
+        format!("{}/groups/{}/students", address, group.id),
+    )
+    .await?;
+    assert!(response.status().is_success());
+
+    let response = send_post_request(
+        &teacher,
+        &serde_json::json!({"courseName": "Chemistry", "assessment": "Exam", "score": 45.0, "maxScore": 50.0}),
+        format!("{}/students/{}/grades", address, students[0]),
+    )
+    .await?;
+    assert!(response.status().is_success());
+
+    for (date, status) in [("2023-04-24", "present"), ("2023-04-25", "absent")] {
+        let response = send_post_request(
+            &teacher,
+            &serde_json::json!({"date": date, "records": [{"studentId": students[0], "status": status}]}),
+            format!("{}/courses/Chemistry/attendance", address),
+        )
+        .await?;
+        assert!(response.status().is_success());
+    }
+
+    let comments_uri = format!("{}/students/{}/report-card/comments", address, students[0]);
+    let response = teacher
+        .put(comments_uri.clone())
+        .json(&serde_json::json!({"courseName": "Chemistry", "comment": ""}))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    //the second comment of the course replaces the first one
+    for comment in ["Good work", "Excellent work in the lab"] {
+        let response = teacher
+            .put(comments_uri.clone())
+            .json(&serde_json::json!({"courseName": "Chemistry", "comment": comment}))
+            .send()
+            .await?;
+        assert!(response.status().is_success());
+    }
+
+    let card = admin
+        .get(format!("{}/students/{}/report-card", address, students[0]))
+        .send()
+        .await?
+        .json::<ReportCard>()
+        .await?;
+    assert_eq!(card.student.id, students[0]);
+    assert_eq!(card.grades.courses[0].average, 90.0);
+    assert_eq!(card.attendance_rate, Some(50.0));
+    assert_eq!(card.comments.len(), 1);
+    assert_eq!(card.comments[0].comment, "Excellent work in the lab");
+
+    let response = admin
+        .get(format!(
+            "{}/students/{}/report-card.pdf",
+            address, students[0]
+        ))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/pdf"
+    );
+    let pdf = response.bytes().await?;
+    assert!(pdf.starts_with(b"%PDF"));
+    assert!(pdf.windows(10).any(|w| w == b"DejaVuSans"));
+
+    let response = admin
+        .get(format!("{}/groups/{}/report-cards.zip", address, group.id))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(response.bytes().await?)).unwrap();
+    assert_eq!(archive.len(), 2);
+    for i in 0..archive.len() {
+        let mut pdf = Vec::new();
+        archive.by_index(i).unwrap().read_to_end(&mut pdf).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use fake::{Fake, Faker};
+use reqwest::multipart::{Form, Part};
+use sqlx::PgPool;
+use std::io::Cursor;
+use time::{Duration, OffsetDateTime};
+use zero2prod::{
+    app::{avatar_key, PurgeSettings},
+    db::db_purge_deleted_students,
+    schemas::{AuditContext, FullStudent, StudentAvatar},
+};
+
+use crate::{
+    authorized_client, client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app, test_storage,
+};
+
+#[sqlx::test]
+async fn soft_delete_and_restore_student(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let new_student: FakeStudent = Faker.fake();
+    let response =
+        send_post_request(&client, &new_student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let student = response.json::<FullStudent>().await?;
+
+    let response = client
+        .delete(format!("{}/delete/{}", address, student.id))
+        .header("If-Match", "*")
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    //deleted student is hidden
+    let students = client
+        .get(format!("{}/students", address))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?;
+    assert!(students.is_empty());
+    let response = client
+        .get(format!("{}/students/{}", address, student.id))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    //only admins can see deleted students
+    let response = client
+        .get(format!("{}/students?include_deleted=true", address))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let students = admin
+        .get(format!("{}/students?include_deleted=true", address))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?;
+    assert_eq!(students.len(), 1);
+    assert!(students[0].deleted_at.is_some());
+
+    //only admins can restore students
+    let restore_address = format!("{}/students/{}/restore", address, student.id);
+    let response = client.post(&restore_address).send().await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = admin.post(&restore_address).send().await?;
+    assert!(response.status().is_success());
+    let restored = response.json::<FullStudent>().await?;
+    assert!(restored.deleted_at.is_none());
+    assert_eq!(restored.courses, student.courses);
+
+    let response = client
+        .get(format!("{}/students/{}", address, student.id))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn purge_deleted_student_files(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let new_student: FakeStudent = Faker.fake();
+    let student = send_post_request(&admin, &new_student, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+
+    let mut png = Vec::new();
+    image::DynamicImage::new_rgb8(100, 100)
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+    let form = Form::new().part(
+        "avatar",
+        Part::bytes(png).file_name("me.png").mime_str("image/png")?,
+    );
+    let avatar = admin
+        .put(format!("{}/students/{}/avatar", address, student.id))
+        .multipart(form)
+        .send()
+        .await?
+        .json::<StudentAvatar>()
+        .await?;
+
+    let response = admin
+        .delete(format!("{}/delete/{}", address, student.id))
+        .header("If-Match", "*")
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    //the thumbnails are kept until the student is purged
+    let storage = test_storage();
+    let keys: Vec<String> = avatar
+        .sizes
+        .iter()
+        .map(|size| avatar_key(student.id, &avatar.etag, *size))
+        .collect();
+    for key in keys.iter() {
+        assert!(storage.get(key).await.is_ok());
+    }
+
+    let purged = db_purge_deleted_students(
+        OffsetDateTime::now_utc() + Duration::minutes(1),
+        storage.as_ref(),
+        &AuditContext::system(),
+        &pool,
+    )
+    .await
+    .unwrap();
+    assert_eq!(purged, 1);
+    for key in keys.iter() {
+        assert!(storage.get(key).await.is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn purge_interval_check() {
+    //a zero interval would panic the purging job
+    let settings = serde_json::json!({"retention_days": 30, "interval_minutes": 0});
+    assert!(serde_json::from_value::<PurgeSettings>(settings).is_err());
+}
@@ -0,0 +1,90 @@
+use sqlx::PgPool;
+use zero2prod::schemas::SearchResult;
+
+use crate::{client_with_role, post_students_tests::send_post_request, start_app};
+
+#[sqlx::test]
+async fn search_students_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let parent = client_with_role(&address, &pool, "parent").await;
+    let search_uri = format!("{}/students/search", address);
+
+    for (name, email, course) in [
+        (
+            "Jonathan Smith",
+            "jonathan.smith@example.com",
+            "Mathematics",
+        ),
+        ("Maria Lopez", "maria&co@example.com", "Art"),
+    ] {
+        let student = serde_json::json!({
+            "fullName": name,
+            "email": email,
+            "age": 17,
+            "courses": [course]
+        });
+        let response = send_post_request(&admin, &student, format!("{}/students", address)).await?;
+        assert!(response.status().is_success());
+    }
+
+    let response = parent
+        .get(search_uri.clone())
+        .query(&[("q", "Maria")])
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = admin
+        .get(search_uri.clone())
+        .query(&[("q", "M")])
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    //typo in the name
+    let found = admin
+        .get(search_uri.clone())
+        .query(&[("q", "jonatan")])
+        .send()
+        .await?
+        .json::<Vec<SearchResult>>()
+        .await?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].full_name, "Jonathan Smith");
+    assert!(found[0].rank > 0.0);
+    assert_eq!(
+        found[0].highlights.full_name.as_deref(),
+        Some("<mark>Jonathan</mark> Smith")
+    );
+
+    //part of the email
+    let found = admin
+        .get(search_uri.clone())
+        .query(&[("q", "maria@exa")])
+        .send()
+        .await?
+        .json::<Vec<SearchResult>>()
+        .await?;
+    assert_eq!(found[0].full_name, "Maria Lopez");
+    assert_eq!(
+        found[0].highlights.email.as_deref(),
+        Some("<mark>maria</mark>&amp;co@<mark>example</mark>.com")
+    );
+
+    //course name with a typo
+    let found = admin
+        .get(search_uri)
+        .query(&[("q", "mathematcs")])
+        .send()
+        .await?
+        .json::<Vec<SearchResult>>()
+        .await?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].courses, vec!["Mathematics".to_string()]);
+    assert_eq!(
+        found[0].highlights.courses,
+        vec!["<mark>Mathematics</mark>"]
+    );
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use sqlx::PgPool;
+use zero2prod::schemas::{CourseEnrollment, Stats};
+
+use crate::{client_with_role, post_students_tests::send_post_request, start_app};
+
+#[sqlx::test]
+async fn stats_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let parent = client_with_role(&address, &pool, "parent").await;
+    let stats_uri = format!("{}/stats", address);
+
+    for (name, age, courses) in [
+        ("Anna Smith", 16, vec!["Math", "Art"]),
+        ("Bobby Brown", 17, vec!["Math"]),
+        ("Carl Jones", 22, vec!["Math", "Chess"]),
+    ] {
+        let student = serde_json::json!({
+            "fullName": name,
+            "email": "student@example.com",
+            "age": age,
+            "courses": courses
+        });
+        let response = send_post_request(&admin, &student, format!("{}/students", address)).await?;
+        assert!(response.status().is_success());
+    }
+
+    let response = parent.get(stats_uri.clone()).send().await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let stats = admin
+        .get(stats_uri.clone())
+        .send()
+        .await?
+        .json::<Stats>()
+        .await?;
+    assert_eq!(stats.total_students, 3);
+    assert_eq!(stats.registrations_per_month.len(), 1);
+    assert_eq!(stats.registrations_per_month[0].count, 3);
+    let ages: Vec<(i32, i32, i64)> = stats
+        .age_distribution
+        .iter()
+        .map(|g| (g.from, g.to, g.count))
+        .collect();
+    assert_eq!(ages, vec![(15, 19, 2), (20, 24, 1)]);
+    assert_eq!(stats.courses.len(), 3);
+    assert_eq!(
+        stats.most_popular,
+        vec![CourseEnrollment {
+            course_name: "Math".into(),
+            students: 3
+        }]
+    );
+    let least: Vec<&str> = stats
+        .least_popular
+        .iter()
+        .map(|c| c.course_name.as_str())
+        .collect();
+    assert_eq!(least, vec!["Art", "Chess"]);
+
+    //statistics are cached
+    let student = serde_json::json!({
+        "fullName": "Dora White",
+        "email": "student@example.com",
+        "age": 18,
+        "courses": ["Math"]
+    });
+    let response = send_post_request(&admin, &student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let cached = admin.get(stats_uri).send().await?.json::<Stats>().await?;
+    assert_eq!(cached.total_students, 3);
+    assert_eq!(cached.generated_at, stats.generated_at);
+    Ok(())
+}
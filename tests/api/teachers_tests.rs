@@ -0,0 +1,76 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{FullStudent, FullTeacher};
+
+use crate::{
+    authorized_client, client_with_role, client_with_role_and_id,
+    post_students_tests::{send_post_request, FakeStudent, ValidFullName},
+    start_app,
+};
+
+#[sqlx::test]
+async fn teacher_students_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let client = authorized_client(&address, &pool).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let (teacher_client, teacher_user_id) =
+        client_with_role_and_id(&address, &pool, "teacher").await;
+
+    let new_teacher = serde_json::json!({
+        "fullName": ValidFullName.fake::<String>(),
+        "email": "teacher@school.com",
+        "subjects": ["Math"],
+        "userId": teacher_user_id,
+    });
+
+    //only admins can manage teachers
+    let response =
+        send_post_request(&client, &new_teacher, format!("{}/teachers", address)).await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = send_post_request(&admin, &new_teacher, format!("{}/teachers", address)).await?;
+    assert!(response.status().is_success());
+    let teacher = response.json::<FullTeacher>().await?;
+
+    let response = send_post_request(
+        &admin,
+        &serde_json::json!({"courses": ["Algebra"]}),
+        format!("{}/teachers/{}/courses", address, teacher.id),
+    )
+    .await?;
+    assert!(response.status().is_success());
+    let teacher = response.json::<FullTeacher>().await?;
+    assert_eq!(teacher.courses, vec!["Algebra".to_string()]);
+
+    //one student in the teacher's course, another one isn't
+    let mut first_student: FakeStudent = Faker.fake();
+    first_student.courses = vec!["Algebra".into(), "Biology".into()];
+    let response =
+        send_post_request(&client, &first_student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+    let first_student = response.json::<FullStudent>().await?;
+
+    let mut second_student: FakeStudent = Faker.fake();
+    second_student.courses = vec!["Biology".into()];
+    let response =
+        send_post_request(&client, &second_student, format!("{}/students", address)).await?;
+    assert!(response.status().is_success());
+
+    let students = teacher_client
+        .get(format!("{}/teachers/me/students", address))
+        .send()
+        .await?
+        .json::<Vec<FullStudent>>()
+        .await?;
+    assert_eq!(students.len(), 1);
+    assert_eq!(students[0].id, first_student.id);
+
+    //user without a teacher profile
+    let response = client
+        .get(format!("{}/teachers/me/students", address))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    Ok(())
+}
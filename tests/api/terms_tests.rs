@@ -0,0 +1,124 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::{
+    db::db_sync_current_term,
+    schemas::{FullAcademicYear, FullStudent, Term, TermCourses},
+};
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn term_enrollments_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let year = send_post_request(
+        &admin,
+        &serde_json::json!({"name": "2023/2024", "startsOn": "2023-09-01", "endsOn": "2024-06-30"}),
+        format!("{}/academic-years", address),
+    )
+    .await?
+    .json::<FullAcademicYear>()
+    .await?;
+
+    let terms_uri = format!("{}/academic-years/{}/terms", address, year.id);
+    for (code, starts_on, ends_on) in [
+        ("2023-fall", "2023-09-01", "2023-12-31"),
+        ("2024-spring", "2024-01-15", "2024-06-30"),
+    ] {
+        let term = serde_json::json!({"code": code, "name": code, "startsOn": starts_on, "endsOn": ends_on});
+        let response = send_post_request(&admin, &term, terms_uri.clone()).await?;
+        assert!(response.status().is_success());
+    }
+
+    //term outside of the academic year
+    let term = serde_json::json!({"code": "2024-summer", "name": "Summer", "startsOn": "2024-07-01", "endsOn": "2024-08-31"});
+    let response = send_post_request(&admin, &term, terms_uri.clone()).await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    //enrolled before any term is current
+    let mut early: FakeStudent = Faker.fake();
+    early.courses = vec!["Chess".into()];
+    let early = send_post_request(&admin, &early, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+
+    assert!(db_sync_current_term(Some("2023-fall"), &pool)
+        .await
+        .unwrap());
+
+    //the enrollment moves to the first current term
+    let courses = admin
+        .get(format!("{}/students/{}/courses", address, early.id))
+        .send()
+        .await?
+        .json::<TermCourses>()
+        .await?;
+    assert_eq!(courses.term.as_deref(), Some("2023-fall"));
+    assert_eq!(courses.courses, vec!["Chess".to_string()]);
+
+    let current = admin
+        .get(format!("{}/terms/current", address))
+        .send()
+        .await?
+        .json::<Term>()
+        .await?;
+    assert_eq!(current.code, "2023-fall");
+
+    let mut student: FakeStudent = Faker.fake();
+    student.courses = vec!["Math".into(), "Art".into()];
+    let student = send_post_request(&admin, &student, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+
+    //new term starts without enrollments
+    assert!(db_sync_current_term(Some("2024-spring"), &pool)
+        .await
+        .unwrap());
+    let student = admin
+        .patch(format!("{}/students/{}", address, student.id))
+        .header("If-Match", "*")
+        .json(&serde_json::json!({"addCourses": ["Physics"]}))
+        .send()
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    assert_eq!(student.courses, vec!["Physics".to_string()]);
+
+    let past = admin
+        .get(format!(
+            "{}/students/{}/courses?term=2023-fall",
+            address, student.id
+        ))
+        .send()
+        .await?
+        .json::<TermCourses>()
+        .await?;
+    assert_eq!(past.courses, vec!["Art".to_string(), "Math".to_string()]);
+
+    let current = admin
+        .get(format!("{}/students/{}/courses", address, student.id))
+        .send()
+        .await?
+        .json::<TermCourses>()
+        .await?;
+    assert_eq!(current.term.as_deref(), Some("2024-spring"));
+    assert_eq!(current.courses, vec!["Physics".to_string()]);
+
+    let response = admin
+        .get(format!(
+            "{}/students/{}/courses?term=1999-fall",
+            address, student.id
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use fake::{Fake, Faker};
+use sqlx::PgPool;
+use zero2prod::schemas::{FullStudent, ScheduleSlot};
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    start_app,
+};
+
+#[sqlx::test]
+async fn schedule_conflicts_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let uri = format!("{}/schedule", address);
+
+    let mut student: FakeStudent = Faker.fake();
+    student.courses = vec!["Math".into(), "Art".into()];
+    let student = send_post_request(&admin, &student, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+
+    let slot = serde_json::json!({"courseName": "Math", "dayOfWeek": 1, "startsAt": "09:00", "endsAt": "09:45", "room": "101"});
+    let response = send_post_request(&admin, &slot, uri.clone()).await?;
+    assert!(response.status().is_success());
+    let math = response.json::<ScheduleSlot>().await?;
+
+    //same room at the overlapping time
+    let slot = serde_json::json!({"courseName": "Physics", "dayOfWeek": 1, "startsAt": "09:30", "endsAt": "10:15", "room": "101"});
+    let response = send_post_request(&admin, &slot, uri.clone()).await?;
+    assert_eq!(response.status().as_u16(), 409);
+
+    //the student has Math at this time
+    let slot = serde_json::json!({"courseName": "Art", "dayOfWeek": 1, "startsAt": "09:30", "endsAt": "10:15", "room": "202"});
+    let response = send_post_request(&admin, &slot, uri.clone()).await?;
+    assert_eq!(response.status().as_u16(), 409);
+
+    //right after Math is fine
+    let slot = serde_json::json!({"courseName": "Art", "dayOfWeek": 1, "startsAt": "09:45", "endsAt": "10:30", "room": "202"});
+    let response = send_post_request(&admin, &slot, uri.clone()).await?;
+    assert!(response.status().is_success());
+
+    //concurrent bookings of the same room get one slot
+    let slots = ["Biology", "History", "Music"].map(|course| {
+        serde_json::json!({"courseName": course, "dayOfWeek": 2, "startsAt": "11:00", "endsAt": "11:45", "room": "303"})
+    });
+    let requests = slots
+        .iter()
+        .map(|slot| send_post_request(&admin, slot, uri.clone()));
+    let mut created = 0;
+    for response in futures_util::future::join_all(requests).await {
+        match response?.status().as_u16() {
+            409 => {}
+            status => {
+                assert!((200..300).contains(&status));
+                created += 1;
+            }
+        }
+    }
+    assert_eq!(created, 1);
+
+    //the slot must start before the end
+    let response = admin
+        .patch(format!("{}/schedule/{}", address, math.id))
+        .json(&serde_json::json!({"endsAt": "08:00"}))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let timetable = admin
+        .get(format!("{}/students/{}/timetable", address, student.id))
+        .send()
+        .await?
+        .json::<Vec<ScheduleSlot>>()
+        .await?;
+    let courses: Vec<&str> = timetable.iter().map(|s| s.course_name.as_str()).collect();
+    assert_eq!(courses, vec!["Math", "Art"]);
+
+    let response = admin
+        .get(format!("{}/students/{}/timetable.ics", address, student.id))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let ics = response.text().await?;
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+    assert!(ics.contains("SUMMARY:Math"));
+
+    //teachers' timetables aren't shown to parents and students
+    let parent = client_with_role(&address, &pool, "parent").await;
+    let response = parent
+        .get(format!(
+            "{}/teachers/{}/timetable",
+            address,
+            uuid::Uuid::new_v4()
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use fake::{Fake, Faker};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::PgPool;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+use zero2prod::{
+    app::{deliver_queued_webhooks, Settings},
+    schemas::{FullStudent, WebhookDelivery},
+};
+
+use crate::{
+    client_with_role,
+    post_students_tests::{send_post_request, FakeStudent},
+    register_user, start_app,
+};
+
+fn header(request: &Request, name: &str) -> String {
+    request
+        .headers
+        .iter()
+        .find(|(n, _)| n.as_str().eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.last().as_str().to_string())
+        .unwrap_or_default()
+}
+
+#[sqlx::test]
+async fn webhook_delivery_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+    let teacher = client_with_role(&address, &pool, "teacher").await;
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hooks"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let secret = "library-secret-key";
+    let new_webhook = serde_json::json!({
+        "url": format!("{}/hooks", mock_server.uri()),
+        "events": ["student.created", "student.deleted"],
+        "secret": secret,
+    });
+
+    //only admins can manage webhooks
+    let response =
+        send_post_request(&teacher, &new_webhook, format!("{}/webhooks", address)).await?;
+    assert_eq!(response.status().as_u16(), 403);
+
+    //invalid url, no events and unknown events are rejected
+    let invalid_webhooks = [
+        serde_json::json!({"url": "not a url", "events": ["student.created"]}),
+        serde_json::json!({"url": mock_server.uri(), "events": []}),
+        serde_json::json!({"url": mock_server.uri(), "events": ["student.graded"]}),
+    ];
+    for webhook in invalid_webhooks.iter() {
+        let response = send_post_request(&admin, webhook, format!("{}/webhooks", address)).await?;
+        assert!(response.status().is_client_error());
+    }
+
+    let response = send_post_request(&admin, &new_webhook, format!("{}/webhooks", address)).await?;
+    assert!(response.status().is_success());
+    let webhook = response.json::<serde_json::Value>().await?;
+    assert_eq!(webhook["secret"], secret);
+    let webhook_id = webhook["id"].as_str().unwrap().to_string();
+
+    //the secret isn't shown again
+    let webhook = admin
+        .get(format!("{}/webhooks/{}", address, webhook_id))
+        .send()
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+    assert!(webhook.get("secret").is_none());
+
+    //created and deleted are delivered, updates and new users aren't subscribed
+    let student: FakeStudent = Faker.fake();
+    let student = send_post_request(&admin, &student, format!("{}/students", address))
+        .await?
+        .json::<FullStudent>()
+        .await?;
+    let response = admin
+        .patch(format!("{}/students/{}", address, student.id))
+        .header("If-Match", "*")
+        .json(&serde_json::json!({"age": 30}))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    register_user(&address).await;
+    let response = admin
+        .delete(format!("{}/delete/{}", address, student.id))
+        .header("If-Match", "*")
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let settings = Settings::get_configuration().unwrap().webhooks;
+    let client = reqwest::Client::new();
+    let delivered = deliver_queued_webhooks(&pool, &client, &settings)
+        .await
+        .unwrap();
+    assert_eq!(delivered, 2);
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let mut events = Vec::new();
+    for request in requests.iter() {
+        //the signature is the HMAC of `<timestamp>.<body>`
+        let timestamp = header(request, "X-Webhook-Timestamp");
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(&request.body);
+        assert_eq!(
+            header(request, "X-Webhook-Signature"),
+            format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+        );
+
+        let payload: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(payload["event"], header(request, "X-Webhook-Event"));
+        assert_eq!(payload["data"]["id"], student.id.to_string());
+        events.push(payload["event"].as_str().unwrap().to_string());
+    }
+    events.sort();
+    assert_eq!(events, ["student.created", "student.deleted"]);
+
+    let deliveries = admin
+        .get(format!(
+            "{}/webhooks/{}/deliveries?status=delivered",
+            address, webhook_id
+        ))
+        .send()
+        .await?
+        .json::<Vec<WebhookDelivery>>()
+        .await?;
+    assert_eq!(deliveries.len(), 2);
+    assert!(deliveries
+        .iter()
+        .all(|d| d.attempts == 1 && d.last_status == Some(200)));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn webhook_retries_check(pool: PgPool) -> Result<(), reqwest::Error> {
+    let address = start_app(pool.clone()).await;
+    let admin = client_with_role(&address, &pool, "admin").await;
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&mock_server)
+        .await;
+
+    let response = send_post_request(
+        &admin,
+        &serde_json::json!({"url": mock_server.uri(), "events": ["user.registered"]}),
+        format!("{}/webhooks", address),
+    )
+    .await?;
+    assert!(response.status().is_success());
+    let webhook_id = response.json::<serde_json::Value>().await?["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let deliveries_uri = format!("{}/webhooks/{}/deliveries", address, webhook_id);
+
+    register_user(&address).await;
+
+    let mut settings = Settings::get_configuration().unwrap().webhooks;
+    settings.outbox.max_attempts = 2;
+    settings.outbox.backoff_seconds = 60.0;
+    let client = reqwest::Client::new();
+
+    //the failed delivery waits for the backoff
+    for _ in 0..2 {
+        let delivered = deliver_queued_webhooks(&pool, &client, &settings)
+            .await
+            .unwrap();
+        assert_eq!(delivered, 0);
+    }
+    let deliveries = admin
+        .get(format!("{}?status=pending", deliveries_uri))
+        .send()
+        .await?
+        .json::<Vec<WebhookDelivery>>()
+        .await?;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].event, "user.registered");
+    assert_eq!(deliveries[0].attempts, 1);
+    assert_eq!(deliveries[0].last_status, Some(503));
+    let delay = deliveries[0].next_attempt_at - time::OffsetDateTime::now_utc();
+    assert!(delay > time::Duration::seconds(50) && delay <= time::Duration::seconds(60));
+
+    //no waiting for the test, all attempts are used after the next one
+    sqlx::query("update webhook_deliveries set next_attempt_at = now()")
+        .execute(&pool)
+        .await
+        .unwrap();
+    deliver_queued_webhooks(&pool, &client, &settings)
+        .await
+        .unwrap();
+
+    let deliveries = admin
+        .get(format!("{}?status=failed", deliveries_uri))
+        .send()
+        .await?
+        .json::<Vec<WebhookDelivery>>()
+        .await?;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].attempts, 2);
+    assert!(deliveries[0].failed_at.is_some());
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
+
+    //failed deliveries aren't sent anymore
+    sqlx::query("update webhook_deliveries set next_attempt_at = now()")
+        .execute(&pool)
+        .await
+        .unwrap();
+    deliver_queued_webhooks(&pool, &client, &settings)
+        .await
+        .unwrap();
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
+
+    Ok(())
+}